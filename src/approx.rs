@@ -0,0 +1,196 @@
+//! Helpers for comparing calculator results with a tolerance.
+//!
+//! These are intended for tests of formulas, where results are rarely exactly equal to the expected value.
+//! The [`assert_evaluates_to!`](crate::assert_evaluates_to) macro evaluates an input and panics
+//! with a descriptive message if the result is not close to the expected value.
+//!
+//! ```
+//! use expressive_calc::{assert_evaluates_to, Calculator};
+//!
+//! let mut calculator = Calculator::new();
+//! assert_evaluates_to!(calculator, "sin(pi)", 0.0, tol = 1e-12);
+//! assert_evaluates_to!(mut calculator, "x = 0.1 + 0.2", 0.3);
+//! assert_evaluates_to!(calculator, "x * 10", 3.0);
+//! ```
+
+use crate::{CalcError, Calculator};
+
+/// The tolerance used by [`assert_evaluates_to!`](crate::assert_evaluates_to) if none is given.
+pub const DEFAULT_TOLERANCE: f64 = 1e-12;
+
+/// Check if two values are close to each other.
+///
+/// The values are close if their difference is within `tol` absolutely,
+/// or within `tol` relative to the magnitude of `expected`.
+/// Infinities are only close to the same infinity, and NaN is only close to NaN.
+pub fn is_close(actual: f64, expected: f64, tol: f64) -> bool {
+    if actual.is_nan() || expected.is_nan() {
+        return actual.is_nan() && expected.is_nan();
+    }
+    if actual.is_infinite() || expected.is_infinite() {
+        return actual == expected;
+    }
+    (actual - expected).abs() <= tol.max(tol * expected.abs())
+}
+
+/// Check that the result of evaluating an input is close to the expected value.
+///
+/// # Errors
+///
+/// Returns a message describing the input, expected value, actual value, and difference
+/// if the result is not close to the expected value, or describing the error if evaluation failed.
+pub fn check(
+    input: &str,
+    result: Result<f64, CalcError>,
+    expected: f64,
+    tol: f64,
+) -> Result<(), String> {
+    match result {
+        Ok(actual) if is_close(actual, expected, tol) => Ok(()),
+        Ok(actual) => Err(format!(
+            "`{}` evaluated to {}, expected {} (difference: {}, tolerance: {})",
+            input,
+            actual,
+            expected,
+            (actual - expected).abs(),
+            tol
+        )),
+        Err(err) => Err(format!(
+            "`{}` failed to evaluate, expected {}: {}",
+            input, expected, err
+        )),
+    }
+}
+
+/// Check that an input evaluates to a value close to the expected value without storing state.
+///
+/// # Errors
+///
+/// Returns a message if the result is not close to the expected value. See [`check`].
+pub fn evaluates_to_within(
+    calculator: &Calculator,
+    input: &str,
+    expected: f64,
+    tol: f64,
+) -> Result<(), String> {
+    check(input, calculator.quick_evaluate(input), expected, tol)
+}
+
+/// Check that an input evaluates to a value close to the expected value, storing state.
+///
+/// # Errors
+///
+/// Returns a message if the result is not close to the expected value. See [`check`].
+pub fn evaluates_to_within_mut(
+    calculator: &mut Calculator,
+    input: &str,
+    expected: f64,
+    tol: f64,
+) -> Result<(), String> {
+    let result = calculator.evaluate(input).map(|(_, value)| value);
+    check(input, result, expected, tol)
+}
+
+/// Assert that an input evaluates to a value close to the expected value.
+///
+/// The input is evaluated with [`Calculator::quick_evaluate`], or with [`Calculator::evaluate`]
+/// if the calculator is preceded by `mut`.
+/// The tolerance defaults to [`DEFAULT_TOLERANCE`](crate::approx::DEFAULT_TOLERANCE) and can be
+/// given with `tol = ...`. See [`is_close`](crate::approx::is_close) for how values are compared.
+///
+/// # Panics
+///
+/// Panics with a message containing the input, expected value, actual value, and difference
+/// if the result is not close to the expected value, or if evaluation fails.
+///
+/// ```
+/// use expressive_calc::{assert_evaluates_to, Calculator};
+///
+/// let calculator = Calculator::new();
+/// assert_evaluates_to!(calculator, "sqrt(2) ^ 2", 2.0);
+/// assert_evaluates_to!(calculator, "pi", 3.14, tol = 0.01);
+/// ```
+#[macro_export]
+macro_rules! assert_evaluates_to {
+    (mut $calculator:expr, $input:expr, $expected:expr) => {
+        $crate::assert_evaluates_to!(
+            mut $calculator,
+            $input,
+            $expected,
+            tol = $crate::approx::DEFAULT_TOLERANCE
+        )
+    };
+    (mut $calculator:expr, $input:expr, $expected:expr, tol = $tol:expr) => {
+        if let Err(message) =
+            $crate::approx::evaluates_to_within_mut(&mut $calculator, $input, $expected, $tol)
+        {
+            panic!("{}", message);
+        }
+    };
+    ($calculator:expr, $input:expr, $expected:expr) => {
+        $crate::assert_evaluates_to!(
+            $calculator,
+            $input,
+            $expected,
+            tol = $crate::approx::DEFAULT_TOLERANCE
+        )
+    };
+    ($calculator:expr, $input:expr, $expected:expr, tol = $tol:expr) => {
+        if let Err(message) =
+            $crate::approx::evaluates_to_within(&$calculator, $input, $expected, $tol)
+        {
+            panic!("{}", message);
+        }
+    };
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_close() {
+        assert!(is_close(0.1 + 0.2, 0.3, 1e-12));
+        assert!(is_close(1e20 + 1e5, 1e20, 1e-12));
+        assert!(!is_close(1.0, 1.1, 1e-12));
+        assert!(is_close(f64::INFINITY, f64::INFINITY, 1e-12));
+        assert!(!is_close(f64::INFINITY, f64::NEG_INFINITY, 1e-12));
+        assert!(is_close(f64::NAN, f64::NAN, 1e-12));
+        assert!(!is_close(f64::NAN, 0.0, 1e-12));
+    }
+
+    #[test]
+    fn test_check_message() {
+        let message = check("1 + 1", Ok(2.0), 3.0, 1e-12).unwrap_err();
+        assert_eq!(
+            message,
+            "`1 + 1` evaluated to 2, expected 3 (difference: 1, tolerance: 0.000000000001)"
+        );
+    }
+
+    #[test]
+    fn test_check_error_message() {
+        let calculator = Calculator::new();
+        let message = evaluates_to_within(&calculator, "$missing", 1.0, 1e-12).unwrap_err();
+        assert_eq!(
+            message,
+            "`$missing` failed to evaluate, expected 1: CalcError: Variable not found: $missing"
+        );
+    }
+
+    #[test]
+    fn test_assert_evaluates_to() {
+        let mut calculator = Calculator::new();
+        assert_evaluates_to!(calculator, "sin(pi)", 0.0, tol = 1e-12);
+        assert_evaluates_to!(mut calculator, "0.1 + 0.2", 0.3);
+        assert_evaluates_to!(calculator, "$0", 0.3);
+    }
+
+    #[test]
+    #[should_panic(expected = "`2 * 3` evaluated to 6, expected 7 (difference: 1, tolerance: 0.1)")]
+    fn test_assert_evaluates_to_panics() {
+        let calculator = Calculator::new();
+        assert_evaluates_to!(calculator, "2 * 3", 7.0, tol = 0.1);
+    }
+}
@@ -0,0 +1,22 @@
+//! Types that make up the abstract syntax tree of an expression.
+//!
+//! An expression is represented by an [`Expr`], whose operators are [`Token`]s.
+//! Built-in functions and constants are [`Word`]s, and positions in the input are [`Span`]s.
+//! The tree can be traversed by implementing [`Visitor`].
+//! The precedence and associativity of binary operators are listed in [`BINARY_OPERATORS`].
+//!
+//! ```
+//! use expressive_calc::ast::{Expr, Token, Word};
+//!
+//! let expr = Expr::UnaryOp {
+//!     op: Token::Keyword(Word::Sqrt),
+//!     operand: Box::new(Expr::Number(4.0)),
+//! };
+//! assert!(matches!(expr, Expr::UnaryOp { .. }));
+//! ```
+
+pub use crate::parser::{
+    binary_operator, Associativity, BinaryOperator, Expr, Visitor, BINARY_OPERATORS,
+    PREFIX_PRECEDENCE,
+};
+pub use crate::scanner::{Arity, Span, Token, Word};
@@ -1,22 +1,190 @@
-use std::{error, fmt};
+use crate::scanner::Span;
+use std::{error, fmt, num::NonZeroU32};
+
+/// The stage of the calculator at which an error occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input contained an invalid character or literal.
+    Scan,
+    /// The tokens did not form a valid expression.
+    Parse,
+    /// The expression could not be evaluated.
+    Evaluation,
+    /// The calculator was configured with invalid values, such as an invalid table.
+    Configuration,
+    /// The expression was too large to evaluate within the evaluation budget.
+    Budget,
+    /// The evaluation was cancelled with a [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
+}
 
 /// Error type for the calculator.
+///
+/// Errors are compared by their kind and message.
+/// The position, span, statement, line, and source are ignored.
 #[derive(Debug)]
 pub struct CalcError {
+    kind: ErrorKind,
     message: String,
+    position: Option<usize>,
+    span: Option<Span>,
+    statement: Option<usize>,
+    line: Option<NonZeroU32>,
     source: Option<Box<dyn error::Error>>,
 }
 impl CalcError {
+    /// Create a new error with a message and an optional source.
+    ///
+    /// The error is an [`ErrorKind::Evaluation`] error.
+    /// Use [`CalcError::with_kind`] to change the kind.
     pub fn new(message: &str, source: Option<Box<dyn error::Error>>) -> Self {
         Self {
+            kind: ErrorKind::Evaluation,
             message: message.to_string(),
+            position: None,
+            span: None,
+            statement: None,
+            line: None,
             source,
         }
     }
+
+    /// Set the kind of the error.
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the message of the error, without the position or statement.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns true if the error occurred while scanning the input.
+    pub fn is_scan_error(&self) -> bool {
+        self.kind == ErrorKind::Scan
+    }
+
+    /// Returns true if the error occurred while parsing the tokens.
+    pub fn is_parse_error(&self) -> bool {
+        self.kind == ErrorKind::Parse
+    }
+
+    /// Returns true if the error occurred while evaluating the expression.
+    pub fn is_evaluation_error(&self) -> bool {
+        self.kind == ErrorKind::Evaluation
+    }
+
+    /// Set the position in the input where the error occurred.
+    pub fn with_position(mut self, position: usize) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Set the span of the input where the error occurred.
+    ///
+    /// The position is set to the start of the span.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.position = Some(span.start);
+        self.span = Some(span);
+        self
+    }
+
+    /// Set the index of the statement where the error occurred.
+    pub fn with_statement(mut self, statement: usize) -> Self {
+        self.statement = Some(statement);
+        self
+    }
+
+    /// Set the line number of the input where the error occurred.
+    ///
+    /// The first line has number 1; a line number of 0, or one too large to store, clears the line.
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = u32::try_from(line).ok().and_then(NonZeroU32::new);
+        self
+    }
+
+    /// Returns the position in the input where the error occurred, if known.
+    ///
+    /// This is the index of the character where scanning or parsing failed.
+    /// If parsing failed because the input ended early, this is the position after the last token.
+    /// Errors that occur during evaluation have no position.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// Returns the span of the input where the error occurred, if known.
+    ///
+    /// For parsing errors, this is the span of the offending token.
+    /// If parsing failed because the input ended early, this is an empty span after the last token.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Returns a line of carets marking where the error occurred, if known.
+    ///
+    /// The carets are aligned by character, so the line can be printed directly below the input
+    /// in a terminal. At least one caret is shown, even if the span is empty.
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let input = "π * 2 + 😀";
+    /// let err = Calculator::new().quick_evaluate(input).unwrap_err();
+    /// assert_eq!(err.caret().unwrap(), "        ^");
+    /// ```
+    pub fn caret(&self) -> Option<String> {
+        let (start, end) = match (self.span, self.position) {
+            (Some(span), _) => (span.start, span.end),
+            (None, Some(position)) => (position, position),
+            (None, None) => return None,
+        };
+        Some(format!(
+            "{}{}",
+            " ".repeat(start),
+            "^".repeat(end.saturating_sub(start).max(1))
+        ))
+    }
+
+    /// Returns the index of the statement where the error occurred, if known.
+    ///
+    /// This is only set when evaluating multiple statements, such as with [`Calculator::evaluate_all`](crate::Calculator::evaluate_all).
+    /// The first statement has index 0.
+    pub fn statement(&self) -> Option<usize> {
+        self.statement
+    }
+
+    /// Returns the line number of the input where the error occurred, if known.
+    ///
+    /// This is only set when evaluating a script with [`Calculator::evaluate_script`](crate::Calculator::evaluate_script).
+    /// The first line has number 1.
+    pub fn line(&self) -> Option<usize> {
+        self.line.map(|line| line.get() as usize)
+    }
 }
 impl fmt::Display for CalcError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CalcError: {}", self.message)
+        write!(f, "CalcError: {}", self.message)?;
+        if let Some(position) = self.position {
+            write!(f, " at position {}", position)?;
+        }
+        if let Some(statement) = self.statement {
+            write!(f, " in statement {}", statement)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " on line {}", line)?;
+        }
+        Ok(())
+    }
+}
+impl PartialEq for CalcError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.message == other.message
     }
 }
 impl error::Error for CalcError {
@@ -24,3 +192,70 @@ impl error::Error for CalcError {
         self.source.as_ref().map(|e| e.as_ref())
     }
 }
+
+/// A warning about input that was evaluated, but possibly not as the user intended.
+///
+/// For example, a number literal with more significant digits than an f64 can represent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    message: String,
+    span: Span,
+}
+impl Warning {
+    /// Create a new warning with a message and the span of the input it refers to.
+    pub fn new(message: &str, span: Span) -> Self {
+        Self {
+            message: message.to_string(),
+            span,
+        }
+    }
+
+    /// Returns the message of the warning.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the span of the input the warning refers to.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Warning: {} at position {}",
+            self.message, self.span.start
+        )
+    }
+}
+
+/// The default maximum number of characters of user input quoted in an error message.
+pub const DEFAULT_MAX_QUOTE_LENGTH: usize = 64;
+
+/// Quote a fragment of user input for use in an error message.
+///
+/// The fragment is kept as written, but if it is longer than `max_length` characters,
+/// it is truncated and followed by an ellipsis.
+/// Truncation never splits a character.
+pub fn quote(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("Radius", 6), "Radius");
+        assert_eq!(quote("Radius", 3), "Rad…");
+        assert_eq!(quote("ππππ", 2), "ππ…");
+        assert_eq!(quote("", 0), "");
+        assert_eq!(quote("x", 0), "…");
+    }
+}
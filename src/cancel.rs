@@ -0,0 +1,41 @@
+//! Module for cancelling evaluations from another thread.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A token used to cancel an evaluation, such as one that is taking too long.
+///
+/// Clones of a token share the same state, so a clone can be moved to another thread
+/// and cancelled there while the evaluation runs.
+/// Pass the token to [`Calculator::evaluate_with_cancel`](crate::Calculator::evaluate_with_cancel).
+///
+/// ```
+/// use expressive_calc::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let remote = token.clone();
+/// std::thread::spawn(move || remote.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+impl CancellationToken {
+    /// Create a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel any evaluation using this token or one of its clones.
+    ///
+    /// Cancellation cannot be undone, so create a new token for the next evaluation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,37 @@
+//! Module for expressions parsed once and evaluated many times.
+
+use crate::parser::Expr;
+
+/// An expression that has been scanned and parsed, ready to be evaluated repeatedly.
+///
+/// Created with [`Calculator::compile`](crate::Calculator::compile)
+/// and evaluated with [`Calculator::evaluate_compiled`](crate::Calculator::evaluate_compiled)
+/// or [`Calculator::evaluate_compiled_with`](crate::Calculator::evaluate_compiled_with).
+/// Compiling skips scanning and parsing on each evaluation,
+/// which helps when the same expression is evaluated in a loop, such as when plotting a function.
+///
+/// Variables are looked up when the expression is evaluated, not when it is compiled,
+/// so the expression sees the calculator's variables as they are at each evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledExpression {
+    input: String,
+    expr: Expr,
+}
+impl CompiledExpression {
+    pub(crate) fn new(input: &str, expr: Expr) -> Self {
+        Self {
+            input: input.to_string(),
+            expr,
+        }
+    }
+
+    /// Returns the input the expression was compiled from.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns the abstract syntax tree of the expression.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
@@ -0,0 +1,201 @@
+//! Test-only utilities for checking that every evaluation path agrees.
+//!
+//! Each public evaluation method of [`Calculator`] takes its own route from the input to a result.
+//! [`check`] runs an input through all of them and panics if any two disagree,
+//! comparing results bit for bit so that subtle numerical drift is caught.
+//! When a new evaluation backend is added, it should be added to [`backends`].
+//!
+//! Every backend ends in the same interpreter, so [`check`] does not test the interpreter against itself.
+//! It catches drift in everything around it: scanning, statement handling, and result storage,
+//! and the separate parse path of reverse Polish notation, which must build the same tree as the infix parser.
+//! The values computed by each function are tested against independent references in the interpreter's tests.
+//!
+//! [`expression`] is a proptest strategy producing random valid expressions to drive [`check`].
+//! Its functions and constants are read from [`Word::ALL`], so new keywords are generated without changes here.
+
+use crate::scanner::Word;
+use crate::{CalcError, Calculator};
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+/// The outcome of evaluating an input, compared bit for bit.
+///
+/// Errors are compared by their kind and message, like [`CalcError`].
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Value(u64),
+    Error(CalcError),
+}
+impl From<Result<f64, CalcError>> for Outcome {
+    fn from(result: Result<f64, CalcError>) -> Self {
+        match result {
+            Ok(value) => Outcome::Value(value.to_bits()),
+            Err(err) => Outcome::Error(err),
+        }
+    }
+}
+
+/// Create a fresh calculator whose random numbers are the same in every backend.
+fn calculator() -> Calculator {
+    let mut calculator = Calculator::new();
+    calculator.seed_rng(0);
+    calculator
+}
+
+/// Evaluate an input with every available evaluation backend, each on a fresh calculator.
+fn backends(input: &str) -> Vec<(&'static str, Outcome)> {
+    vec![
+        ("evaluate", {
+            let mut calculator = calculator();
+            calculator.evaluate(input).map(|(_, value)| value).into()
+        }),
+        ("evaluate_all", {
+            let mut calculator = calculator();
+            calculator
+                .evaluate_all(&[input])
+                .map(|results| results[0].1)
+                .into()
+        }),
+        ("quick_evaluate", calculator().quick_evaluate(input).into()),
+        (
+            "quick_evaluate_shadowed",
+            calculator()
+                .quick_evaluate_shadowed(input, &HashMap::new())
+                .into(),
+        ),
+        ("quick_evaluate_into", {
+            let mut out = HashMap::new();
+            calculator()
+                .quick_evaluate_into(input, &mut out, "result")
+                .into()
+        }),
+        ("evaluate_rpn", {
+            let mut calculator = calculator();
+            // Input that does not parse has no RPN form, so its error is compared instead
+            calculator
+                .parse(input)
+                .and_then(|expr| calculator.evaluate_rpn(&expr.to_rpn()))
+                .map(|(_, value)| value)
+                .into()
+        }),
+    ]
+}
+
+/// Run an input through every evaluation backend and panic if any results differ.
+///
+/// Values must be bitwise identical, so `0.0` and `-0.0` differ, and NaNs must have the same bits.
+/// Errors must have the same kind and message.
+pub fn check(input: &str) {
+    let mut outcomes = backends(input).into_iter();
+    let (first_name, first) = outcomes.next().unwrap();
+    for (name, outcome) in outcomes {
+        assert_eq!(
+            first, outcome,
+            "{} and {} disagree on input: {}",
+            first_name, name, input
+        );
+    }
+}
+
+/// The binary operators written between two operands.
+const BINARY_OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "^", "and", "or"];
+
+/// A strategy for a number literal, constant, or factorial.
+fn primary() -> impl Strategy<Value = String> {
+    // Physical constants are not enabled on a new calculator
+    let constants: Vec<&'static str> = Word::ALL
+        .iter()
+        .filter(|w| w.constant().is_some() && !w.is_physical_constant())
+        .map(Word::name)
+        .collect();
+    prop_oneof![
+        prop::sample::select(constants).prop_map(String::from),
+        (0..8u32).prop_map(|n| format!("{}!", n)),
+        (0..100u32, 0..1000u32).prop_map(|(whole, fraction)| format!("{}.{}", whole, fraction)),
+        (0..10u32, -20..20i32)
+            .prop_map(|(mantissa, exponent)| format!("{}e{}", mantissa, exponent)),
+        (0..20u32).prop_map(|n| n.to_string()),
+    ]
+}
+
+/// A strategy for a call to a keyword function with an accepted number of arguments.
+///
+/// Variadic functions are called with up to three more arguments than they require.
+fn call(operand: BoxedStrategy<String>) -> impl Strategy<Value = String> {
+    let functions: Vec<&'static Word> = Word::ALL
+        .iter()
+        .filter(|w| w.constant().is_none())
+        .collect();
+    prop::sample::select(functions).prop_flat_map(move |word| {
+        let min = word.arity().min();
+        let max = word.arity().max().unwrap_or(min + 3);
+        prop::collection::vec(operand.clone(), min..=max)
+            .prop_filter("the count must be accepted", |args| {
+                word.check_arity(args.len()).is_ok()
+            })
+            .prop_map(|args| format!("{}({})", word.name(), args.join(", ")))
+    })
+}
+
+/// A strategy for expressions that are valid in the grammar, nested up to about `depth` levels deep.
+pub fn expression(depth: u32) -> impl Strategy<Value = String> {
+    primary().prop_recursive(depth, 64, 4, |operand| {
+        prop_oneof![
+            (
+                operand.clone(),
+                prop::sample::select(BINARY_OPERATORS),
+                operand.clone()
+            )
+                .prop_map(|(left, op, right)| format!("{} {} {}", left, op, right)),
+            operand.clone().prop_map(|e| format!("-{}", e)),
+            operand.clone().prop_map(|e| format!("({})", e)),
+            operand.clone().prop_map(|e| format!("|{}|", e)),
+            call(operand),
+        ]
+    })
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        for input in [
+            "1 + 2", "0 / 0", "-0", "1 / 0", "1 +", "sqrt(-1)", "x", "rand()",
+        ] {
+            check(input);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_expression_valid(input in expression(4)) {
+            let result = Calculator::new().quick_evaluate(&input);
+            prop_assert!(
+                !result
+                    .as_ref()
+                    .is_err_and(|e| e.is_scan_error() || e.is_parse_error()),
+                "{}: {:?}",
+                input,
+                result
+            );
+        }
+
+        #[test]
+        fn test_differential(input in expression(6)) {
+            check(&input);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(10_000))]
+
+        #[test]
+        #[ignore = "slow; run with `cargo test -- --ignored`"]
+        fn test_differential_many(input in expression(6)) {
+            check(&input);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Module for the detailed result of evaluating an expression.
+
+use crate::calc_error::Warning;
+use crate::parser::Expr;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// The detailed result of [`Calculator::evaluate_full`](crate::Calculator::evaluate_full).
+///
+/// The name and value are the same as those returned by [`Calculator::evaluate`](crate::Calculator::evaluate).
+/// The other details are only collected by `evaluate_full`, so `evaluate` does not pay for them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub(crate) name: String,
+    pub(crate) value: f64,
+    pub(crate) assignment: bool,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) normalized: Option<String>,
+    pub(crate) dependencies: Option<Vec<String>>,
+    pub(crate) timings: Option<Timings>,
+}
+impl Evaluation {
+    /// Returns the name of the variable the result was stored in, such as `$3` or an assigned name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the result of the evaluation.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns true if the expression was an assignment, such as `radius = 5`.
+    pub fn is_assignment(&self) -> bool {
+        self.assignment
+    }
+
+    /// Returns the warnings about the input, as returned by [`Calculator::warnings`](crate::Calculator::warnings).
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns the expression as normalized text, as printed by [`Calculator::substitute`](crate::Calculator::substitute),
+    /// but without replacing variables.
+    pub fn normalized(&self) -> Option<&str> {
+        self.normalized.as_deref()
+    }
+
+    /// Returns the names of the variables read by the expression, sorted and without duplicates.
+    ///
+    /// An assigned name is not included unless the expression also reads it.
+    pub fn dependencies(&self) -> Option<&[String]> {
+        self.dependencies.as_deref()
+    }
+
+    /// Returns the time spent in each phase of the evaluation.
+    pub fn timings(&self) -> Option<Timings> {
+        self.timings
+    }
+}
+
+/// The time spent in each phase of an evaluation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timings {
+    parse: Duration,
+    evaluate: Duration,
+}
+impl Timings {
+    /// Create new timings from the time spent scanning and parsing, and the time spent evaluating.
+    pub fn new(parse: Duration, evaluate: Duration) -> Self {
+        Self { parse, evaluate }
+    }
+
+    /// Returns the time spent scanning and parsing the input.
+    pub fn parse(&self) -> Duration {
+        self.parse
+    }
+
+    /// Returns the time spent evaluating the parsed expression.
+    pub fn evaluate(&self) -> Duration {
+        self.evaluate
+    }
+}
+
+/// Collect the names of the variables read by an expression, sorted and without duplicates.
+pub fn dependencies(expr: &Expr) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    collect_variables(expr, &mut names);
+    names.into_iter().collect()
+}
+
+/// Add the names of the variables read by an expression to a set.
+fn collect_variables(expr: &Expr, names: &mut BTreeSet<String>) {
+    match expr {
+        Expr::Number(_) => {}
+        Expr::Variable(name) => {
+            names.insert(name.clone());
+        }
+        Expr::UnaryOp { operand, .. } => collect_variables(operand, names),
+        Expr::BinaryOp { left, right, .. } => {
+            collect_variables(left, names);
+            collect_variables(right, names);
+        }
+        Expr::NaryOp { args, .. } | Expr::Call { args, .. } => {
+            for arg in args {
+                collect_variables(arg, names);
+            }
+        }
+        Expr::Assign { value, .. } => collect_variables(value, names),
+    }
+}
@@ -0,0 +1,80 @@
+//! Module for computing stable fingerprints of calculator state.
+//!
+//! Fingerprints use the 64-bit FNV-1a hash, which has no random seed,
+//! so the same state always has the same fingerprint, across processes and platforms.
+//! All values are written in a fixed byte order, and strings are prefixed with their length
+//! so that adjacent values cannot run together.
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A deterministic hasher for fingerprints.
+pub struct Fingerprint {
+    hash: u64,
+}
+impl Fingerprint {
+    /// Create a new fingerprint with nothing written to it.
+    pub fn new() -> Self {
+        Self { hash: OFFSET_BASIS }
+    }
+
+    /// Write raw bytes to the fingerprint.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(PRIME);
+        }
+    }
+
+    /// Write an unsigned integer to the fingerprint.
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write a boolean to the fingerprint.
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_bytes(&[value as u8]);
+    }
+
+    /// Write a number to the fingerprint by its bits, so `0.0` and `-0.0` differ.
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    /// Write a string to the fingerprint, prefixed with its length.
+    pub fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write_bytes(value.as_bytes());
+    }
+
+    /// Returns the fingerprint of everything written so far.
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values for the 64-bit FNV-1a hash
+        let mut fingerprint = Fingerprint::new();
+        assert_eq!(fingerprint.finish(), 0xcbf2_9ce4_8422_2325);
+        fingerprint.write_bytes(b"a");
+        assert_eq!(fingerprint.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_strings_do_not_run_together() {
+        let mut first = Fingerprint::new();
+        first.write_str("ab");
+        first.write_str("c");
+        let mut second = Fingerprint::new();
+        second.write_str("a");
+        second.write_str("bc");
+        assert_ne!(first.finish(), second.finish());
+    }
+}
@@ -0,0 +1,131 @@
+//! Module for rendering numbers as human-friendly strings.
+
+/// The number of significant digits shown by default.
+pub const DEFAULT_PRECISION: usize = 15;
+
+/// Numbers with a magnitude at or above this power of ten are shown in scientific notation.
+const MAX_FIXED_EXPONENT: i32 = 15;
+
+/// Numbers with a magnitude below this power of ten are shown in scientific notation.
+const MIN_FIXED_EXPONENT: i32 = -6;
+
+/// Format a number for display.
+///
+/// If `precision` is `Some`, the number is rounded to that many significant digits (at least one),
+/// and trailing zeros are removed.
+/// If `precision` is `None`, the shortest representation that round-trips to the same value is used.
+/// Numbers with very large or very small magnitudes are shown in scientific notation, such as `1e20`.
+/// Infinities are shown as `inf` and `-inf`, and NaN is shown as `NaN`.
+pub fn format_number(value: f64, precision: Option<usize>) -> String {
+    if !value.is_finite() || value == 0.0 {
+        return format!("{}", value);
+    }
+
+    match precision {
+        Some(precision) => {
+            let precision = precision.max(1);
+            // Round to the requested significant digits first so that the exponent
+            // accounts for any carry, such as 9.99 becoming 10.0.
+            let rounded = format!("{:.*e}", precision - 1, value);
+            let (mantissa, exponent) = split_exponent(&rounded);
+            if is_fixed(exponent) {
+                let rounded: f64 = rounded.parse().unwrap_or(value);
+                let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+                trim_zeros(&format!("{:.*}", decimals, rounded)).to_string()
+            } else {
+                format!("{}e{}", trim_zeros(mantissa), exponent)
+            }
+        }
+        None => {
+            let (_, exponent) = split_exponent(&format!("{:e}", value));
+            if is_fixed(exponent) {
+                format!("{}", value)
+            } else {
+                format!("{:e}", value)
+            }
+        }
+    }
+}
+
+/// Check if a number is negative zero, `-0.0`.
+///
+/// Negative zero compares equal to zero, so `value == -0.0` cannot tell them apart.
+/// It results from operations such as `-1 * 0` and `1 / -inf`,
+/// and it still matters in some arithmetic, since `1 / -0.0` is negative infinity.
+pub fn is_negative_zero(value: f64) -> bool {
+    value == 0.0 && value.is_sign_negative()
+}
+
+/// Split a number formatted in scientific notation into its mantissa and exponent.
+fn split_exponent(formatted: &str) -> (&str, i32) {
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((formatted, "0"));
+    (mantissa, exponent.parse().unwrap_or(0))
+}
+
+/// Check if a number with the given exponent should be shown in fixed notation.
+fn is_fixed(exponent: i32) -> bool {
+    (MIN_FIXED_EXPONENT..MAX_FIXED_EXPONENT).contains(&exponent)
+}
+
+/// Remove trailing zeros after the decimal point, and the decimal point itself if nothing follows it.
+fn trim_zeros(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_default_precision() {
+        let cases = [
+            (0.1 + 0.2, "0.3"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (2.0, "2"),
+            (-2.5, "-2.5"),
+            (0.0, "0"),
+            (123456.789, "123456.789"),
+            (std::f64::consts::PI, "3.14159265358979"),
+            (1e20, "1e20"),
+            (-1.5e-7, "-1.5e-7"),
+            (0.000123, "0.000123"),
+            (999999999999999.9, "1e15"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "NaN"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(format_number(value, Some(DEFAULT_PRECISION)), expected);
+        }
+    }
+
+    #[test]
+    fn test_is_negative_zero() {
+        assert!(is_negative_zero(-0.0));
+        assert!(is_negative_zero(1.0 / f64::NEG_INFINITY));
+        assert!(!is_negative_zero(0.0));
+        assert!(!is_negative_zero(-1e-300));
+        assert!(!is_negative_zero(f64::NAN));
+    }
+
+    #[test]
+    fn test_format_full_precision() {
+        assert_eq!(format_number(0.1 + 0.2, None), "0.30000000000000004");
+        assert_eq!(format_number(2.0, None), "2");
+        assert_eq!(format_number(1e300, None), "1e300");
+        assert_eq!(format_number(1.5e-7, None), "1.5e-7");
+    }
+
+    #[test]
+    fn test_format_low_precision() {
+        assert_eq!(format_number(std::f64::consts::PI, Some(3)), "3.14");
+        assert_eq!(format_number(9.99, Some(2)), "10");
+        assert_eq!(format_number(123456.0, Some(2)), "120000");
+        assert_eq!(format_number(2.4, Some(0)), "2");
+    }
+}
@@ -0,0 +1,39 @@
+//! Module for the history of inputs evaluated by a calculator.
+
+/// An input evaluated by [`Calculator::evaluate`](crate::Calculator::evaluate) and its result.
+///
+/// See [`Calculator::history`](crate::Calculator::history).
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    input: String,
+    name: String,
+    result: f64,
+}
+impl HistoryEntry {
+    /// Create a new entry with the input as written, the name the result was stored in, and the result.
+    pub fn new(input: &str, name: &str, result: f64) -> Self {
+        Self {
+            input: input.to_string(),
+            name: name.to_string(),
+            result,
+        }
+    }
+
+    /// Returns the input as it was written.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns the name of the variable the result was stored in.
+    ///
+    /// This is the same name returned by [`Calculator::evaluate`](crate::Calculator::evaluate),
+    /// such as `$3`, or the assigned name for an assignment, such as `radius`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the result of the evaluation.
+    pub fn result(&self) -> f64 {
+        self.result
+    }
+}
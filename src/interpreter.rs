@@ -1,115 +1,821 @@
+use crate::calc_error::{self, CalcError, ErrorKind};
+use crate::cancel::CancellationToken;
+use crate::fingerprint::Fingerprint;
+use crate::math;
 use crate::parser::{Expr, Visitor};
-use crate::scanner::{Token, Word};
-use crate::CalcError;
+use crate::rng::Rng;
+use crate::scanner::{self, Token, Word};
+use crate::state::CalcState;
+use crate::table::Table;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The default maximum number of nodes visited while evaluating one expression.
+pub const DEFAULT_MAX_NODES: usize = 1_000_000;
+
+/// The number of nodes visited between checks for cancellation.
+const CANCEL_CHECK_INTERVAL: usize = 64;
+
+/// The default maximum number of arguments in one function call.
+pub const DEFAULT_MAX_ARGUMENTS: usize = 10_000;
+
+/// The unit of angles used by trigonometric functions.
+///
+/// See [`Calculator::set_angle_mode`](crate::Calculator::set_angle_mode).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AngleMode {
+    /// Angles are in radians, so `sin(pi / 2)` is `1`.
+    #[default]
+    Radians,
+    /// Angles are in degrees, so `sin(90)` is `1` and `asin(1)` is `90`.
+    Degrees,
+}
+
+/// What happens when a result is stored after the maximum number of results is reached.
+///
+/// See [`Calculator::set_max_results`](crate::Calculator::set_max_results).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultOverflow {
+    /// Return an error instead of evaluating, until the history is cleared.
+    Error,
+    /// Start numbering from `$0` again, replacing the oldest results.
+    Wrap,
+}
+
+/// A saved copy of the variables stored in an [`Interpreter`].
+///
+/// Created with [`Interpreter::snapshot`] and used to undo changes with [`Interpreter::restore`].
+pub struct Snapshot {
+    table: HashMap<String, f64>,
+    history: Vec<Option<f64>>,
+    next_result: usize,
+}
+
+/// The signature of a native function that can be called in expressions.
+///
+/// Functions must be `Send + Sync` so that a calculator can be shared between threads.
+type NativeFn = dyn Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync;
+
+/// A native function registered with [`Interpreter::register_function`].
+///
+/// Cloning a function shares the closure rather than copying it.
+#[derive(Clone)]
+struct Function {
+    arity: usize,
+    f: Arc<NativeFn>,
+}
+
+/// Counts of keyword function evaluations, collected when usage tracking is enabled.
+///
+/// The counts are behind a lock so that evaluations through a shared reference can update them.
+/// Cloning copies the counts, so the clone counts separately from the original.
+#[derive(Default)]
+struct Usage(Mutex<HashMap<Word, u64>>);
+impl Usage {
+    /// Lock the counts for reading or updating.
+    fn lock(&self) -> MutexGuard<'_, HashMap<Word, u64>> {
+        // The counts are always consistent, even if a thread panicked while holding the lock
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Clone for Usage {
+    fn clone(&self) -> Self {
+        Usage(Mutex::new(self.lock().clone()))
+    }
+}
+
+/// The state of a single evaluation, discarded when the evaluation finishes.
+///
+/// Keeping this out of the [`Interpreter`] lets evaluations that do not store results
+/// run through a shared reference, including from several threads at once.
+struct Context<'a> {
+    /// Variables looked up before the stored variables.
+    scope: &'a HashMap<String, f64>,
+    /// The number of nodes visited so far, limited by the node budget.
+    nodes_visited: usize,
+}
+impl<'a> Context<'a> {
+    /// Create a context for a new evaluation.
+    fn new(scope: &'a HashMap<String, f64>) -> Self {
+        Context {
+            scope,
+            nodes_visited: 0,
+        }
+    }
+}
 
 /// An interpreter for evaluating an abstract syntax tree.
 ///
 /// The `interpret` method will traverse the AST and evaluate the expression.
 /// State information may be stored in the struct.
+/// Results are stored separately from named variables so that they can be cleared independently.
+#[derive(Clone)]
 pub struct Interpreter {
     table: HashMap<String, f64>,
-    variable_count: usize,
+    history: Vec<Option<f64>>,
+    next_result: usize,
+    max_results: usize,
+    result_overflow: ResultOverflow,
+    tables: HashMap<String, Table>,
+    functions: HashMap<String, Function>,
+    strict: bool,
+    strict_division: bool,
+    angle_mode: AngleMode,
+    rng: Rng,
+    max_nodes: usize,
+    max_arguments: usize,
+    max_quote_length: usize,
+    track_usage: bool,
+    usage: Usage,
+    cancellation: Option<CancellationToken>,
 }
 impl Interpreter {
     /// Create a new interpreter.
     pub fn new() -> Self {
         Interpreter {
             table: HashMap::new(),
-            variable_count: 0,
+            history: Vec::new(),
+            next_result: 0,
+            max_results: usize::MAX,
+            result_overflow: ResultOverflow::Error,
+            tables: HashMap::new(),
+            functions: HashMap::new(),
+            strict: false,
+            strict_division: false,
+            angle_mode: AngleMode::Radians,
+            rng: Rng::from_entropy(),
+            max_nodes: DEFAULT_MAX_NODES,
+            max_arguments: DEFAULT_MAX_ARGUMENTS,
+            max_quote_length: calc_error::DEFAULT_MAX_QUOTE_LENGTH,
+            track_usage: false,
+            usage: Usage::default(),
+            cancellation: None,
+        }
+    }
+
+    /// Enable or disable strict mode.
+    ///
+    /// In strict mode, functions evaluated outside of their domain return an error
+    /// instead of producing a non-finite result.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Enable or disable strict division.
+    ///
+    /// When enabled, dividing by zero or taking the remainder of a division by zero
+    /// returns an error instead of producing a non-finite result.
+    pub fn set_strict_division(&mut self, strict_division: bool) {
+        self.strict_division = strict_division;
+    }
+
+    /// Set the unit of angles used by trigonometric functions.
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.angle_mode = angle_mode;
+    }
+
+    /// Seed the pseudo-random number generator used by `rand`.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Returns the next pseudo-random number in `[0, 1)`.
+    fn next_random(&self) -> f64 {
+        self.rng.next_f64()
+    }
+
+    /// Set the maximum number of nodes visited while evaluating one expression.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Set the maximum number of arguments in one function call.
+    pub fn set_max_arguments(&mut self, max_arguments: usize) {
+        self.max_arguments = max_arguments;
+    }
+
+    /// Set the maximum number of characters of user input quoted in an error message.
+    pub fn set_max_quote_length(&mut self, max_quote_length: usize) {
+        self.max_quote_length = max_quote_length;
+    }
+
+    /// Quote a fragment of user input for use in an error message.
+    fn quote(&self, text: &str) -> String {
+        calc_error::quote(text, self.max_quote_length)
+    }
+
+    /// Check that a registered function or table is called with the expected number of arguments.
+    fn check_call_arity(&self, name: &str, expected: usize, count: usize) -> Result<(), CalcError> {
+        if count != expected {
+            return Err(CalcError::new(
+                &format!(
+                    "{} expects {} argument(s), got {}",
+                    self.quote(name),
+                    expected,
+                    count
+                ),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Enable or disable counting how many times each keyword function is evaluated.
+    pub fn set_track_usage(&mut self, track_usage: bool) {
+        self.track_usage = track_usage;
+    }
+
+    /// Get the number of times each keyword function has been evaluated while tracking was enabled.
+    pub fn usage_stats(&self) -> HashMap<Word, u64> {
+        self.usage.lock().clone()
+    }
+
+    /// Reset all usage counts to zero.
+    pub fn reset_usage_stats(&mut self) {
+        self.usage = Usage::default();
+    }
+
+    /// Count an evaluation of a keyword function if usage tracking is enabled.
+    fn record_usage(&self, op: &Token) {
+        if let (true, Token::Keyword(w)) = (self.track_usage, op) {
+            *self.usage.lock().entry(w.clone()).or_insert(0) += 1;
         }
     }
 
+    /// Set the token checked for cancellation during evaluation, or `None` to never cancel.
+    pub fn set_cancellation(&mut self, cancellation: Option<CancellationToken>) {
+        self.cancellation = cancellation;
+    }
+
+    /// Count a visited node, returning an error if the node budget is exceeded.
+    ///
+    /// The cancellation token, if any, is checked on the first node and every
+    /// [`CANCEL_CHECK_INTERVAL`] nodes after it.
+    fn spend_node(&self, context: &mut Context) -> Result<(), CalcError> {
+        let visited = context.nodes_visited + 1;
+        if visited > self.max_nodes {
+            return Err(
+                CalcError::new("Evaluation budget exceeded: too many nodes", None)
+                    .with_kind(ErrorKind::Budget),
+            );
+        }
+        if let Some(cancellation) = &self.cancellation {
+            if (visited - 1).is_multiple_of(CANCEL_CHECK_INTERVAL) && cancellation.is_cancelled() {
+                return Err(CalcError::new("Evaluation was cancelled", None)
+                    .with_kind(ErrorKind::Cancelled));
+            }
+        }
+        context.nodes_visited = visited;
+        Ok(())
+    }
+
+    /// Check that a function call does not have more arguments than the budget allows.
+    fn check_arguments(&self, count: usize) -> Result<(), CalcError> {
+        if count > self.max_arguments {
+            return Err(
+                CalcError::new("Evaluation budget exceeded: too many arguments", None)
+                    .with_kind(ErrorKind::Budget),
+            );
+        }
+        Ok(())
+    }
+
+    /// Evaluate a word with the given arguments, converting angles according to the angle mode.
+    ///
+    /// In degree mode, forward trigonometric functions such as `sin` and `sec` take their argument in degrees,
+    /// and inverse functions such as `asin`, `asec`, and `atan2` return their result in degrees,
+    /// computed like `sind` and `asind` so that special angles are exact.
+    /// Other words, including hyperbolic functions, `rad`, and `deg`, are unaffected.
+    /// `rand` draws from the interpreter's generator.
+    fn apply(&self, word: &Word, args: &[f64]) -> Result<f64, CalcError> {
+        if *word == Word::Rand {
+            word.check_arity(args.len())?;
+            return math::uniform(self.next_random(), args);
+        }
+        match self.angle_mode {
+            AngleMode::Radians => word.apply(args, self.strict),
+            AngleMode::Degrees => word.apply_degrees(args, self.strict),
+        }
+    }
+
+    /// Check that the divisor is not zero if strict division is enabled.
+    fn check_divisor(&self, divisor: f64) -> Result<(), CalcError> {
+        if self.strict_division && divisor == 0.0 {
+            return Err(CalcError::new("Division by zero", None));
+        }
+        Ok(())
+    }
+
+    /// Store a value in a variable, replacing any existing value.
+    ///
+    /// The result history is not changed, so automatic variable names are not affected.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid variable name, such as `$health`,
+    /// or a valid identifier that is not a reserved word, such as `health`.
+    /// Numbered variables, such as `$0`, are reserved for results and cannot be set.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalcError> {
+        check_variable_name(name)?;
+        self.table.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Get the value stored in a variable, if it exists.
+    pub fn get_variable(&self, name: &str) -> Option<f64> {
+        match history_index(name) {
+            Some(index) => self.history.get(index).copied().flatten(),
+            None => self.table.get(name).copied(),
+        }
+    }
+
+    /// Remove a variable, returning its value if it existed.
+    ///
+    /// Removing a numbered variable, such as `$1`, does not renumber the other results,
+    /// and the next result is still numbered after the last one created.
+    /// Removing a variable does not change `$ans`, even if it holds the same result.
+    pub fn remove_variable(&mut self, name: &str) -> Option<f64> {
+        match history_index(name) {
+            Some(index) => self.history.get_mut(index)?.take(),
+            None => self.table.remove(name),
+        }
+    }
+
+    /// Get the numbered results and their values in numeric order, skipping any that were removed.
+    pub fn results(&self) -> impl Iterator<Item = (String, f64)> + '_ {
+        self.history
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| Some((format!("${}", index), (*value)?)))
+    }
+
+    /// Get all stored variables and their values.
+    ///
+    /// Numbered variables come first in numeric order (`$0`, `$1`, etc.),
+    /// followed by named variables sorted by name, followed by `$ans` if it has been stored.
+    pub fn variables(&self) -> impl Iterator<Item = (String, f64)> + '_ {
+        let mut named: Vec<_> = self.table.iter().collect();
+        named.sort_by_key(|(name, _)| (name.as_str() == "$ans", name.as_str()));
+        self.results().chain(
+            named
+                .into_iter()
+                .map(|(name, value)| (name.clone(), *value)),
+        )
+    }
+
+    /// Register an interpolation table under a name, replacing any table with the same name.
+    ///
+    /// The table can then be called like a function with one argument.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid identifier or is a reserved word.
+    pub fn register_table(&mut self, name: &str, table: Table) -> Result<(), CalcError> {
+        if !scanner::is_identifier(name) {
+            return Err(
+                CalcError::new("Invalid table name", None).with_kind(ErrorKind::Configuration)
+            );
+        }
+        self.functions.remove(name);
+        self.tables.insert(name.to_string(), table);
+        Ok(())
+    }
+
+    /// Register a native function under a name, replacing any function or table with the same name.
+    ///
+    /// The function can then be called with exactly `arity` arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid identifier or is a reserved word.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: usize,
+        f: impl Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync + 'static,
+    ) -> Result<(), CalcError> {
+        if !scanner::is_identifier(name) {
+            return Err(
+                CalcError::new("Invalid function name", None).with_kind(ErrorKind::Configuration)
+            );
+        }
+        self.tables.remove(name);
+        self.functions.insert(
+            name.to_string(),
+            Function {
+                arity,
+                f: Arc::new(f),
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove a native function, returning true if it was registered.
+    pub fn unregister_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    /// Remove an interpolation table, returning true if it was registered.
+    pub fn unregister_table(&mut self, name: &str) -> bool {
+        self.tables.remove(name).is_some()
+    }
+
     /// Interpret an expression and return a variable name and result.
     ///
     /// This method will visit each node in the AST and evaluate the expression.
     /// The result will be stored in a variable name that can be used in future expressions.
     /// Variables are named based on the order: `$0`, `$1`, `$2`, etc.
     /// The last result is also stored in the variable `$ans`.
+    /// If the expression is an assignment, the result is also stored in the assigned variable,
+    /// and the assigned name is returned instead.
     pub fn interpret(&mut self, input: Box<Expr>) -> Result<(String, f64), CalcError> {
+        let index = self.next_result_index()?;
         let result = self.visit(&input)?;
-        let name = format!("${}", self.variable_count);
-        self.table.insert(name.clone(), result);
+        let mut name = format!("${}", index);
+        match self.history.get_mut(index) {
+            Some(slot) => *slot = Some(result),
+            None => self.history.push(Some(result)),
+        }
+        self.next_result = index + 1;
         self.table.insert("$ans".to_string(), result);
-        self.variable_count += 1;
+        if let Expr::Assign { name: assigned, .. } = *input {
+            self.table.insert(assigned.clone(), result);
+            name = assigned;
+        }
         Ok((name, result))
     }
 
+    /// Set the maximum number of numbered results, and what happens when it is reached.
+    ///
+    /// The maximum is at least one.
+    pub fn set_max_results(&mut self, max_results: usize, overflow: ResultOverflow) {
+        self.max_results = max_results.max(1);
+        self.result_overflow = overflow;
+    }
+
+    /// Returns the index of the next numbered result, such as 2 for `$2`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the maximum number of results is reached and results do not wrap.
+    fn next_result_index(&self) -> Result<usize, CalcError> {
+        if self.next_result < self.max_results {
+            return Ok(self.next_result);
+        }
+        match self.result_overflow {
+            ResultOverflow::Error => Err(CalcError::new(
+                "Result history is full; clear the history or reset to continue",
+                None,
+            )
+            .with_kind(ErrorKind::Budget)),
+            ResultOverflow::Wrap => Ok(0),
+        }
+    }
+
     /// Interpret an expression without storing the result.
     ///
     /// This method will visit each node in the AST and evaluate the expression.
     /// Variables previously stored in the interpreter may still be used,
     /// but no new variables will be created.
-    pub fn quick_interpret(&self, input: Box<Expr>) -> Result<f64, CalcError> {
-        self.visit(&input)
+    /// Assignments are evaluated, but the assigned variable is not stored.
+    pub fn quick_interpret(&self, input: &Expr) -> Result<f64, CalcError> {
+        self.visit(input)
+    }
+
+    /// Interpret an expression without storing the result, using variables in `scope` first.
+    ///
+    /// Variables in `scope` are used before variables stored in the interpreter.
+    /// Nothing is stored in either place.
+    pub fn quick_interpret_shadowed(
+        &self,
+        input: &Expr,
+        scope: &HashMap<String, f64>,
+    ) -> Result<f64, CalcError> {
+        self.visit_with(input, &mut Context::new(scope))
+    }
+
+    /// Interpret an expression without storing the result, inserting it into `scope` instead.
+    ///
+    /// Variables in `scope` are used before variables stored in the interpreter.
+    /// The result is only inserted if evaluation succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid variable name or if evaluation fails.
+    pub fn quick_interpret_into(
+        &self,
+        input: Box<Expr>,
+        scope: &mut HashMap<String, f64>,
+        name: &str,
+    ) -> Result<f64, CalcError> {
+        check_variable_name(name)?;
+        let result = self.visit_with(&input, &mut Context::new(scope))?;
+        scope.insert(name.to_string(), result);
+        Ok(result)
+    }
+
+    /// Write the variables, result counter, tables, functions, and evaluation modes to a fingerprint.
+    ///
+    /// Everything is written in a canonical order, so the order of registration does not matter.
+    /// Functions are written by name and arity only, since closures cannot be inspected.
+    pub fn write_fingerprint(&self, fingerprint: &mut Fingerprint) {
+        fingerprint.write_u64(self.next_result as u64);
+        let variables: Vec<_> = self.variables().collect();
+        fingerprint.write_u64(variables.len() as u64);
+        for (name, value) in variables {
+            fingerprint.write_str(&name);
+            fingerprint.write_f64(value);
+        }
+
+        let mut tables: Vec<_> = self.tables.iter().collect();
+        tables.sort_by_key(|(name, _)| name.as_str());
+        fingerprint.write_u64(tables.len() as u64);
+        for (name, table) in tables {
+            fingerprint.write_str(name);
+            table.write_fingerprint(fingerprint);
+        }
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|(name, _)| name.as_str());
+        fingerprint.write_u64(functions.len() as u64);
+        for (name, function) in functions {
+            fingerprint.write_str(name);
+            fingerprint.write_u64(function.arity as u64);
+        }
+
+        fingerprint.write_bool(self.strict);
+        fingerprint.write_bool(self.strict_division);
+        // Only written when not the default, so fingerprints from before the option existed are unchanged
+        if self.angle_mode != AngleMode::Radians {
+            fingerprint.write_str("degrees");
+        }
+    }
+
+    /// Replace every variable in an expression that has a stored value with that value.
+    ///
+    /// Variables without a stored value are left unchanged.
+    /// The name assigned to by an assignment is not a variable reference, so it is also left unchanged.
+    pub fn substitute(&self, expr: Expr) -> Expr {
+        let substitute_all =
+            |args: Vec<Expr>| args.into_iter().map(|arg| self.substitute(arg)).collect();
+        let substitute_box = |expr: Box<Expr>| Box::new(self.substitute(*expr));
+        match expr {
+            Expr::Variable(name) => match self.get_variable(&name) {
+                Some(value) => Expr::Number(value),
+                None => Expr::Variable(name),
+            },
+            Expr::Number(n) => Expr::Number(n),
+            Expr::UnaryOp { op, operand } => Expr::UnaryOp {
+                op,
+                operand: substitute_box(operand),
+            },
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op,
+                left: substitute_box(left),
+                right: substitute_box(right),
+            },
+            Expr::NaryOp { op, args } => Expr::NaryOp {
+                op,
+                args: substitute_all(args),
+            },
+            Expr::Call { name, args } => Expr::Call {
+                name,
+                args: substitute_all(args),
+            },
+            Expr::Assign { name, value } => Expr::Assign {
+                name,
+                value: substitute_box(value),
+            },
+        }
+    }
+
+    /// Save a copy of the stored variables and the result history.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            table: self.table.clone(),
+            history: self.history.clone(),
+            next_result: self.next_result,
+        }
+    }
+
+    /// Restore the stored variables and the result history from a snapshot.
+    ///
+    /// Any variables stored since the snapshot was taken are discarded.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.table = snapshot.table;
+        self.history = snapshot.history;
+        self.next_result = snapshot.next_result;
+    }
+
+    /// Export the stored variables and the result history.
+    pub fn export_state(&self) -> CalcState {
+        CalcState {
+            variables: self.table.clone().into_iter().collect(),
+            results: self.history.clone(),
+            next_result: self.next_result,
+        }
+    }
+
+    /// Replace the stored variables and the result history with an exported state.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if a named variable in the state has an invalid name,
+    /// or if the result counter is past the end of the results,
+    /// in which case the stored variables are not changed.
+    pub fn import_state(&mut self, state: CalcState) -> Result<(), CalcError> {
+        for name in state.variables.keys() {
+            if name != "$ans" {
+                check_variable_name(name)?;
+            }
+        }
+        // The next result either replaces an existing one or is appended
+        if state.next_result > state.results.len() {
+            return Err(CalcError::new(
+                &format!(
+                    "Invalid state: the next result is ${}, but there are only {} result(s)",
+                    state.next_result,
+                    state.results.len()
+                ),
+                None,
+            ));
+        }
+        self.table = state.variables.into_iter().collect();
+        self.history = state.results;
+        self.next_result = state.next_result;
+        Ok(())
+    }
+
+    /// Clear the result history so that results are numbered from `$0` again.
+    ///
+    /// Named variables, registered tables, and registered functions are kept.
+    /// If `clear_ans` is true, `$ans` is also removed.
+    pub fn clear_history(&mut self, clear_ans: bool) {
+        self.history.clear();
+        self.next_result = 0;
+        if clear_ans {
+            self.table.remove("$ans");
+        }
     }
 
     /// Reset the interpreter, clearing all stored variables.
     ///
-    /// This method will clear all stored variables and reset the variable count.
+    /// This method will clear all stored variables and the result history.
+    /// Registered tables and functions are kept.
     pub fn reset(&mut self) {
         self.table.clear();
-        self.variable_count = 0;
+        self.history.clear();
+        self.next_result = 0;
     }
 }
+
+/// Check that a name can be used for a variable set outside of an expression.
+///
+/// Numbered variables, such as `$0`, are reserved for results.
+fn check_variable_name(name: &str) -> Result<(), CalcError> {
+    if !scanner::is_variable(name) && !scanner::is_identifier(name) {
+        return Err(
+            CalcError::new("Invalid variable name", None).with_kind(ErrorKind::Configuration)
+        );
+    }
+    if history_index(name).is_some() {
+        return Err(
+            CalcError::new("Numbered variables are reserved for results", None)
+                .with_kind(ErrorKind::Configuration),
+        );
+    }
+    Ok(())
+}
+
+/// Get the index of a numbered variable, such as 2 for `$2`.
+///
+/// Returns `None` for any other variable name, including names with leading zeros like `$02`.
+fn history_index(name: &str) -> Option<usize> {
+    let digits = name.strip_prefix('$')?;
+    if digits.is_empty()
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+        || (digits.len() > 1 && digits.starts_with('0'))
+    {
+        return None;
+    }
+    digits.parse().ok()
+}
+
 impl Visitor<f64> for Interpreter {
     fn visit(&self, expr: &Expr) -> Result<f64, CalcError> {
+        self.visit_with(expr, &mut Context::new(&HashMap::new()))
+    }
+}
+impl Interpreter {
+    /// Visit an expression as part of the evaluation described by `context`.
+    fn visit_with(&self, expr: &Expr, context: &mut Context) -> Result<f64, CalcError> {
+        self.spend_node(context)?;
         match expr {
             Expr::Number(n) => Ok(*n),
             Expr::UnaryOp { op, operand } => {
-                let operand = self.visit(operand)?;
+                let operand = self.visit_with(operand, context)?;
+                self.record_usage(op);
                 match op {
                     Token::Minus => Ok(-operand),
-                    Token::Keyword(Word::Sqrt) => Ok(operand.sqrt()),
-                    Token::Keyword(Word::Cbrt) => Ok(operand.cbrt()),
-                    Token::Keyword(Word::Exp) => Ok(operand.exp()),
-                    Token::Keyword(Word::Log2) => Ok(operand.log2()),
-                    Token::Keyword(Word::Log10) => Ok(operand.log10()),
-                    Token::Keyword(Word::Ln) => Ok(operand.ln()),
-                    Token::Keyword(Word::Sin) => Ok(operand.sin()),
-                    Token::Keyword(Word::Cos) => Ok(operand.cos()),
-                    Token::Keyword(Word::Tan) => Ok(operand.tan()),
-                    Token::Keyword(Word::Asin) => Ok(operand.asin()),
-                    Token::Keyword(Word::Acos) => Ok(operand.acos()),
-                    Token::Keyword(Word::Atan) => Ok(operand.atan()),
-                    Token::Keyword(Word::Sinh) => Ok(operand.sinh()),
-                    Token::Keyword(Word::Cosh) => Ok(operand.cosh()),
-                    Token::Keyword(Word::Tanh) => Ok(operand.tanh()),
-                    Token::Keyword(Word::Asinh) => Ok(operand.asinh()),
-                    Token::Keyword(Word::Acosh) => Ok(operand.acosh()),
-                    Token::Keyword(Word::Atanh) => Ok(operand.atanh()),
-                    Token::Keyword(Word::Rad) => Ok(operand.to_radians()),
-                    Token::Keyword(Word::Deg) => Ok(operand.to_degrees()),
-                    Token::Keyword(Word::Abs) => Ok(operand.abs()),
-                    Token::Keyword(Word::Floor) => Ok(operand.floor()),
-                    Token::Keyword(Word::Ceil) => Ok(operand.ceil()),
-                    Token::Keyword(Word::Trunc) => Ok(operand.trunc()),
-                    Token::Keyword(Word::Round) => Ok(operand.round()),
-                    _ => Ok(0.0),
+                    Token::Bang => math::factorial(operand),
+                    Token::Percent => Ok(operand / 100.0),
+                    Token::Degree => Ok(match self.angle_mode {
+                        AngleMode::Radians => operand.to_radians(),
+                        AngleMode::Degrees => operand,
+                    }),
+                    Token::Keyword(w) => self.apply(w, &[operand]),
+                    _ => Err(CalcError::new(
+                        &format!("Unsupported unary operator: {:?}", op),
+                        None,
+                    )),
                 }
             }
             Expr::BinaryOp { op, left, right } => {
-                let left = self.visit(left)?;
-                let right = self.visit(right)?;
+                let left = self.visit_with(left, context)?;
+                // Logical operators short-circuit, so the right side is only visited if needed
+                match op {
+                    Token::And if left == 0.0 => return Ok(0.0),
+                    Token::Or if left != 0.0 => return Ok(1.0),
+                    Token::And | Token::Or => {
+                        let right = self.visit_with(right, context)?;
+                        return Ok(if right != 0.0 { 1.0 } else { 0.0 });
+                    }
+                    _ => {}
+                }
+                let right = self.visit_with(right, context)?;
+                self.record_usage(op);
                 match op {
                     Token::Plus => Ok(left + right),
                     Token::Minus => Ok(left - right),
                     Token::Star => Ok(left * right),
-                    Token::Slash => Ok(left / right),
-                    Token::Caret | Token::Keyword(Word::Pow) => Ok(left.powf(right)),
-                    Token::Percent | Token::Keyword(Word::Mod) => Ok(left % right),
-                    Token::Keyword(Word::Log) => Ok(left.log(right)),
-                    Token::Keyword(Word::Hypot) => Ok(left.hypot(right)),
-                    Token::Keyword(Word::Atan2) => Ok(left.atan2(right)),
-                    Token::Keyword(Word::Max) => Ok(left.max(right)),
-                    Token::Keyword(Word::Min) => Ok(left.min(right)),
-                    _ => Ok(0.0),
+                    Token::Slash => {
+                        self.check_divisor(right)?;
+                        Ok(left / right)
+                    }
+                    Token::Caret => Word::Pow.apply(&[left, right], self.strict),
+                    Token::Percent | Token::Keyword(Word::Mod) => {
+                        self.check_divisor(right)?;
+                        Word::Mod.apply(&[left, right], self.strict)
+                    }
+                    Token::Keyword(w) => self.apply(w, &[left, right]),
+                    _ => Err(CalcError::new(
+                        &format!("Unsupported binary operator: {:?}", op),
+                        None,
+                    )),
+                }
+            }
+            Expr::NaryOp { op, args } => {
+                self.check_arguments(args.len())?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.visit_with(arg, context))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.record_usage(op);
+                match op {
+                    Token::Keyword(w) => self.apply(w, &args),
+                    _ => Err(CalcError::new(
+                        &format!("Unsupported operator: {:?}", op),
+                        None,
+                    )),
+                }
+            }
+            Expr::Call { name, args } => {
+                self.check_arguments(args.len())?;
+                if let Some(function) = self.functions.get(name) {
+                    self.check_call_arity(name, function.arity, args.len())?;
+                    let args = args
+                        .iter()
+                        .map(|arg| self.visit_with(arg, context))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return (function.f)(&args);
+                }
+                let table = match self.tables.get(name) {
+                    Some(table) => table,
+                    None => {
+                        return Err(CalcError::new(
+                            &format!("Unknown function: {}", self.quote(name)),
+                            None,
+                        ))
+                    }
+                };
+                self.check_call_arity(name, 1, args.len())?;
+                Ok(table.lookup(self.visit_with(&args[0], context)?))
+            }
+            Expr::Assign { value, .. } => self.visit_with(value, context),
+            Expr::Variable(name) => {
+                match context
+                    .scope
+                    .get(name)
+                    .copied()
+                    .or_else(|| self.get_variable(name))
+                {
+                    Some(value) => Ok(value),
+                    None => Err(CalcError::new(
+                        &format!("Variable not found: {}", self.quote(name)),
+                        None,
+                    )),
                 }
             }
-            Expr::Variable(name) => match self.table.get(name) {
-                Some(value) => Ok(*value),
-                None => Err(CalcError::new("Variable not found", None)),
-            },
         }
     }
 }
@@ -142,6 +848,22 @@ mod tests {
         assert_eq!(result, -42.0);
     }
 
+    #[test]
+    fn test_interpret_stacked_unary() {
+        let interpreter = Interpreter::new();
+        for depth in 1..=5 {
+            let mut input = Box::new(Expr::Number(5.0));
+            for _ in 0..depth {
+                input = Box::new(Expr::UnaryOp {
+                    op: Token::Minus,
+                    operand: input,
+                });
+            }
+            let expected = if depth % 2 == 0 { 5.0 } else { -5.0 };
+            assert_eq!(interpreter.quick_interpret(&input).unwrap(), expected);
+        }
+    }
+
     #[test]
     fn test_interpret_complex() {
         let input = Box::new(Expr::BinaryOp {
@@ -193,14 +915,14 @@ mod tests {
         });
         let mut interpreter = Interpreter::new();
         let (_, result) = interpreter.interpret(input).unwrap();
-        assert_eq!(result, 2.718281828459045);
+        assert_eq!(result, std::f64::consts::E);
     }
 
     #[test]
     fn test_interpret_ln() {
         let input = Box::new(Expr::UnaryOp {
             op: Token::Keyword(Word::Ln),
-            operand: Box::new(Expr::Number(2.718281828459045)),
+            operand: Box::new(Expr::Number(std::f64::consts::E)),
         });
         let mut interpreter = Interpreter::new();
         let (_, result) = interpreter.interpret(input).unwrap();
@@ -242,4 +964,272 @@ mod tests {
         let (_, result) = interpreter.interpret(input).unwrap();
         assert_eq!(result, 2.0);
     }
+
+    #[test]
+    fn test_interpret_unsupported_unary() {
+        let input = Box::new(Expr::UnaryOp {
+            op: Token::Plus,
+            operand: Box::new(Expr::Number(42.0)),
+        });
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.interpret(input).unwrap_err();
+        assert!(err.to_string().contains("Unsupported unary operator: Plus"));
+    }
+
+    #[test]
+    fn test_interpret_keywords() {
+        use std::f64::consts::{E, FRAC_1_SQRT_2, LN_10, LN_2, PI, SQRT_2, TAU};
+        let cases: &[(Word, &[f64], f64)] = &[
+            (Word::Inf, &[], f64::INFINITY),
+            (Word::Pi, &[], PI),
+            (Word::Tau, &[], TAU),
+            (Word::E, &[], E),
+            (Word::Phi, &[], (1.0 + 5f64.sqrt()) / 2.0),
+            (Word::Ln2, &[], LN_2),
+            (Word::Ln10, &[], LN_10),
+            (Word::Sqrt2, &[], SQRT_2),
+            (Word::Invsqrt2, &[], FRAC_1_SQRT_2),
+            (Word::True, &[], 1.0),
+            (Word::False, &[], 0.0),
+            (Word::FloatDigits, &[], 15.0),
+            (Word::FloatMaxexp, &[], 1024.0),
+            (Word::Eps, &[], 2f64.powi(-52)),
+            (Word::Maxval, &[], f64::MAX),
+            (Word::Minval, &[], 2f64.powi(-1022)),
+            (Word::SpeedOfLight, &[], 299_792_458.0),
+            (Word::Gravitation, &[], 6.674_30e-11),
+            (Word::Planck, &[], 6.626_070_15e-34),
+            (Word::ReducedPlanck, &[], 1.054_571_817e-34),
+            (Word::Avogadro, &[], 6.022_140_76e23),
+            (Word::Boltzmann, &[], 1.380_649e-23),
+            (Word::ElementaryCharge, &[], 1.602_176_634e-19),
+            (Word::StandardGravity, &[], 9.806_65),
+            (Word::Sqrt, &[0.75], 0.75f64.sqrt()),
+            (Word::Cbrt, &[-27.0], -3.0),
+            (Word::Exp, &[0.75], 0.75f64.exp()),
+            (Word::Log2, &[8.0], 3.0),
+            (Word::Log10, &[1000.0], 3.0),
+            (Word::Ln, &[0.75], 0.75f64.ln()),
+            (Word::Sin, &[0.75], 0.75f64.sin()),
+            (Word::Cos, &[0.75], 0.75f64.cos()),
+            (Word::Tan, &[0.75], 0.75f64.tan()),
+            (Word::Asin, &[0.75], 0.75f64.asin()),
+            (Word::Acos, &[0.75], 0.75f64.acos()),
+            (Word::Atan, &[0.75], 0.75f64.atan()),
+            (Word::Sec, &[0.75], 1.0 / 0.75f64.cos()),
+            (Word::Csc, &[0.75], 1.0 / 0.75f64.sin()),
+            (Word::Cot, &[0.75], 1.0 / 0.75f64.tan()),
+            (Word::Asec, &[2.0], PI / 3.0),
+            (Word::Acsc, &[2.0], PI / 6.0),
+            (Word::Acot, &[1.0], PI / 4.0),
+            (Word::Sind, &[30.0], 0.5),
+            (Word::Cosd, &[60.0], 0.5),
+            (Word::Tand, &[135.0], -1.0),
+            (Word::Asind, &[0.5], 30.0),
+            (Word::Acosd, &[0.5], 60.0),
+            (Word::Atand, &[1.0], 45.0),
+            (Word::Sinh, &[0.75], 0.75f64.sinh()),
+            (Word::Cosh, &[0.75], 0.75f64.cosh()),
+            (Word::Tanh, &[0.75], 0.75f64.tanh()),
+            (Word::Asinh, &[0.75], 0.75f64.asinh()),
+            (Word::Acosh, &[2.5], 2.5f64.acosh()),
+            (Word::Atanh, &[0.75], 0.75f64.atanh()),
+            (Word::Sech, &[0.75], 1.0 / 0.75f64.cosh()),
+            (Word::Csch, &[0.75], 1.0 / 0.75f64.sinh()),
+            (Word::Coth, &[0.75], 1.0 / 0.75f64.tanh()),
+            (Word::Asech, &[0.75], (1.0 / 0.75f64).acosh()),
+            (Word::Acsch, &[0.75], (1.0 / 0.75f64).asinh()),
+            (Word::Acoth, &[2.5], (1.0 / 2.5f64).atanh()),
+            (Word::Rad, &[180.0], PI),
+            (Word::Deg, &[PI], 180.0),
+            (Word::Abs, &[-0.75], 0.75),
+            (Word::Floor, &[-2.5], -3.0),
+            (Word::Ceil, &[-2.5], -2.0),
+            (Word::Trunc, &[-2.5], -2.0),
+            (Word::Round, &[2.5], 3.0),
+            (Word::Round, &[1.23456, 2.0], 1.23),
+            (Word::Ulp, &[1.0], f64::EPSILON),
+            (Word::Gamma, &[5.0], 24.0),
+            (Word::Gamma, &[0.5], PI.sqrt()),
+            (Word::Lgamma, &[5.0], 24f64.ln()),
+            (Word::Fact, &[5.0], 120.0),
+            (Word::Sign, &[-2.5], -1.0),
+            (Word::Isnan, &[0.75], 0.0),
+            (Word::Isinf, &[0.75], 0.0),
+            (Word::Isfinite, &[0.75], 1.0),
+            (Word::Pow, &[2.0, 10.0], 1024.0),
+            (Word::Log, &[81.0, 3.0], 4.0),
+            (Word::Hypot, &[3.0, 4.0], 5.0),
+            (Word::Atan2, &[1.0, -1.0], 3.0 * PI / 4.0),
+            (Word::Atan2d, &[1.0, -1.0], 135.0),
+            (Word::Mod, &[7.5, 2.0], 1.5),
+            (Word::Max, &[0.75, 2.5], 2.5),
+            (Word::Max, &[3.0, 7.0, 5.0], 7.0),
+            (Word::Min, &[0.75, 2.5], 0.75),
+            (Word::Min, &[3.0, 7.0, 5.0], 3.0),
+            (Word::Logaddexp, &[0.0, 0.0], LN_2),
+            (Word::Gcd, &[12.0, 18.0], 6.0),
+            (Word::Lcm, &[4.0, 6.0], 12.0),
+            (Word::Ncr, &[5.0, 2.0], 10.0),
+            (Word::Npr, &[5.0, 2.0], 20.0),
+            (Word::Copysign, &[0.75, -1.0], -0.75),
+            (Word::Nthroot, &[-27.0, 3.0], -3.0),
+            (Word::Clamp, &[5.0, 0.0, 3.0], 3.0),
+            (Word::Logsumexp, &[0.0, 0.0, 0.0], 3f64.ln()),
+            (Word::Avg, &[1.0, 2.0, 3.0, 4.0], 2.5),
+            (Word::Sum, &[1.0, 2.0, 3.0], 6.0),
+            (Word::Prod, &[2.0, 3.0, 4.0], 24.0),
+        ];
+
+        let interpreter = Interpreter::new();
+        for (word, args, expected) in cases {
+            let op = Token::Keyword(word.clone());
+            let mut operands = args.iter().map(|&arg| Box::new(Expr::Number(arg)));
+            // Build the same shape of expression as the parser
+            let expr = match (word, args.len()) {
+                (Word::Clamp | Word::Logsumexp | Word::Avg | Word::Sum | Word::Prod, _)
+                | (_, 0 | 3..) => Expr::NaryOp {
+                    op,
+                    args: operands.map(|arg| *arg).collect(),
+                },
+                (_, 1) => Expr::UnaryOp {
+                    op,
+                    operand: operands.next().unwrap(),
+                },
+                (_, 2) => Expr::BinaryOp {
+                    op,
+                    left: operands.next().unwrap(),
+                    right: operands.next().unwrap(),
+                },
+            };
+            let result = interpreter.visit(&expr).unwrap();
+            assert!(
+                result == *expected || (result - expected).abs() <= 1e-12 * expected.abs(),
+                "{:?}{:?} = {}, expected {}",
+                word,
+                args,
+                result,
+                expected
+            );
+        }
+
+        // `rand` is tested separately since its result is random
+        let tested: std::collections::HashSet<&Word> =
+            cases.iter().map(|(word, ..)| word).collect();
+        for word in Word::ALL
+            .iter()
+            .filter(|w| **w != Word::Rand && **w != Word::Nan)
+        {
+            assert!(tested.contains(word), "{:?} is not tested", word);
+        }
+        let nan = Expr::NaryOp {
+            op: Token::Keyword(Word::Nan),
+            args: vec![],
+        };
+        assert!(interpreter.visit(&nan).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_interpret_unsupported_binary() {
+        let input = Box::new(Expr::BinaryOp {
+            op: Token::Comma,
+            left: Box::new(Expr::Number(1.0)),
+            right: Box::new(Expr::Number(2.0)),
+        });
+        let interpreter = Interpreter::new();
+        let err = interpreter.quick_interpret(&input).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported binary operator: Comma"));
+    }
+
+    #[test]
+    fn test_interpret_unsupported_binary_nested() {
+        let input = Box::new(Expr::BinaryOp {
+            op: Token::Plus,
+            left: Box::new(Expr::Number(1.0)),
+            right: Box::new(Expr::BinaryOp {
+                op: Token::LParen,
+                left: Box::new(Expr::Number(2.0)),
+                right: Box::new(Expr::Number(3.0)),
+            }),
+        });
+        let interpreter = Interpreter::new();
+        let err = interpreter.quick_interpret(&input).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported binary operator: LParen"));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(Box::new(Expr::Number(1.0))).unwrap();
+        let snapshot = interpreter.snapshot();
+        interpreter.interpret(Box::new(Expr::Number(2.0))).unwrap();
+        interpreter.restore(snapshot);
+
+        let ans = Expr::Variable("$ans".to_string());
+        assert_eq!(interpreter.visit(&ans).unwrap(), 1.0);
+        let (name, _) = interpreter.interpret(Box::new(Expr::Number(3.0))).unwrap();
+        assert_eq!(name, "$1");
+    }
+
+    fn visit_unary(interpreter: &Interpreter, word: Word, operand: f64) -> Result<f64, CalcError> {
+        interpreter.visit(&Expr::UnaryOp {
+            op: Token::Keyword(word),
+            operand: Box::new(Expr::Number(operand)),
+        })
+    }
+
+    #[test]
+    fn test_interpret_reciprocal_hyperbolic_identities() {
+        let interpreter = Interpreter::new();
+        for x in [-2.5, -0.5, 0.75, 1.0, 3.0] {
+            let coth = visit_unary(&interpreter, Word::Coth, x).unwrap();
+            assert!((coth - x.cosh() / x.sinh()).abs() < 1e-12);
+            let sech = visit_unary(&interpreter, Word::Sech, x).unwrap();
+            assert!((sech - 1.0 / x.cosh()).abs() < 1e-12);
+            let csch = visit_unary(&interpreter, Word::Csch, x).unwrap();
+            assert!((csch - 1.0 / x.sinh()).abs() < 1e-12);
+            let acsch = visit_unary(&interpreter, Word::Acsch, csch).unwrap();
+            assert!((acsch - x).abs() < 1e-12);
+            let acoth = visit_unary(&interpreter, Word::Acoth, coth).unwrap();
+            assert!((acoth - x).abs() < 1e-9);
+            let asech = visit_unary(&interpreter, Word::Asech, sech).unwrap();
+            assert!((asech - x.abs()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_interpret_hyperbolic_poles_default() {
+        let interpreter = Interpreter::new();
+        assert_eq!(
+            visit_unary(&interpreter, Word::Csch, 0.0).unwrap(),
+            f64::INFINITY
+        );
+        assert_eq!(
+            visit_unary(&interpreter, Word::Coth, 0.0).unwrap(),
+            f64::INFINITY
+        );
+        assert!(visit_unary(&interpreter, Word::Acoth, 0.5)
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn test_interpret_hyperbolic_poles_strict() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_strict(true);
+        assert!(visit_unary(&interpreter, Word::Csch, 0.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Coth, 0.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Asech, 0.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Asech, 2.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Acsch, 0.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Acoth, 1.0).is_err());
+        assert!(visit_unary(&interpreter, Word::Acosh, 0.5).is_err());
+        assert!(visit_unary(&interpreter, Word::Atanh, 1.0).is_err());
+        assert_eq!(visit_unary(&interpreter, Word::Asech, 1.0).unwrap(), 0.0);
+        assert!(visit_unary(&interpreter, Word::Sech, 0.0).is_ok());
+    }
 }
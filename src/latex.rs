@@ -0,0 +1,307 @@
+//! Module for rendering an abstract syntax tree as LaTeX math.
+
+use crate::format;
+use crate::parser::{self, Associativity, Expr};
+use crate::printer::{ASSIGN, FACTOR, OR, POSTFIX, PRIMARY, UNARY};
+use crate::scanner::{Token, Word};
+
+/// Constants that are written as symbols instead of numbers.
+const SYMBOLS: &[(Word, &str)] = &[
+    (Word::Pi, "\\pi"),
+    (Word::Tau, "\\tau"),
+    (Word::E, "e"),
+    (Word::Phi, "\\varphi"),
+];
+
+impl Expr {
+    /// Render the expression as LaTeX math, such as `\frac{1}{2} + \sqrt{x}` for `1/2 + sqrt($x)`.
+    ///
+    /// Division is written as a fraction, powers as superscripts, and roots with `\sqrt`.
+    /// Numbers equal to `pi`, `tau`, `e`, or `phi` are written as their symbols,
+    /// since the parser replaces constants with their values.
+    /// Variables are written without their `$`, and names longer than one letter are italicized as words.
+    /// Brackets are only added where precedence requires them, so `(1 + 2)^3` keeps them but `x^2` does not.
+    ///
+    /// ```
+    /// use expressive_calc::ast::{Expr, Token};
+    ///
+    /// let expr = Expr::BinaryOp {
+    ///     op: Token::Slash,
+    ///     left: Box::new(Expr::Number(1.0)),
+    ///     right: Box::new(Expr::Variable("$x".to_string())),
+    /// };
+    /// assert_eq!(expr.to_latex(), "\\frac{1}{x}");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        latex(self, ASSIGN)
+    }
+}
+
+/// Render an expression, adding brackets if it binds looser than `min_precedence`.
+fn latex(expr: &Expr, min_precedence: u8) -> String {
+    let (text, precedence) = latex_unwrapped(expr);
+    if precedence < min_precedence {
+        format!("\\left({}\\right)", text)
+    } else {
+        text
+    }
+}
+
+/// Render a function applied to a list of arguments, such as `\max\left(a, b\right)`.
+fn apply<'a>(function: &str, args: impl IntoIterator<Item = &'a Expr>) -> (String, u8) {
+    let args: Vec<_> = args.into_iter().map(|arg| latex(arg, ASSIGN)).collect();
+    (
+        format!("{}\\left({}\\right)", function, args.join(", ")),
+        PRIMARY,
+    )
+}
+
+/// Returns the LaTeX command for a function, such as `\sin` or `\operatorname{sech}`.
+fn function_name(word: &Word) -> String {
+    match word {
+        Word::Sin
+        | Word::Cos
+        | Word::Tan
+        | Word::Sec
+        | Word::Csc
+        | Word::Cot
+        | Word::Sinh
+        | Word::Cosh
+        | Word::Tanh
+        | Word::Coth
+        | Word::Ln
+        | Word::Max
+        | Word::Min => format!("\\{}", word.name()),
+        Word::Asin => "\\arcsin".to_string(),
+        Word::Acos => "\\arccos".to_string(),
+        Word::Atan => "\\arctan".to_string(),
+        Word::Gamma => "\\Gamma".to_string(),
+        Word::Log10 => "\\log_{10}".to_string(),
+        Word::Log2 => "\\log_{2}".to_string(),
+        _ => format!("\\operatorname{{{}}}", word.name()),
+    }
+}
+
+/// Render a number, using a symbol for well-known constants and `\times 10^{n}` for exponents.
+fn number(n: f64) -> (String, u8) {
+    if n.is_nan() {
+        return ("\\mathrm{NaN}".to_string(), PRIMARY);
+    }
+    let (sign, magnitude, precedence) = match n.is_sign_negative() {
+        true => ("-", -n, UNARY),
+        false => ("", n, PRIMARY),
+    };
+    if magnitude.is_infinite() {
+        return (format!("{}\\infty", sign), precedence);
+    }
+    if let Some((_, symbol)) = SYMBOLS
+        .iter()
+        .find(|(word, _)| word.constant() == Some(magnitude))
+    {
+        return (format!("{}{}", sign, symbol), precedence);
+    }
+    let text = format::format_number(magnitude, None);
+    match text.split_once('e') {
+        Some(("1", exponent)) => (
+            format!("{}10^{{{}}}", sign, exponent),
+            precedence.min(POSTFIX),
+        ),
+        Some((mantissa, exponent)) => (
+            format!("{}{} \\times 10^{{{}}}", sign, mantissa, exponent),
+            precedence.min(FACTOR),
+        ),
+        None => (format!("{}{}", sign, text), precedence),
+    }
+}
+
+/// Render a variable name without its `$`, italicizing names longer than one letter.
+fn variable(name: &str) -> String {
+    let bare = name.strip_prefix('$').unwrap_or(name);
+    if bare.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("\\${}", bare);
+    }
+    match bare.len() {
+        1 => bare.to_string(),
+        _ => format!("\\mathit{{{}}}", bare.replace('_', "\\_")),
+    }
+}
+
+/// Render an expression without outer brackets, returning the text and its precedence.
+fn latex_unwrapped(expr: &Expr) -> (String, u8) {
+    match expr {
+        Expr::Number(n) => number(*n),
+        Expr::Variable(name) => (variable(name), PRIMARY),
+        Expr::UnaryOp { op, operand } => match op {
+            Token::Minus => (format!("-{}", latex(operand, UNARY)), UNARY),
+            Token::Bang => (format!("{}!", latex(operand, PRIMARY)), POSTFIX),
+            Token::Percent => (format!("{}\\%", latex(operand, PRIMARY)), POSTFIX),
+            Token::Degree => (format!("{}^\\circ", latex(operand, PRIMARY)), POSTFIX),
+            Token::Keyword(Word::Sqrt) => {
+                (format!("\\sqrt{{{}}}", latex(operand, ASSIGN)), PRIMARY)
+            }
+            Token::Keyword(Word::Cbrt) => {
+                (format!("\\sqrt[3]{{{}}}", latex(operand, ASSIGN)), PRIMARY)
+            }
+            Token::Keyword(Word::Abs) => (
+                format!("\\left|{}\\right|", latex(operand, ASSIGN)),
+                PRIMARY,
+            ),
+            Token::Keyword(Word::Floor) => (
+                format!("\\left\\lfloor {} \\right\\rfloor", latex(operand, ASSIGN)),
+                PRIMARY,
+            ),
+            Token::Keyword(Word::Ceil) => (
+                format!("\\left\\lceil {} \\right\\rceil", latex(operand, ASSIGN)),
+                PRIMARY,
+            ),
+            Token::Keyword(Word::Exp) => (format!("e^{{{}}}", latex(operand, ASSIGN)), POSTFIX),
+            Token::Keyword(Word::Rad) => {
+                (format!("{}^{{\\circ}}", latex(operand, PRIMARY)), POSTFIX)
+            }
+            Token::Keyword(w) => apply(&function_name(w), [&**operand]),
+            _ => apply(&format!("\\operatorname{{{:?}}}", op), [&**operand]),
+        },
+        Expr::BinaryOp { op, left, right } => match op {
+            Token::Slash => (
+                format!(
+                    "\\frac{{{}}}{{{}}}",
+                    latex(left, ASSIGN),
+                    latex(right, ASSIGN)
+                ),
+                FACTOR,
+            ),
+            Token::Caret | Token::Keyword(Word::Pow) => (
+                format!("{}^{{{}}}", latex(left, PRIMARY), latex(right, ASSIGN)),
+                POSTFIX,
+            ),
+            Token::Keyword(Word::Log) => {
+                let base = latex(right, ASSIGN);
+                apply(&format!("\\log_{{{}}}", base), [&**left])
+            }
+            Token::Keyword(Word::Mod) => binary(left, "\\bmod", FACTOR, right),
+            Token::Keyword(Word::Nthroot) => (
+                format!(
+                    "\\sqrt[{}]{{{}}}",
+                    latex(right, ASSIGN),
+                    latex(left, ASSIGN)
+                ),
+                PRIMARY,
+            ),
+            Token::Keyword(Word::Ncr) => (
+                format!(
+                    "\\binom{{{}}}{{{}}}",
+                    latex(left, ASSIGN),
+                    latex(right, ASSIGN)
+                ),
+                PRIMARY,
+            ),
+            Token::Keyword(w) => apply(&function_name(w), [&**left, &**right]),
+            _ => match parser::binary_operator(op) {
+                Some(operator) => {
+                    let symbol = match op {
+                        Token::Or => "\\lor",
+                        Token::And => "\\land",
+                        Token::Star => "\\cdot",
+                        Token::Percent => "\\bmod",
+                        _ => operator.symbol,
+                    };
+                    match operator.associativity {
+                        Associativity::Left => binary(left, symbol, operator.precedence, right),
+                        Associativity::Right => {
+                            let text = format!(
+                                "{} {} {}",
+                                latex(left, operator.precedence + 1),
+                                symbol,
+                                latex(right, operator.precedence)
+                            );
+                            (text, operator.precedence)
+                        }
+                    }
+                }
+                None => apply(&format!("\\operatorname{{{:?}}}", op), [&**left, &**right]),
+            },
+        },
+        Expr::NaryOp { op, args } => match op {
+            Token::Keyword(w) => apply(&function_name(w), args),
+            _ => apply(&format!("\\operatorname{{{:?}}}", op), args),
+        },
+        Expr::Call { name, args } => apply(
+            &format!("\\operatorname{{{}}}", name.replace('_', "\\_")),
+            args,
+        ),
+        Expr::Assign { name, value } => {
+            (format!("{} = {}", variable(name), latex(value, OR)), ASSIGN)
+        }
+    }
+}
+
+/// Render a left-associative binary operation, so a right operand at the same precedence needs brackets.
+fn binary(left: &Expr, symbol: &str, precedence: u8, right: &Expr) -> (String, u8) {
+    let text = format!(
+        "{} {} {}",
+        latex(left, precedence),
+        symbol,
+        latex(right, precedence + 1)
+    );
+    (text, precedence)
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Expr, Parser};
+    use crate::scanner::Scanner;
+
+    /// Parse an input into an expression.
+    fn parse(input: &str) -> Box<Expr> {
+        let tokens = Scanner::new(input).scan().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_to_latex() {
+        let cases = [
+            ("1/2 + sqrt($x)", "\\frac{1}{2} + \\sqrt{x}"),
+            ("(1+2)^3", "\\left(1 + 2\\right)^{3}"),
+            ("x^2", "x^{2}"),
+            ("2^(3+1)", "2^{3 + 1}"),
+            ("(x/2)^2", "\\left(\\frac{x}{2}\\right)^{2}"),
+            ("-2^2", "-2^{2}"),
+            ("(-2)^2", "\\left(-2\\right)^{2}"),
+            ("pow(x, 2) * 3", "x^{2} \\cdot 3"),
+            ("cbrt(8) - 1", "\\sqrt[3]{8} - 1"),
+            ("nthroot($x + 1, 5)", "\\sqrt[5]{x + 1}"),
+            ("ncr($n, 2)", "\\binom{n}{2}"),
+            ("2 * pi * r", "2 \\cdot \\pi \\cdot r"),
+            ("tau + e + phi", "\\tau + e + \\varphi"),
+            ("1 - (2 - 3)", "1 - \\left(2 - 3\\right)"),
+            (
+                "(1 + 2) * (3 - 4)",
+                "\\left(1 + 2\\right) \\cdot \\left(3 - 4\\right)",
+            ),
+            ("|x - 1| + 3!", "\\left|x - 1\\right| + 3!"),
+            ("90° - x°", "90^\\circ - x^\\circ"),
+            (
+                "sin(x)^2 + cos(x)^2",
+                "\\sin\\left(x\\right)^{2} + \\cos\\left(x\\right)^{2}",
+            ),
+            (
+                "log(8, 2) + log10(x)",
+                "\\log_{2}\\left(8\\right) + \\log_{10}\\left(x\\right)",
+            ),
+            (
+                "radius = 2 * $rate_1",
+                "\\mathit{radius} = 2 \\cdot \\mathit{rate\\_1}",
+            ),
+            ("max(1, 2) % 3", "\\max\\left(1, 2\\right) \\bmod 3"),
+            ("6.02e23 * -inf", "6.02 \\times 10^{23} \\cdot -\\infty"),
+            (
+                "cal($0, 1e-10)",
+                "\\operatorname{cal}\\left(\\$0, 10^{-10}\\right)",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input).to_latex(), expected, "{}", input);
+        }
+    }
+}
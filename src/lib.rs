@@ -1,40 +1,515 @@
+//! A calculator that evaluates math expressions written as text.
+//!
+//! Most programs only need a [`Calculator`], which scans, parses, and evaluates an input in one call,
+//! and stores results between calls:
+//!
+//! ```
+//! use expressive_calc::Calculator;
+//!
+//! let mut calculator = Calculator::new();
+//! assert_eq!(calculator.evaluate("1 + 2").unwrap(), ("$0".to_string(), 3.0));
+//! assert_eq!(calculator.quick_evaluate("$0 * 2").unwrap(), 6.0);
+//! ```
+//!
+//! To analyze or transform an expression without evaluating it, call [`parse`] to get its abstract syntax tree.
+//! The types of the tree are in the [`ast`] module, and it can be walked by implementing [`ast::Visitor`].
+//! A tree can be evaluated later with [`Calculator::quick_evaluate_expr`].
+
+pub mod approx;
+pub mod ast;
 mod calc_error;
+mod cancel;
+mod compiled;
+#[cfg(test)]
+mod differential;
+mod evaluation;
+mod fingerprint;
+pub mod format;
+mod history;
 mod interpreter;
+mod latex;
+mod math;
 mod parser;
+pub mod prelude;
+mod printer;
+mod rng;
+mod rpn;
 mod scanner;
+mod state;
+mod table;
+#[cfg(feature = "serde")]
+mod values;
+
+pub use calc_error::{CalcError, ErrorKind, Warning};
+pub use cancel::CancellationToken;
+pub use compiled::CompiledExpression;
+pub use evaluation::{Evaluation, Timings};
+pub use history::HistoryEntry;
+pub use interpreter::{AngleMode, ResultOverflow};
+pub use scanner::{Arity, Span, Word};
+pub use state::CalcState;
+
+// Compile and run the README examples as doctests, so they stay covered by the prelude.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+/// Parse an input into an abstract syntax tree, without evaluating it.
+///
+/// The input is scanned and parsed with the default options of [`Calculator::new`].
+/// Constants such as `pi` are replaced by their values, but variables and functions are left for evaluation.
+///
+/// ```
+/// use expressive_calc::ast::{Expr, Token};
+///
+/// let expr = expressive_calc::parse("$x * 2").unwrap();
+/// assert_eq!(
+///     expr,
+///     Expr::BinaryOp {
+///         op: Token::Star,
+///         left: Box::new(Expr::Variable("$x".to_string())),
+///         right: Box::new(Expr::Number(2.0)),
+///     }
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if an invalid character is encountered, or if the input cannot be parsed.
+pub fn parse(input: &str) -> Result<ast::Expr, CalcError> {
+    Calculator::new().parse(input).map(|expr| *expr)
+}
+
+/// Returns the name, value, and description of every constant that can be used in expressions.
+///
+/// The list includes `pi`, `tau`, `e`, `phi`, and `inf`, among others, and is suitable for a constants palette.
+/// It is the same table the parser reads, so each name evaluates to exactly the listed value.
+///
+/// ```
+/// let (name, value, _) = expressive_calc::constants()[1];
+/// assert_eq!((name, value), ("pi", std::f64::consts::PI));
+/// ```
+pub fn constants() -> &'static [(&'static str, f64, &'static str)] {
+    scanner::CONSTANTS
+}
+
+/// Returns the name, value, and description of every physical constant, such as the speed of light `c`.
+///
+/// Values are in SI units, from CODATA 2018.
+/// These names can only be used in expressions after [`Calculator::set_physical_constants`] is enabled.
+///
+/// ```
+/// let (name, value, _) = expressive_calc::physical_constants()[0];
+/// assert_eq!((name, value), ("c", 299_792_458.0));
+/// ```
+pub fn physical_constants() -> &'static [(&'static str, f64, &'static str)] {
+    scanner::PHYSICAL_CONSTANTS
+}
 
-pub use calc_error::CalcError;
+/// Add a warning to `warnings` for each number literal that lost significant digits when it was parsed.
+fn check_literals(
+    input: &str,
+    tokens: &[scanner::Token],
+    spans: &[Span],
+    warnings: &mut Vec<Warning>,
+) {
+    for (token, span) in tokens.iter().zip(spans) {
+        if let scanner::Token::Number(n) = token {
+            if scanner::loses_precision(&input[span.byte_start..span.byte_end], *n) {
+                warnings.push(Warning::new(
+                    "Number literal has more digits than can be represented",
+                    *span,
+                ));
+            }
+        }
+    }
+}
 
 /// A simple calculator that can evaluate expressions.
+///
+/// Cloning a calculator forks the session: the clone starts with a copy of the variables, results,
+/// history, tables, and settings, and the two evolve independently afterward.
+/// Functions registered with [`Calculator::register_function`] cannot be copied,
+/// so the clone shares them with the original.
+/// Registering or unregistering a function on one calculator does not affect the other,
+/// but a closure that keeps its own state, such as a counter in an `AtomicUsize`, sees calls from both.
+/// The clone also copies the state of the random number generator, so `rand` returns the same sequence
+/// in both calculators until one of them is reseeded with [`Calculator::seed_rng`].
+#[derive(Clone)]
 pub struct Calculator {
     interpreter: interpreter::Interpreter,
+    lenient: bool,
+    single_letter_constants: bool,
+    physical_constants: bool,
+    display_precision: Option<usize>,
+    show_signed_zero: bool,
+    truthy: bool,
+    implicit_multiplication: bool,
+    percent_suffix: bool,
+    max_literal_length: usize,
+    max_quote_length: usize,
+    d_exponent: bool,
+    case_insensitive_keywords: bool,
+    warnings: Vec<Warning>,
+    history: Vec<HistoryEntry>,
 }
 impl Calculator {
     /// Create a new calculator.
     pub fn new() -> Self {
         Self {
             interpreter: interpreter::Interpreter::new(),
+            lenient: false,
+            single_letter_constants: true,
+            physical_constants: false,
+            display_precision: Some(format::DEFAULT_PRECISION),
+            show_signed_zero: false,
+            truthy: false,
+            implicit_multiplication: false,
+            percent_suffix: false,
+            max_literal_length: scanner::DEFAULT_MAX_LITERAL_LENGTH,
+            max_quote_length: calc_error::DEFAULT_MAX_QUOTE_LENGTH,
+            d_exponent: false,
+            case_insensitive_keywords: false,
+            warnings: Vec::new(),
+            history: Vec::new(),
         }
     }
 
+    /// Create a scanner for an input string, configured with the calculator's options.
+    fn scanner<'a>(&self, input: &'a str) -> scanner::Scanner<'a> {
+        scanner::Scanner::new(input)
+            .lenient(self.lenient)
+            .single_letter_constants(self.single_letter_constants)
+            .physical_constants(self.physical_constants)
+            .max_literal_length(self.max_literal_length)
+            .d_exponent(self.d_exponent)
+            .case_insensitive_keywords(self.case_insensitive_keywords)
+    }
+
+    /// Scan and parse an input string into an abstract syntax tree.
+    ///
+    /// The scanner and parser are configured with the calculator's options.
+    /// Warnings about the input are discarded.
+    fn parse(&self, input: &str) -> Result<Box<parser::Expr>, CalcError> {
+        self.parse_with_warnings(input, &mut Vec::new())
+    }
+
+    /// Scan and parse an input string, replacing the stored warnings with the warnings about it.
+    ///
+    /// Only methods that store results record warnings, so that evaluating through `&self` changes nothing.
+    fn parse_and_record(&mut self, input: &str) -> Result<Box<parser::Expr>, CalcError> {
+        let mut warnings = Vec::new();
+        let result = self.parse_with_warnings(input, &mut warnings);
+        self.warnings = warnings;
+        result
+    }
+
+    /// Scan and parse an input string into an abstract syntax tree, adding warnings about the input to `warnings`.
+    fn parse_with_warnings(
+        &self,
+        input: &str,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Box<parser::Expr>, CalcError> {
+        let (tokens, spans) = self.scanner(input).scan_with_spans()?;
+        check_literals(input, &tokens, &spans, warnings);
+
+        let parser = parser::Parser::with_spans(&tokens, spans)
+            .implicit_multiplication(self.implicit_multiplication)
+            .percent_suffix(self.percent_suffix)
+            .max_quote_length(self.max_quote_length);
+        parser.parse()
+    }
+
+    /// Scan an input string in reverse Polish notation and build an abstract syntax tree.
+    ///
+    /// The scanner is configured with the calculator's options, and warnings are recorded as in [`Calculator::parse_and_record`].
+    fn parse_rpn(&mut self, input: &str) -> Result<Box<parser::Expr>, CalcError> {
+        self.warnings.clear();
+        let (tokens, spans) = self.scanner(input).scan_with_spans()?;
+        check_literals(input, &tokens, &spans, &mut self.warnings);
+        rpn::parse(&tokens, &spans)
+    }
+
+    /// Returns the warnings for the most recently evaluated input.
+    ///
+    /// Warnings are replaced each time an input is evaluated with a method that stores results,
+    /// such as [`Calculator::evaluate`], whether or not evaluation succeeds.
+    /// Methods that take `&self`, such as [`Calculator::quick_evaluate`], do not change them.
+    /// Currently, a warning is given for each number literal with more significant digits than
+    /// an f64 can represent, such as `12345678901234567890`.
+    /// Such literals are rounded to the nearest representable value.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.clone()
+    }
+
+    /// Scan an input string and describe its tokens in a stable format, one token per line.
+    ///
+    /// Each line has the kind of the token, its value if it has one, and its character span,
+    /// such as `NUMBER 1.5 @3..6` or `KEYWORD sqrt @0..4`.
+    /// The format is intended for snapshot tests and is only changed deliberately,
+    /// unlike the `Debug` output of tokens. The scanner is configured with the calculator's options.
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let dump = Calculator::new().dump_tokens("sqrt(2)").unwrap();
+    /// assert_eq!(dump, "KEYWORD sqrt @0..4\nLPAREN @4..5\nNUMBER 2 @5..6\nRPAREN @6..7");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the input cannot be scanned.
+    pub fn dump_tokens(&self, input: &str) -> Result<String, CalcError> {
+        let (tokens, spans) = self.scanner(input).scan_with_spans()?;
+        let lines: Vec<String> = tokens
+            .iter()
+            .zip(&spans)
+            .map(|(token, span)| format!("{} @{}..{}", token.dump(), span.start, span.end))
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
     /// Evaluate an expression, storing state between calls.
     ///
     /// This function will scan the input string, parse the tokens, and interpret the expression.
     /// The interpreter will store the result in a variable that can be used in future expressions.
     /// Variables are named based on the order: `$0`, `$1`, `$2`, etc.
     /// The variable name and result are returned as a tuple.
+    /// The input is also recorded in [`Calculator::history`].
     ///
     /// # Errors
     ///
     /// Returns a [`CalcError`] if an invalid character is encountered, or if an expression cannot be parsed.
     pub fn evaluate(&mut self, input: &str) -> Result<(String, f64), CalcError> {
-        let scanner = scanner::Scanner::new(input);
-        let tokens = scanner.scan()?;
+        let evaluation = self.evaluate_details(input, false)?;
+        Ok((evaluation.name, evaluation.value))
+    }
+
+    /// Evaluate an expression, storing state between calls, and return the details of the evaluation.
+    ///
+    /// This function behaves like [`Calculator::evaluate`], but returns an [`Evaluation`]
+    /// with the assigned name and result, the warnings about the input, the expression as normalized text,
+    /// the variables it depends on, and the time spent parsing and evaluating it.
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let mut calculator = Calculator::new();
+    /// calculator.set_variable("$rate", 0.5).unwrap();
+    /// let evaluation = calculator.evaluate_full("2*(1+$rate)").unwrap();
+    /// assert_eq!((evaluation.name(), evaluation.value()), ("$0", 3.0));
+    /// assert_eq!(evaluation.normalized(), Some("2 * (1 + $rate)"));
+    /// assert_eq!(evaluation.dependencies().unwrap(), ["$rate"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, or if an expression cannot be parsed.
+    pub fn evaluate_full(&mut self, input: &str) -> Result<Evaluation, CalcError> {
+        self.evaluate_details(input, true)
+    }
+
+    /// Evaluate an expression, storing state between calls and recording it in the history.
+    ///
+    /// If `full` is false, only the name, value, and warnings are collected.
+    fn evaluate_details(&mut self, input: &str, full: bool) -> Result<Evaluation, CalcError> {
+        let start = full.then(Instant::now);
+        let expr = self.parse_and_record(input)?;
+        let parse_time = start.map(|start| start.elapsed());
+        let assignment = matches!(*expr, parser::Expr::Assign { .. });
+        let normalized = full.then(|| printer::to_infix(&expr));
+        let dependencies = full.then(|| evaluation::dependencies(&expr));
+
+        let start = full.then(Instant::now);
+        let (name, value) = self.interpreter.interpret(expr)?;
+        let timings = parse_time
+            .zip(start)
+            .map(|(parse_time, start)| Timings::new(parse_time, start.elapsed()));
+        self.history.push(HistoryEntry::new(input, &name, value));
+        Ok(Evaluation {
+            name,
+            value,
+            assignment,
+            warnings: self.warnings(),
+            normalized,
+            dependencies,
+            timings,
+        })
+    }
+
+    /// Evaluate an expression, storing state between calls, unless it is cancelled first.
+    ///
+    /// This function behaves like [`Calculator::evaluate`], but the evaluation stops soon after
+    /// `token` is cancelled, such as from another thread that enforces a time limit.
+    /// The token is checked periodically while the expression is evaluated.
+    /// A cancelled evaluation stores no result.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] of kind [`ErrorKind::Cancelled`] if the token is cancelled
+    /// before the evaluation finishes, as well as any error [`Calculator::evaluate`] can return.
+    pub fn evaluate_with_cancel(
+        &mut self,
+        input: &str,
+        token: &CancellationToken,
+    ) -> Result<(String, f64), CalcError> {
+        let expr = self.parse_and_record(input)?;
+        self.interpreter.set_cancellation(Some(token.clone()));
+        let result = self.interpreter.interpret(expr);
+        self.interpreter.set_cancellation(None);
+        let (name, result) = result?;
+        self.history.push(HistoryEntry::new(input, &name, result));
+        Ok((name, result))
+    }
+
+    /// Evaluate an expression written in reverse Polish notation, storing state between calls.
+    ///
+    /// Operands come before their operators, so `3 4 + 2 *` is 14, `9 sqrt` is 3, and `2 3 pow` is 8.
+    /// The input is read with a stack instead of the infix parser, in the notation written by
+    /// [`Expr::to_rpn`](crate::ast::Expr::to_rpn): `-` is always subtraction and `neg` is unary minus,
+    /// and named functions are followed by their argument count, such as `1 2 cal(2)`.
+    /// An assignment is written as the value, the name, and `=`, such as `2 radius =`.
+    /// Results are stored in `$n` and `$ans` and recorded in the history, as with [`Calculator::evaluate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, if an operator has too few operands,
+    /// if more than one value is left at the end, or if the evaluation fails.
+    pub fn evaluate_rpn(&mut self, input: &str) -> Result<(String, f64), CalcError> {
+        let expr = self.parse_rpn(input)?;
+        let (name, result) = self.interpreter.interpret(expr)?;
+        self.history.push(HistoryEntry::new(input, &name, result));
+        Ok((name, result))
+    }
+
+    /// Evaluate an expression, storing state between calls, and render the result for display.
+    ///
+    /// This function behaves like [`Calculator::evaluate`], but also returns the result
+    /// formatted as a human-friendly string.
+    /// By default, the result is rounded to 15 significant digits, so `0.1 + 0.2` is displayed as `0.3`.
+    /// Very large or very small magnitudes are displayed in scientific notation, such as `1e20`.
+    /// The precision can be changed with [`Calculator::set_display_precision`].
+    /// Negative zero is displayed as `0`, unless [`Calculator::set_show_signed_zero`] is enabled,
+    /// but the returned value is not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, or if an expression cannot be parsed.
+    pub fn evaluate_display(&mut self, input: &str) -> Result<(String, f64, String), CalcError> {
+        let (name, value) = self.evaluate(input)?;
+        // Negative zero is displayed as 0, but the result keeps its sign
+        let shown = if format::is_negative_zero(value) && !self.show_signed_zero {
+            0.0
+        } else {
+            value
+        };
+        let display = format::format_number(shown, self.display_precision);
+        Ok((name, value, display))
+    }
+
+    /// Replace the stored variables in an expression with their values, without evaluating it.
+    ///
+    /// The expression is parsed, then every variable with a stored value is replaced by a number,
+    /// and the result is printed back as text, for use in systems that cannot evaluate variables.
+    /// Variables without a stored value are left as they are.
+    /// Numbers are printed with full precision, so the text evaluates to the same result.
+    /// The text is normalized: operators are surrounded by spaces, unneeded parentheses are removed,
+    /// and bars and other shorthand are written as function calls, such as `abs(x)`.
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let mut calculator = Calculator::new();
+    /// calculator.set_variable("$rate", 0.07).unwrap();
+    /// let text = calculator.substitute("100 * (1 + $rate)").unwrap();
+    /// assert_eq!(text, "100 * (1 + 0.07)");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, or if the expression cannot be parsed.
+    pub fn substitute(&self, input: &str) -> Result<String, CalcError> {
+        let expr = self.parse(input)?;
+        Ok(printer::to_infix(&self.interpreter.substitute(*expr)))
+    }
 
-        let parser = parser::Parser::new(&tokens);
-        let expr = parser.parse()?;
+    /// Evaluate multiple statements in order, storing state between them.
+    ///
+    /// Each statement is evaluated as if by [`Calculator::evaluate`], so later statements
+    /// may refer to the results of earlier statements.
+    /// The variable names and results of each statement are returned in order.
+    /// If a statement fails, evaluation stops, but the results of the preceding statements are kept.
+    /// Use [`Calculator::evaluate_all_atomic`] to discard them instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] for the first statement that fails.
+    /// The index of the statement is available from [`CalcError::statement`].
+    pub fn evaluate_all(&mut self, statements: &[&str]) -> Result<Vec<(String, f64)>, CalcError> {
+        statements
+            .iter()
+            .enumerate()
+            .map(|(i, statement)| self.evaluate(statement).map_err(|e| e.with_statement(i)))
+            .collect()
+    }
+
+    /// Evaluate a script of statements separated by newlines or semicolons, storing state between them.
+    ///
+    /// The statements are evaluated in order as if by [`Calculator::evaluate_all`],
+    /// so `"a = 2\nb = 3\na * b"` returns the results of all three statements.
+    /// Comments starting with `#` or `//` run to the end of the line.
+    /// Blank lines, comment-only lines, and empty statements are skipped.
+    /// A script with a single statement behaves exactly like [`Calculator::evaluate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] for the first statement that fails; the preceding results are kept.
+    /// The line number is available from [`CalcError::line`],
+    /// and the index of the statement among the non-empty statements from [`CalcError::statement`].
+    /// The position of the error is relative to the start of the failing statement.
+    pub fn evaluate_script(&mut self, input: &str) -> Result<Vec<(String, f64)>, CalcError> {
+        let statements = input.lines().enumerate().flat_map(|(line, text)| {
+            scanner::strip_comment(text)
+                .split(';')
+                .map(str::trim)
+                .filter(|statement| !statement.is_empty())
+                .map(move |statement| (line + 1, statement))
+        });
+        statements
+            .enumerate()
+            .map(|(i, (line, statement))| {
+                self.evaluate(statement)
+                    .map_err(|e| e.with_statement(i).with_line(line))
+            })
+            .collect()
+    }
 
-        Ok(self.interpreter.interpret(expr)?)
+    /// Evaluate multiple statements in order, storing state only if every statement succeeds.
+    ///
+    /// This function behaves like [`Calculator::evaluate_all`], except that if any statement fails,
+    /// the calculator is left exactly as it was before the call.
+    /// No `$n` variables are created, `$ans` is not changed, and nothing is added to the history.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] for the first statement that fails.
+    /// The index of the statement is available from [`CalcError::statement`].
+    pub fn evaluate_all_atomic(
+        &mut self,
+        statements: &[&str],
+    ) -> Result<Vec<(String, f64)>, CalcError> {
+        let snapshot = self.interpreter.snapshot();
+        let history_len = self.history.len();
+        let results = self.evaluate_all(statements);
+        if results.is_err() {
+            self.interpreter.restore(snapshot);
+            self.history.truncate(history_len);
+        }
+        results
     }
 
     /// Evaluate an expression without storing state.
@@ -47,141 +522,3446 @@ impl Calculator {
     ///
     /// Returns a [`CalcError`] if an invalid character is encountered, or if an expression cannot be parsed.
     pub fn quick_evaluate(&self, input: &str) -> Result<f64, CalcError> {
-        let scanner = scanner::Scanner::new(input);
-        let tokens = scanner.scan()?;
+        let expr = self.parse(input)?;
 
-        let parser = parser::Parser::new(&tokens);
-        let expr = parser.parse()?;
+        self.interpreter.quick_interpret(&expr)
+    }
 
-        Ok(self.interpreter.quick_interpret(expr)?)
+    /// Evaluate an expression that has already been parsed, without storing state.
+    ///
+    /// This is useful for expressions that were built or transformed as an [`Expr`](crate::ast::Expr),
+    /// or, with the `serde` feature, received in serialized form from another process.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the evaluation fails, such as for an unknown variable.
+    pub fn quick_evaluate_expr(&self, expr: parser::Expr) -> Result<f64, CalcError> {
+        self.interpreter.quick_interpret(&expr)
     }
 
-    /// Reset the calculator, clearing all stored state.
+    /// Scan and parse an expression once, so it can be evaluated many times.
     ///
-    /// This function resets the interpreter.
-    /// All stored variables are cleared, and the variable count is reset to zero.
-    pub fn reset(&mut self) {
-        self.interpreter.reset();
+    /// The returned [`CompiledExpression`] can be evaluated with [`Calculator::evaluate_compiled`]
+    /// or [`Calculator::evaluate_compiled_with`] without scanning or parsing the input again.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, or if the expression cannot be parsed.
+    pub fn compile(&self, input: &str) -> Result<CompiledExpression, CalcError> {
+        let expr = self.parse(input)?;
+        Ok(CompiledExpression::new(input, *expr))
     }
-}
 
-// MARK: Tests
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Evaluate a compiled expression without storing state.
+    ///
+    /// This gives the same result as [`Calculator::quick_evaluate`] on the input the expression was compiled from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the evaluation fails, such as for an unknown variable.
+    pub fn evaluate_compiled(&self, compiled: &CompiledExpression) -> Result<f64, CalcError> {
+        self.interpreter.quick_interpret(compiled.expr())
+    }
 
-    #[test]
-    fn test_evaluate() {
-        let input = "1";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 1.0);
+    /// Evaluate a compiled expression without storing state, using values in `vars` for some variables.
+    ///
+    /// Variables are looked up in `vars` first, then in the calculator's stored variables,
+    /// as with [`Calculator::quick_evaluate_shadowed`].
+    /// This is useful for evaluating the same expression over many values of a loop variable.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the evaluation fails,
+    /// including if a variable is in neither `vars` nor the calculator.
+    pub fn evaluate_compiled_with(
+        &self,
+        compiled: &CompiledExpression,
+        vars: &HashMap<String, f64>,
+    ) -> Result<f64, CalcError> {
+        self.interpreter
+            .quick_interpret_shadowed(compiled.expr(), vars)
     }
 
-    #[test]
-    fn test_evaluate_addition() {
-        let input = "1 + 2";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 3.0);
+    /// Evaluate an expression without storing state, and convert the result to a boolean.
+    ///
+    /// By default, the result must be exactly `1` for true or `0` for false,
+    /// as produced by logical operators like `and` and `or`.
+    /// If truthy conversion is enabled with [`Calculator::set_truthy`], any nonzero result is true.
+    /// NaN is never converted.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the input cannot be evaluated, or if the result is not boolean-like.
+    pub fn evaluate_bool(&self, input: &str) -> Result<bool, CalcError> {
+        let result = self.quick_evaluate(input)?;
+        if result == 1.0 || (self.truthy && result != 0.0 && !result.is_nan()) {
+            Ok(true)
+        } else if result == 0.0 {
+            Ok(false)
+        } else {
+            Err(CalcError::new(
+                &format!(
+                    "Result is not a boolean: {}",
+                    format::format_number(result, self.display_precision)
+                ),
+                None,
+            ))
+        }
     }
 
-    #[test]
-    fn test_evaluate_subtraction() {
-        let input = "1 - 2";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, -1.0);
+    /// Evaluate an expression without storing state, using hypothetical values for some variables.
+    ///
+    /// Variables are looked up in `shadow` first, then in the calculator's stored variables,
+    /// so `shadow` can override existing variables like `$0` or provide new ones.
+    /// Neither the calculator nor `shadow` is changed.
+    /// This is useful for a template like `$price * (1 + $tax_rate)` evaluated against many records,
+    /// and works on a fresh calculator with no stored variables at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the input cannot be evaluated,
+    /// including if a variable is in neither `shadow` nor the calculator.
+    pub fn quick_evaluate_shadowed(
+        &self,
+        input: &str,
+        shadow: &HashMap<String, f64>,
+    ) -> Result<f64, CalcError> {
+        let expr = self.parse(input)?;
+        self.interpreter.quick_interpret_shadowed(&expr, shadow)
     }
 
-    #[test]
-    fn test_evaluate_multiplication() {
-        let input = "2 * 3";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 6.0);
+    /// Evaluate an expression without storing state, collecting the result in a scratch map instead.
+    ///
+    /// The expression can use the calculator's variables as well as any variables in `out`.
+    /// If a name is in both, the value in `out` is used.
+    /// The result is inserted into `out` under `name`, which must be a valid variable name
+    /// like `$x` or `x`. Numbered variables like `$0` are reserved for results and cannot be used.
+    /// The calculator itself is never changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is invalid or if the input cannot be evaluated.
+    /// On error, `out` is left unchanged.
+    pub fn quick_evaluate_into(
+        &self,
+        input: &str,
+        out: &mut HashMap<String, f64>,
+        name: &str,
+    ) -> Result<f64, CalcError> {
+        let expr = self.parse(input)?;
+        self.interpreter.quick_interpret_into(expr, out, name)
     }
 
-    #[test]
-    fn test_evaluate_division() {
-        let input = "6 / 4";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 1.5);
+    /// Enable or disable strict mode.
+    ///
+    /// By default, functions evaluated outside of their domain produce non-finite results,
+    /// such as `inf` for `coth(0)`.
+    /// In strict mode, these evaluations return a [`CalcError`] instead.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.interpreter.set_strict(strict);
     }
 
-    #[test]
-    fn test_evaluate_multiple_operations() {
-        let input = "1 + 2 * 3";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 7.0);
+    /// Enable or disable strict division.
+    ///
+    /// By default, `1 / 0` evaluates to `inf` and `0 / 0` evaluates to NaN.
+    /// When enabled, division by zero returns a [`CalcError`] instead.
+    /// This applies to the `/` and `%` operators and the `mod` function.
+    pub fn set_strict_division(&mut self, strict_division: bool) {
+        self.interpreter.set_strict_division(strict_division);
     }
 
-    #[test]
-    fn test_evaluate_parentheses() {
-        let input = "(1 + 2) * 3";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 9.0);
+    /// Set the maximum number of nodes visited while evaluating one expression.
+    ///
+    /// Every number, variable, operator, and function call in an expression is a node.
+    /// If an expression has more nodes than the budget allows,
+    /// an [`ErrorKind::Budget`] error is returned instead of a result.
+    /// The default is 1,000,000 nodes, which is large enough for any expression typed by hand.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.interpreter.set_max_nodes(max_nodes);
     }
 
-    #[test]
-    fn test_inf() {
-        let input = "inf";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, f64::INFINITY);
+    /// Set the maximum number of arguments in one function call.
+    ///
+    /// Calls with more arguments return an [`ErrorKind::Budget`] error before any argument is evaluated.
+    /// The default is 10,000 arguments.
+    pub fn set_max_arguments(&mut self, max_arguments: usize) {
+        self.interpreter.set_max_arguments(max_arguments);
     }
 
-    #[test]
-    fn test_unary_func() {
-        let input = "sqrt(9)";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 3.0);
+    /// Set the unit of angles used by trigonometric functions.
+    ///
+    /// With [`AngleMode::Degrees`], `sin`, `cos`, `tan`, `sec`, `csc`, and `cot` take their argument in degrees,
+    /// and their inverses and `atan2` return their result in degrees.
+    /// They are computed like `sind` and `asind`, so special angles are exact, as in `tan(45)` and `asin(0.5)`.
+    /// Hyperbolic functions are unaffected, and `rad` and `deg` always convert between the two units.
+    /// The degree suffix `°` converts degrees to the angle unit, so `sin(90°)` is `1` in either mode.
+    /// The default is [`AngleMode::Radians`].
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.interpreter.set_angle_mode(angle_mode);
     }
 
-    #[test]
-    fn test_binary_func() {
-        let input = "pow(2, 3)";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 8.0);
+    /// Seed the pseudo-random number generator used by `rand`.
+    ///
+    /// After seeding, the same expressions produce the same sequence of random numbers.
+    /// Without a seed, the generator is seeded differently each time a calculator is created.
+    /// Clones of a calculator continue the same sequence independently.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.interpreter.seed_rng(seed);
     }
 
-    #[test]
-    fn test_abs_bars() {
-        let input = "|-42|";
-        let calculator = Calculator::new();
-        let result = calculator.quick_evaluate(input).unwrap();
-        assert_eq!(result, 42.0);
+    /// Set the maximum number of numbered results, and what happens when it is reached.
+    ///
+    /// Results are numbered from `$0` up to one less than `max_results`.
+    /// With [`ResultOverflow::Error`], evaluating after the last number is used returns an
+    /// [`ErrorKind::Budget`] error until [`Calculator::clear_history`] or [`Calculator::reset`] is called.
+    /// With [`ResultOverflow::Wrap`], numbering starts from `$0` again and each new result
+    /// replaces the oldest one, so at most `max_results` numbered variables are kept.
+    /// A result removed with [`Calculator::remove_variable`] stays removed until its number is reused.
+    /// The maximum is at least one. The default is no limit, with [`ResultOverflow::Error`].
+    pub fn set_max_results(&mut self, max_results: usize, overflow: ResultOverflow) {
+        self.interpreter.set_max_results(max_results, overflow);
     }
 
-    #[test]
-    fn test_state() {
-        let input = "1 + 2";
-        let mut calculator = Calculator::new();
-        let result = calculator.evaluate(input).unwrap();
-        assert_eq!(result, ("$0".to_string(), 3.0));
+    /// Set the maximum number of characters of user input quoted in an error message.
+    ///
+    /// Some error messages quote part of the input, such as the name of an unknown function.
+    /// Longer fragments are truncated and followed by an ellipsis, so that very long input
+    /// does not produce very long error messages. The default is 64 characters.
+    pub fn set_max_quote_length(&mut self, max_quote_length: usize) {
+        self.max_quote_length = max_quote_length;
+        self.interpreter.set_max_quote_length(max_quote_length);
+    }
 
-        let input = "$0 * 3";
-        let result = calculator.evaluate(input).unwrap();
-        assert_eq!(result, ("$1".to_string(), 9.0));
+    /// Enable or disable implicit multiplication of adjacent factors.
+    ///
+    /// When enabled, a factor followed by a parenthesis, function, constant, or variable is multiplied by it,
+    /// so `2pi`, `3(4 + 5)`, `(1 + 2)(3 + 4)`, and `2sqrt(9)` are valid.
+    /// Implicit multiplication has the same precedence as `*`, so `2^3x` is `(2^3) * x`.
+    /// Two adjacent numbers, such as `1 2`, are still an error.
+    /// A number directly followed by `e` is scientific notation, so `2e` is an error; write `2 e` instead.
+    /// Implicit multiplication is disabled by default.
+    pub fn set_implicit_multiplication(&mut self, enabled: bool) {
+        self.implicit_multiplication = enabled;
     }
 
-    #[test]
-    fn test_reset() {
-        let input = "1 + 2";
-        let mut calculator = Calculator::new();
-        let result = calculator.evaluate(input).unwrap();
-        assert_eq!(result, ("$0".to_string(), 3.0));
+    /// Enable or disable `%` as a percent suffix, so `200 * 15%` is `200 * 0.15`.
+    ///
+    /// When enabled, a `%` is a percent suffix if the next token cannot start an operand,
+    /// and modulo otherwise. Tokens that start an operand are numbers, variables, names,
+    /// functions, constants, `(`, and `|`.
+    /// So `10 % 3` is modulo, while `10%`, `10% * 3`, `(10 + 5)%`, and `50% + 1` are percentages.
+    /// Because `+` and `-` do not start an operand, `10 % -3` is `10% - 3`.
+    /// Modulo by a negative number can still be written as `10 % (-3)` or `mod(10, -3)`.
+    /// The percent suffix is disabled by default, so every `%` is modulo.
+    pub fn set_percent_suffix(&mut self, enabled: bool) {
+        self.percent_suffix = enabled;
+    }
 
-        calculator.reset();
-        assert!(matches!(calculator.quick_evaluate("$0"), Err(_)));
+    /// Set the maximum number of characters in a number literal.
+    ///
+    /// Longer literals return an error as soon as the limit is exceeded,
+    /// so pasting a very long number does not take long to reject.
+    /// The default is 1000 characters.
+    pub fn set_max_literal_length(&mut self, max_literal_length: usize) {
+        self.max_literal_length = max_literal_length;
+    }
 
-        let input = "1 + 3";
-        let result = calculator.evaluate(input).unwrap();
-        assert_eq!(result, ("$0".to_string(), 4.0));
+    /// Enable or disable `d` and `D` as exponent markers in numbers, as in `1.5d-3`.
+    ///
+    /// This notation is used by Fortran and related tools, and means the same as `1.5e-3`.
+    /// When enabled, a `d` directly after a number with no exponent digits, as in `1d`, is an error.
+    /// Keywords starting with `d`, such as `deg`, are not affected.
+    /// Disabled by default.
+    pub fn set_d_exponent(&mut self, enabled: bool) {
+        self.d_exponent = enabled;
+    }
+
+    /// Enable or disable recognizing reserved words regardless of case, as in `SQRT(9)`, `Pi`, or `LOG10(x)`.
+    ///
+    /// Names that are not reserved words keep their case, so `Radius` and `radius` are still different variables,
+    /// and errors about unknown names show them as written.
+    /// Disabled by default, so reserved words must be lowercase.
+    pub fn set_case_insensitive_keywords(&mut self, enabled: bool) {
+        self.case_insensitive_keywords = enabled;
+    }
+
+    /// Enable or disable counting how many times each keyword function is evaluated.
+    ///
+    /// Functions are counted each time they are evaluated, not when they are parsed,
+    /// so a function in a branch skipped by `and` or `or` is not counted.
+    /// Evaluations by any method are counted, including [`Calculator::quick_evaluate`].
+    /// Constants such as `pi` are not counted.
+    /// Counting is disabled by default, in which case no counts are recorded.
+    pub fn set_usage_stats(&mut self, enabled: bool) {
+        self.interpreter.set_track_usage(enabled);
+    }
+
+    /// Get the number of times each keyword function has been evaluated while counting was enabled.
+    ///
+    /// See [`Calculator::set_usage_stats`]. Functions that were never evaluated are not included.
+    pub fn usage_stats(&self) -> HashMap<Word, u64> {
+        self.interpreter.usage_stats()
+    }
+
+    /// Reset all counts returned by [`Calculator::usage_stats`] to zero.
+    pub fn reset_usage_stats(&mut self) {
+        self.interpreter.reset_usage_stats();
+    }
+
+    /// Enable or disable truthy conversion in [`Calculator::evaluate_bool`].
+    ///
+    /// When enabled, any nonzero result other than NaN is converted to true.
+    /// When disabled, only `1` and `0` are converted. Truthy conversion is disabled by default.
+    pub fn set_truthy(&mut self, truthy: bool) {
+        self.truthy = truthy;
+    }
+
+    /// Set the number of significant digits used by [`Calculator::evaluate_display`].
+    ///
+    /// If `None`, results are displayed with full precision, using the shortest
+    /// representation that round-trips to the same value.
+    /// The default is 15 significant digits.
+    pub fn set_display_precision(&mut self, precision: Option<usize>) {
+        self.display_precision = precision;
+    }
+
+    /// Set whether [`Calculator::evaluate_display`] displays negative zero as `-0`.
+    ///
+    /// Results such as `-1 * 0` and `1 / -inf` are negative zero.
+    /// By default, negative zero is displayed as `0`.
+    /// Either way, results keep their sign, so `1 / $ans` is still negative infinity afterwards.
+    /// Use [`format::is_negative_zero`] to check a result for negative zero.
+    pub fn set_show_signed_zero(&mut self, show_signed_zero: bool) {
+        self.show_signed_zero = show_signed_zero;
+    }
+
+    /// Enable or disable single-letter constants.
+    ///
+    /// The only single-letter constant is `e`, which is enabled by default,
+    /// along with the physical constants `c`, `h`, and `G` if they are enabled.
+    /// When disabled, these are scanned as identifiers instead of constants.
+    /// The constant `e` is always available under the name `euler`.
+    pub fn set_single_letter_constants(&mut self, enabled: bool) {
+        self.single_letter_constants = enabled;
+    }
+
+    /// Enable or disable physical constants, such as the speed of light `c` and the Planck constant `h`.
+    ///
+    /// The constants are listed by [`physical_constants`].
+    /// Since their names are short and often used for variables, they are disabled by default,
+    /// and their names are variables unless enabled.
+    pub fn set_physical_constants(&mut self, enabled: bool) {
+        self.physical_constants = enabled;
+    }
+
+    /// Enable or disable lenient scanning.
+    ///
+    /// The byte order mark and zero-width characters are always ignored, and
+    /// tabs, line breaks, and non-breaking spaces are treated as whitespace.
+    /// When lenient, other invisible formatting characters are ignored as well.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Store a value in a variable so that it can be used in future expressions.
+    ///
+    /// The name may be a `$` variable, such as `$health`, or an identifier, such as `health`.
+    /// Any existing value is replaced.
+    /// Setting a variable does not change the numbering of automatic variables like `$0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid variable name, or if it is a reserved word.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalcError> {
+        self.interpreter.set_variable(name, value)
+    }
+
+    /// Get the value stored in a variable, if it exists.
+    pub fn get_variable(&self, name: &str) -> Option<f64> {
+        self.interpreter.get_variable(name)
+    }
+
+    /// Remove a variable, returning its value if it existed.
+    ///
+    /// Any variable can be removed, including numbered variables like `$0` and `$ans`.
+    /// Removing a numbered variable does not renumber the others,
+    /// and future results continue to be numbered after the last result, so names are never reused.
+    /// `$ans` is only removed if it is removed by name.
+    pub fn remove_variable(&mut self, name: &str) -> Option<f64> {
+        self.interpreter.remove_variable(name)
+    }
+
+    /// Get all stored variables and their values.
+    ///
+    /// Variables are always listed in the same canonical order:
+    /// numbered variables in numeric order (`$0`, `$1`, etc.), skipping any that were removed,
+    /// then named variables sorted by name, then `$ans` once a result has been stored.
+    /// The order does not depend on the order the variables were stored in,
+    /// so the result can be shown directly to the user.
+    pub fn variables(&self) -> impl Iterator<Item = (String, f64)> + '_ {
+        self.interpreter.variables()
+    }
+
+    /// Get the stored results and their values in numeric order (`$0`, `$1`, etc.), skipping any that were removed.
+    ///
+    /// Only numbered results are included, not named variables or `$ans`.
+    /// These are the first variables listed by [`Calculator::variables`].
+    pub fn results(&self) -> impl Iterator<Item = (String, f64)> + '_ {
+        self.interpreter.results()
+    }
+
+    /// Export a copy of all stored variables and their values, for saving or comparing sessions.
+    ///
+    /// Variables are in the same canonical order as [`Calculator::variables`],
+    /// so two calculators with the same variables always export identical lists.
+    pub fn export_variables(&self) -> Vec<(String, f64)> {
+        self.variables().collect()
+    }
+
+    /// Export the stored variables and results, for saving a session and restoring it later.
+    ///
+    /// The state includes every `$n` result, named variables, `$ans`,
+    /// and the counter that numbers the next result.
+    /// With the `serde` feature, the returned [`CalcState`] can be serialized.
+    pub fn export_state(&self) -> CalcState {
+        self.interpreter.export_state()
+    }
+
+    /// Replace the stored variables and results with a state from [`Calculator::export_state`].
+    ///
+    /// Registered tables, registered functions, and settings are kept.
+    /// The input [`history`](Calculator::history) is cleared, since it describes a different session.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the state has a variable with an invalid name,
+    /// or a result counter larger than the number of results,
+    /// in which case the calculator is not changed.
+    pub fn import_state(&mut self, state: CalcState) -> Result<(), CalcError> {
+        self.interpreter.import_state(state)?;
+        self.history.clear();
+        Ok(())
+    }
+
+    /// Returns a fingerprint of the calculator's state, suitable as a cache key.
+    ///
+    /// Two calculators with the same fingerprint evaluate every input to the same result,
+    /// unless they have functions with the same names and arities but different behavior,
+    /// or the input calls `rand`, since the state of the random number generator is not included.
+    /// The fingerprint includes:
+    ///
+    /// - all stored variables and their values, including `$n` results and `$ans`
+    /// - the result counter, which numbers the next `$n` variable
+    /// - the names and breakpoints of registered tables
+    /// - the names and arities of registered functions, but not their behavior
+    /// - options that change how input is scanned, parsed, evaluated, or displayed,
+    ///   such as strict mode, angle mode, implicit multiplication, and display precision
+    ///
+    /// It does not include evaluation limits such as [`Calculator::set_max_nodes`],
+    /// the maximum quote length, the random number generator, usage statistics, or warnings.
+    /// The input history is not included, so two sessions that reach the same state have the same fingerprint.
+    ///
+    /// The fingerprint is computed with a fixed hash function, so it is stable across processes and platforms.
+    /// It only changes between crate versions if state or options are added,
+    /// which is noted in the release notes, and never in a patch release.
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut fingerprint = fingerprint::Fingerprint::new();
+        // The format version, increased whenever the fingerprinted state changes
+        fingerprint.write_u64(3);
+        self.interpreter.write_fingerprint(&mut fingerprint);
+        fingerprint.write_bool(self.lenient);
+        fingerprint.write_bool(self.single_letter_constants);
+        fingerprint.write_u64(self.display_precision.map_or(0, |p| p as u64 + 1));
+        fingerprint.write_bool(self.show_signed_zero);
+        fingerprint.write_bool(self.truthy);
+        fingerprint.write_bool(self.implicit_multiplication);
+        fingerprint.write_bool(self.percent_suffix);
+        fingerprint.write_u64(self.max_literal_length as u64);
+        fingerprint.write_bool(self.d_exponent);
+        fingerprint.write_bool(self.case_insensitive_keywords);
+        // Written only when enabled, so that fingerprints from before the option existed are unchanged
+        if self.physical_constants {
+            fingerprint.write_str("physical_constants");
+        }
+        fingerprint.finish()
+    }
+
+    /// Register a linear interpolation table that can be called like a function.
+    ///
+    /// The `points` are `(x, y)` breakpoints, which must be sorted by x with no duplicate x values.
+    /// Calling `name(x)` in an expression linearly interpolates between the breakpoints around `x`.
+    /// Outside the range of the breakpoints, the result is extrapolated from the nearest segment
+    /// if `extrapolate` is true, or clamped to the nearest endpoint otherwise.
+    /// Registering a table with the same name as an existing table replaces it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid identifier or is a reserved word,
+    /// if there are fewer than two breakpoints, if any value is not finite,
+    /// or if the x values are not strictly increasing.
+    pub fn register_table(
+        &mut self,
+        name: &str,
+        points: &[(f64, f64)],
+        extrapolate: bool,
+    ) -> Result<(), CalcError> {
+        let table = table::Table::new(points, extrapolate)?;
+        self.interpreter.register_table(name, table)
+    }
+
+    /// Remove a table registered with [`Calculator::register_table`].
+    ///
+    /// Returns true if a table with the name was registered.
+    pub fn unregister_table(&mut self, name: &str) -> bool {
+        self.interpreter.unregister_table(name)
+    }
+
+    /// Register a native function that can be called in expressions.
+    ///
+    /// Calling `name(...)` with exactly `arity` arguments evaluates the arguments,
+    /// then calls `f` with them. Any error returned by `f` is returned from the evaluation.
+    /// Registering a function with the same name as an existing function or table replaces it.
+    /// The function must be `Send + Sync`, so that the calculator can still be moved to or shared with other threads.
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let mut calculator = Calculator::new();
+    /// calculator
+    ///     .register_function("lerp", 3, |args| Ok(args[0] + (args[1] - args[0]) * args[2]))
+    ///     .unwrap();
+    /// assert_eq!(calculator.quick_evaluate("lerp(10, 20, 0.25)").unwrap(), 12.5);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the name is not a valid identifier or is a reserved word.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: usize,
+        f: impl Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync + 'static,
+    ) -> Result<(), CalcError> {
+        self.interpreter.register_function(name, arity, f)
+    }
+
+    /// Remove a function registered with [`Calculator::register_function`].
+    ///
+    /// Returns true if a function with the name was registered.
+    pub fn unregister_function(&mut self, name: &str) -> bool {
+        self.interpreter.unregister_function(name)
+    }
+
+    /// Clear the results of previous evaluations so that results are numbered from `$0` again.
+    ///
+    /// Numbered variables like `$0` are removed, while named variables are kept,
+    /// whether they were stored by an assignment or with [`Calculator::set_variable`].
+    /// Registered tables, registered functions, and settings are also kept.
+    /// If `clear_ans` is true, `$ans` is removed as well; otherwise it keeps the last result.
+    /// The entries of [`Calculator::history`] are removed, since they refer to the removed results.
+    pub fn clear_history(&mut self, clear_ans: bool) {
+        self.interpreter.clear_history(clear_ans);
+        self.history.clear();
+    }
+
+    /// Returns the inputs evaluated with [`Calculator::evaluate`] and their results, oldest first.
+    ///
+    /// Inputs that failed to evaluate are not recorded,
+    /// and neither are inputs evaluated with [`Calculator::quick_evaluate`].
+    ///
+    /// ```
+    /// use expressive_calc::Calculator;
+    ///
+    /// let mut calculator = Calculator::new();
+    /// calculator.evaluate("2.5 * 5").unwrap();
+    /// let entry = &calculator.history()[0];
+    /// assert_eq!((entry.input(), entry.name(), entry.result()), ("2.5 * 5", "$0", 12.5));
+    /// ```
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// Reset the calculator, clearing all stored state.
+    ///
+    /// This function resets the interpreter.
+    /// All stored variables and the input history are cleared, and the variable count is reset to zero.
+    /// Registered tables and functions are kept.
+    pub fn reset(&mut self) {
+        self.interpreter.reset();
+        self.history.clear();
+    }
+}
+impl fmt::Debug for Calculator {
+    /// Formats the calculator with its variables in the canonical order of [`Calculator::variables`].
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Calculator")
+            .field("variables", &self.export_variables())
+            .finish_non_exhaustive()
+    }
+}
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let input = "1";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 1.0);
+    }
+
+    #[test]
+    fn test_constants() {
+        let calculator = Calculator::new();
+        for (name, value, description) in constants() {
+            // Compared bit for bit, since NaN is not equal to itself
+            let result = calculator.quick_evaluate(name).unwrap();
+            assert_eq!(result.to_bits(), value.to_bits(), "{}", name);
+            assert!(!description.is_empty());
+        }
+        let names: Vec<&str> = constants()
+            .iter()
+            .chain(physical_constants())
+            .map(|(name, _, _)| *name)
+            .collect();
+        let words: Vec<&str> = Word::ALL
+            .iter()
+            .filter(|w| w.arity() == Arity::Exactly(0))
+            .map(Word::name)
+            .collect();
+        assert_eq!(names, words);
+    }
+
+    #[test]
+    fn test_physical_constants() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("c").is_err());
+        calculator.set_variable("c", 3.0).unwrap();
+        assert_eq!(calculator.quick_evaluate("c * 2").unwrap(), 6.0);
+
+        calculator.set_physical_constants(true);
+        for (name, value, description) in physical_constants() {
+            assert_eq!(calculator.quick_evaluate(name).unwrap(), *value, "{}", name);
+            assert!(!description.is_empty());
+        }
+        assert_eq!(calculator.quick_evaluate("c / 1e8").unwrap(), 2.99792458);
+        let gas_constant = calculator.quick_evaluate("na * kb").unwrap();
+        assert!(
+            (gas_constant - 8.314_462_618).abs() < 1e-9,
+            "{}",
+            gas_constant
+        );
+        assert_eq!(calculator.quick_evaluate("g0").unwrap(), 9.80665);
+        assert!(calculator.quick_evaluate("g").is_err());
+
+        calculator.set_single_letter_constants(false);
+        assert_eq!(calculator.quick_evaluate("c").unwrap(), 3.0);
+        assert_eq!(
+            calculator.quick_evaluate("hbar").unwrap(),
+            1.054_571_817e-34
+        );
+
+        calculator.set_single_letter_constants(true);
+        calculator.set_case_insensitive_keywords(true);
+        assert_eq!(
+            calculator.quick_evaluate("HBAR").unwrap(),
+            1.054_571_817e-34
+        );
+        assert_eq!(calculator.quick_evaluate("g").unwrap(), 6.674_30e-11);
+    }
+
+    #[test]
+    fn test_evaluate_addition() {
+        let input = "1 + 2";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn test_evaluate_subtraction() {
+        let input = "1 - 2";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, -1.0);
+    }
+
+    #[test]
+    fn test_evaluate_multiplication() {
+        let input = "2 * 3";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 6.0);
+    }
+
+    #[test]
+    fn test_evaluate_division() {
+        let input = "6 / 4";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 1.5);
+    }
+
+    #[test]
+    fn test_evaluate_modulo() {
+        let input = "10 % 3 + 1";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn test_evaluate_modulo_negative() {
+        let input = "-7 % 3";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, -1.0);
+    }
+
+    #[test]
+    fn test_evaluate_modulo_chained() {
+        let input = "10 % 4 / 2";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 1.0);
+    }
+
+    #[test]
+    fn test_evaluate_left_associative() {
+        let input = "10 - 4 - 3";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn test_evaluate_multiple_operations() {
+        let input = "1 + 2 * 3";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 7.0);
+    }
+
+    #[test]
+    fn test_evaluate_parentheses() {
+        let input = "(1 + 2) * 3";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 9.0);
+    }
+
+    #[test]
+    fn test_inf() {
+        let input = "inf";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_unary_func() {
+        let input = "sqrt(9)";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn test_binary_func() {
+        let input = "pow(2, 3)";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 8.0);
+    }
+
+    #[test]
+    fn test_abs_bars() {
+        let input = "|-42|";
+        let calculator = Calculator::new();
+        let result = calculator.quick_evaluate(input).unwrap();
+        assert_eq!(result, 42.0);
+    }
+
+    #[test]
+    fn test_abs_bars_nested() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("|3 - 5| * 2", 4.0),
+            ("||-3| - 5|", 2.0),
+            ("|1 - |2 - 5||", 2.0),
+            ("|-2| * |-3|", 6.0),
+            ("-|-2|", -2.0),
+            ("|-2|^2", 4.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_abs_bars_unbalanced() {
+        let calculator = Calculator::new();
+        let err = calculator.quick_evaluate("|3 - 5").unwrap_err();
+        assert_eq!(err.message(), "Expected closing bar");
+        assert_eq!(err.position(), Some(6));
+        assert!(calculator.quick_evaluate("3 - 5|").is_err());
+        assert!(calculator.quick_evaluate("||").is_err());
+    }
+
+    #[test]
+    fn test_state() {
+        let input = "1 + 2";
+        let mut calculator = Calculator::new();
+        let result = calculator.evaluate(input).unwrap();
+        assert_eq!(result, ("$0".to_string(), 3.0));
+
+        let input = "$0 * 3";
+        let result = calculator.evaluate(input).unwrap();
+        assert_eq!(result, ("$1".to_string(), 9.0));
+    }
+
+    #[test]
+    fn test_reset() {
+        let input = "1 + 2";
+        let mut calculator = Calculator::new();
+        let result = calculator.evaluate(input).unwrap();
+        assert_eq!(result, ("$0".to_string(), 3.0));
+
+        calculator.reset();
+        assert!(calculator.quick_evaluate("$0").is_err());
+
+        let input = "1 + 3";
+        let result = calculator.evaluate(input).unwrap();
+        assert_eq!(result, ("$0".to_string(), 4.0));
+    }
+
+    #[test]
+    fn test_reciprocal_hyperbolic() {
+        let calculator = Calculator::new();
+        let coth = calculator.quick_evaluate("coth(2)").unwrap();
+        let expected = calculator.quick_evaluate("cosh(2) / sinh(2)").unwrap();
+        assert!((coth - expected).abs() < 1e-12);
+        assert_eq!(calculator.quick_evaluate("csch(0)").unwrap(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_strict_domain_error() {
+        let mut calculator = Calculator::new();
+        calculator.set_strict(true);
+        assert!(calculator.quick_evaluate("coth(0)").is_err());
+        assert!(calculator.quick_evaluate("acoth(0.5)").is_err());
+        assert_eq!(calculator.quick_evaluate("sech(0)").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_invisible_characters() {
+        let calculator = Calculator::new();
+        let result = calculator
+            .quick_evaluate("\u{FEFF}1\u{00A0}+ 1\u{200B}0")
+            .unwrap();
+        assert_eq!(result, 11.0);
+        assert!(calculator.quick_evaluate("1 + 2 \u{2603}").is_err());
+    }
+
+    #[test]
+    fn test_lenient() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("\u{200F}1 + 2").is_err());
+        calculator.set_lenient(true);
+        assert_eq!(calculator.quick_evaluate("\u{200F}1 + 2").unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_keyword_with_digits() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("log2(8)").unwrap(), 3.0);
+        assert_eq!(calculator.quick_evaluate("log10(1000)").unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_division_by_zero_default() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("1 / 0").unwrap(), f64::INFINITY);
+        assert!(calculator.quick_evaluate("0 / 0").unwrap().is_nan());
+        assert!(calculator.quick_evaluate("mod(5, 0)").unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_strict_division() {
+        let mut calculator = Calculator::new();
+        calculator.set_strict_division(true);
+        assert!(calculator.quick_evaluate("1 / 0").is_err());
+        assert!(calculator.quick_evaluate("0 / 0").is_err());
+        assert!(calculator.quick_evaluate("mod(5, 0)").is_err());
+        assert!(calculator.quick_evaluate("5 % 0").is_err());
+        assert_eq!(calculator.quick_evaluate("1 / 4").unwrap(), 0.25);
+
+        calculator.set_strict_division(false);
+        assert_eq!(calculator.quick_evaluate("1 / 0").unwrap(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_table() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_table("cal", &[(0.0, 1.0), (10.0, 1.5), (20.0, 2.2)], false)
+            .unwrap();
+        assert_eq!(calculator.quick_evaluate("cal(7.5)").unwrap(), 1.375);
+        assert_eq!(calculator.quick_evaluate("cal(10)").unwrap(), 1.5);
+        assert_eq!(calculator.quick_evaluate("cal(-5)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("2 * cal(30)").unwrap(), 4.4);
+
+        let result = calculator.evaluate("cal(5) + 1").unwrap();
+        assert_eq!(result, ("$0".to_string(), 2.25));
+    }
+
+    #[test]
+    fn test_table_replace_and_unregister() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_table("cal", &[(0.0, 0.0), (1.0, 1.0)], false)
+            .unwrap();
+        assert_eq!(calculator.quick_evaluate("cal(2)").unwrap(), 1.0);
+
+        calculator
+            .register_table("cal", &[(0.0, 0.0), (1.0, 1.0)], true)
+            .unwrap();
+        assert_eq!(calculator.quick_evaluate("cal(2)").unwrap(), 2.0);
+
+        assert!(calculator.unregister_table("cal"));
+        assert!(!calculator.unregister_table("cal"));
+        assert!(calculator.quick_evaluate("cal(2)").is_err());
+    }
+
+    #[test]
+    fn test_table_errors() {
+        let mut calculator = Calculator::new();
+        let points = [(0.0, 0.0), (1.0, 1.0)];
+        assert!(calculator.register_table("sqrt", &points, false).is_err());
+        assert!(calculator.register_table("1cal", &points, false).is_err());
+        assert!(calculator
+            .register_table("cal", &[(1.0, 0.0), (0.0, 1.0)], false)
+            .is_err());
+        assert!(calculator
+            .register_table("cal", &[(1.0, 0.0), (1.0, 1.0)], false)
+            .is_err());
+
+        calculator.register_table("cal", &points, false).unwrap();
+        assert!(calculator.quick_evaluate("cal(1, 2)").is_err());
+        assert!(calculator.quick_evaluate("cal").is_err());
+        assert!(calculator.quick_evaluate("other(1)").is_err());
+    }
+
+    #[test]
+    fn test_error_position() {
+        let calculator = Calculator::new();
+        let err = calculator.quick_evaluate("1 + & 2").unwrap_err();
+        assert_eq!(err.position(), Some(4));
+
+        let err = calculator.quick_evaluate("(1 + 2").unwrap_err();
+        assert_eq!(err.position(), Some(6));
+
+        let err = calculator.quick_evaluate("sqrt(4 5)").unwrap_err();
+        assert_eq!(
+            err.span(),
+            Some(Span {
+                start: 7,
+                end: 8,
+                byte_start: 7,
+                byte_end: 8
+            })
+        );
+
+        let err = calculator.quick_evaluate("$missing").unwrap_err();
+        assert_eq!(err.position(), None);
+    }
+
+    #[test]
+    fn test_evaluate_display() {
+        let mut calculator = Calculator::new();
+        let result = calculator.evaluate_display("0.1 + 0.2").unwrap();
+        assert_eq!(
+            result,
+            ("$0".to_string(), 0.30000000000000004, "0.3".to_string())
+        );
+
+        calculator.set_display_precision(None);
+        let (_, _, display) = calculator.evaluate_display("0.1 + 0.2").unwrap();
+        assert_eq!(display, "0.30000000000000004");
+
+        calculator.set_display_precision(Some(3));
+        let (_, _, display) = calculator.evaluate_display("pi").unwrap();
+        assert_eq!(display, "3.14");
+    }
+
+    #[test]
+    fn test_signed_zero_display() {
+        let mut calculator = Calculator::new();
+        for input in ["-1 * 0", "1 / -inf", "-0"] {
+            let (_, value, display) = calculator.evaluate_display(input).unwrap();
+            assert!(format::is_negative_zero(value), "{}", input);
+            assert_eq!(display, "0", "{}", input);
+        }
+        assert_eq!(
+            calculator.quick_evaluate("1 / $ans").unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            calculator.quick_evaluate("1 / (-1 * 0)").unwrap(),
+            f64::NEG_INFINITY
+        );
+
+        calculator.set_show_signed_zero(true);
+        let (_, _, display) = calculator.evaluate_display("-1 * 0").unwrap();
+        assert_eq!(display, "-0");
+        let (_, _, display) = calculator.evaluate_display("0").unwrap();
+        assert_eq!(display, "0");
+    }
+
+    #[test]
+    fn test_euler() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.quick_evaluate("euler").unwrap(),
+            std::f64::consts::E
+        );
+        assert_eq!(calculator.quick_evaluate("e").unwrap(), std::f64::consts::E);
+
+        calculator.set_single_letter_constants(false);
+        assert_eq!(
+            calculator.quick_evaluate("euler ^ 2").unwrap(),
+            std::f64::consts::E.powi(2)
+        );
+        assert!(calculator.quick_evaluate("e").is_err());
+    }
+
+    #[test]
+    fn test_euler_scientific_notation() {
+        let mut calculator = Calculator::new();
+        for enabled in [true, false] {
+            calculator.set_single_letter_constants(enabled);
+            assert_eq!(calculator.quick_evaluate("2e3").unwrap(), 2000.0);
+            assert_eq!(calculator.quick_evaluate("1.5E-3").unwrap(), 0.0015);
+            assert_eq!(
+                calculator.quick_evaluate("2 * e").is_ok(),
+                enabled,
+                "enabled: {}",
+                enabled
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_all() {
+        let mut calculator = Calculator::new();
+        let results = calculator.evaluate_all(&["1 + 2", "$0 * 2"]).unwrap();
+        assert_eq!(
+            results,
+            vec![("$0".to_string(), 3.0), ("$1".to_string(), 6.0)]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_all_failure() {
+        let mut calculator = Calculator::new();
+        let statements = ["1", "2", "(3", "4", "5"];
+        let err = calculator.evaluate_all(&statements).unwrap_err();
+        assert_eq!(err.statement(), Some(2));
+
+        assert_eq!(calculator.quick_evaluate("$0").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("$1").unwrap(), 2.0);
+        assert!(calculator.quick_evaluate("$2").is_err());
+        assert_eq!(calculator.quick_evaluate("$ans").unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_rand() {
+        let mut calculator = Calculator::new();
+        calculator.seed_rng(7);
+        let first: Vec<f64> = (0..5)
+            .map(|_| calculator.quick_evaluate("rand()").unwrap())
+            .collect();
+        calculator.seed_rng(7);
+        let second: Vec<f64> = (0..5)
+            .map(|_| calculator.quick_evaluate("rand()").unwrap())
+            .collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|x| (0.0..1.0).contains(x)));
+        assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+
+        for _ in 0..100 {
+            let x = calculator.quick_evaluate("rand(5, 10)").unwrap();
+            assert!((5.0..10.0).contains(&x), "{}", x);
+        }
+        // Each call draws a new number
+        assert_ne!(calculator.quick_evaluate("rand() - rand()").unwrap(), 0.0);
+
+        let err = calculator.quick_evaluate("rand(10, 5)").unwrap_err();
+        assert_eq!(
+            err.message(),
+            "rand(a, b) requires finite bounds with a <= b"
+        );
+        let err = calculator.quick_evaluate("rand(5)").unwrap_err();
+        assert_eq!(err.message(), "rand expects 0 or 2 argument(s), got 1");
+        assert_eq!(err.kind(), ErrorKind::Parse);
+        assert!(calculator.quick_evaluate("rand").is_err());
+    }
+
+    #[test]
+    fn test_angle_mode() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("sin(pi / 2)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("asin(1)").unwrap(), FRAC_PI_2);
+
+        calculator.set_angle_mode(AngleMode::Degrees);
+        // Trigonometric functions agree exactly with the degree variants, such as sind
+        let cases = [
+            ("sin(90)", 1.0),
+            ("sin(180)", 0.0),
+            ("cos(60)", 0.5),
+            ("cos(90)", 0.0),
+            ("tan(45)", 1.0),
+            ("tan(135)", -1.0),
+            ("asin(1)", 90.0),
+            ("asin(0.5)", 30.0),
+            ("acos(0.5)", 60.0),
+            ("atan(1)", 45.0),
+            ("atan2(1, 1)", 45.0),
+            ("atan2(-1, 0)", -90.0),
+            ("sec(60)", 2.0),
+            ("sec(180)", -1.0),
+            ("csc(30)", 2.0),
+            ("cot(45)", 1.0),
+            ("cot(90)", 0.0),
+            ("asec(2)", 60.0),
+            ("acsc(2)", 30.0),
+            ("acot(1)", 45.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        for (input, degree_variant) in [
+            ("sin(37)", "sind(37)"),
+            ("cos(-721)", "cosd(-721)"),
+            ("tan(1e10)", "tand(1e10)"),
+            ("asin(0.3)", "asind(0.3)"),
+            ("acos(-0.7)", "acosd(-0.7)"),
+            ("atan(5)", "atand(5)"),
+            ("atan2(3, -4)", "atan2d(3, -4)"),
+        ] {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                calculator.quick_evaluate(degree_variant).unwrap(),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            calculator.quick_evaluate("csc(180)").unwrap(),
+            f64::INFINITY
+        );
+
+        let cases = [
+            ("sinh(1)", 1.0f64.sinh()),
+            ("atanh(0.5)", 0.5f64.atanh()),
+            ("rad(180)", PI),
+            ("deg(pi)", 180.0),
+        ];
+        for (input, expected) in cases {
+            let result = calculator.quick_evaluate(input).unwrap();
+            assert!((result - expected).abs() < 1e-12, "{} = {}", input, result);
+        }
+        assert!(calculator.quick_evaluate("asin(2)").unwrap().is_nan());
+        calculator.set_strict(true);
+        assert!(calculator.quick_evaluate("asin(2)").is_err());
+
+        // The degree variants always use degrees
+        assert_eq!(calculator.quick_evaluate("sind(30)").unwrap(), 0.5);
+        assert_eq!(calculator.quick_evaluate("atan2d(1, 1)").unwrap(), 45.0);
+
+        calculator.set_angle_mode(AngleMode::Radians);
+        assert_eq!(calculator.quick_evaluate("asin(1)").unwrap(), FRAC_PI_2);
+        assert_eq!(calculator.quick_evaluate("sind(30)").unwrap(), 0.5);
+        assert_eq!(calculator.quick_evaluate("cosd(60)").unwrap(), 0.5);
+        assert_eq!(calculator.quick_evaluate("tand(45)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("asind(1)").unwrap(), 90.0);
+
+        // The degree suffix converts to the angle unit, so it gives the same angle in either mode
+        for mode in [AngleMode::Radians, AngleMode::Degrees] {
+            calculator.set_angle_mode(mode);
+            assert_eq!(calculator.quick_evaluate("sin(90°)").unwrap(), 1.0);
+            assert_eq!(calculator.quick_evaluate("sin(90 deg)").unwrap(), 1.0);
+            assert_eq!(calculator.quick_evaluate("cos(180°)").unwrap(), -1.0);
+        }
+        assert_eq!(calculator.quick_evaluate("45°").unwrap(), 45.0);
+    }
+
+    #[test]
+    fn test_gamma() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("gamma(6)").unwrap(), 120.0);
+        let result = calculator.quick_evaluate("gamma(0.5) - sqrt(pi)").unwrap();
+        assert!(result.abs() < 1e-14);
+        let result = calculator.quick_evaluate("lgamma(100) - ln(99!)").unwrap();
+        assert!(result.abs() < 1e-12);
+        assert_eq!(
+            calculator.quick_evaluate("gamma(-2)").unwrap(),
+            f64::INFINITY
+        );
+        assert!(calculator.quick_evaluate("gamma(1, 2)").is_err());
+    }
+
+    #[test]
+    fn test_fact() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("fact(0)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("fact(10)").unwrap(), 3_628_800.0);
+        assert_eq!(
+            calculator.quick_evaluate("fact(170)").unwrap(),
+            calculator.quick_evaluate("170!").unwrap()
+        );
+        assert_eq!(
+            calculator.quick_evaluate("fact(171)").unwrap(),
+            f64::INFINITY
+        );
+        assert_eq!(
+            calculator
+                .quick_evaluate("fact(2.5)")
+                .unwrap_err()
+                .message(),
+            "Factorial requires a non-negative integer"
+        );
+    }
+
+    #[test]
+    fn test_avg() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("avg(7)").unwrap(), 7.0);
+        assert_eq!(calculator.quick_evaluate("avg(1, 2)").unwrap(), 1.5);
+        assert_eq!(
+            calculator.quick_evaluate("avg(1, 2, 3, 4, 5)").unwrap(),
+            3.0
+        );
+        let hundred = (1..=100).map(|i| i.to_string()).collect::<Vec<_>>();
+        let input = format!("avg({})", hundred.join(", "));
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), 50.5);
+
+        let err = calculator.quick_evaluate("avg()").unwrap_err();
+        assert_eq!(err.message(), "avg expects at least 1 argument(s), got 0");
+        assert_eq!(err.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn test_math_constants() {
+        use std::f64::consts::{FRAC_1_SQRT_2, LN_10, LN_2, SQRT_2};
+
+        let calculator = Calculator::new();
+        let cases = [
+            ("ln2", LN_2),
+            ("ln10", LN_10),
+            ("sqrt2", SQRT_2),
+            ("invsqrt2", FRAC_1_SQRT_2),
+        ];
+        for (input, expected) in cases {
+            let result = calculator.quick_evaluate(input).unwrap();
+            assert_eq!(result.to_bits(), expected.to_bits(), "{}", input);
+        }
+        assert_eq!(calculator.quick_evaluate("2 * ln2").unwrap(), 2.0 * LN_2);
+        assert!(calculator.quick_evaluate("ln2(4)").is_err());
+    }
+
+    #[test]
+    fn test_float_limit_constants() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("eps", f64::EPSILON),
+            ("maxval", f64::MAX),
+            ("minval", f64::MIN_POSITIVE),
+            ("-maxval", f64::MIN),
+        ];
+        for (input, expected) in cases {
+            let result = calculator.quick_evaluate(input).unwrap();
+            assert_eq!(result.to_bits(), expected.to_bits(), "{}", input);
+        }
+        assert_eq!(
+            calculator.quick_evaluate("1 + eps - 1").unwrap(),
+            f64::EPSILON
+        );
+        assert_eq!(calculator.quick_evaluate("1 + eps / 2 - 1").unwrap(), 0.0);
+        assert_eq!(
+            calculator.quick_evaluate("maxval * 2").unwrap(),
+            f64::INFINITY
+        );
+        assert_eq!(
+            calculator.quick_evaluate("maxval / 2 * 2").unwrap(),
+            f64::MAX
+        );
+        let subnormal = calculator.quick_evaluate("minval / 2").unwrap();
+        assert!(subnormal > 0.0 && !subnormal.is_normal());
+    }
+
+    #[test]
+    fn test_nan_predicates() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("nan").unwrap().is_nan());
+        assert!(calculator.quick_evaluate("nan + 1").unwrap().is_nan());
+        let cases = [
+            ("isnan(nan)", 1.0),
+            ("isnan(0 / 0)", 1.0),
+            ("isnan(inf)", 0.0),
+            ("isnan(1)", 0.0),
+            ("isinf(inf)", 1.0),
+            ("isinf(-inf)", 1.0),
+            ("isinf(nan)", 0.0),
+            ("isinf(1e308)", 0.0),
+            ("isfinite(1e308)", 1.0),
+            ("isfinite(-0)", 1.0),
+            ("isfinite(inf)", 0.0),
+            ("isfinite(nan)", 0.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+
+        calculator.evaluate("sqrt(-1)").unwrap();
+        assert_eq!(calculator.evaluate("isnan($0)").unwrap().1, 1.0);
+        assert_eq!(calculator.evaluate("isnan($1)").unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn test_max_min() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("max(1, 7, 3, 5)").unwrap(), 7.0);
+        assert_eq!(calculator.quick_evaluate("min(4, -2, 8)").unwrap(), -2.0);
+        assert_eq!(calculator.quick_evaluate("max(1, 2)").unwrap(), 2.0);
+        calculator.evaluate("5").unwrap();
+        let (_, result) = calculator.evaluate("max(2, $0, pi)").unwrap();
+        assert_eq!(result, 5.0);
+        let (_, result) = calculator.evaluate("min(2, $0, pi)").unwrap();
+        assert_eq!(result, 2.0);
+
+        // NaN arguments are ignored, as with f64::max
+        let nan = "(0 / 0)";
+        let input = format!("max(1, {0}, 3)", nan);
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), 3.0);
+        let input = format!("min({0}, {0}, -1)", nan);
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), -1.0);
+        let input = format!("max({0}, {0}, {0})", nan);
+        assert!(calculator.quick_evaluate(&input).unwrap().is_nan());
+
+        let err = calculator.quick_evaluate("max(1)").unwrap_err();
+        assert_eq!(err.message(), "max expects at least 2 argument(s), got 1");
+        assert_eq!(err.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn test_sum_prod() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.quick_evaluate("sum(1, 2, 3, 4, 5)").unwrap(),
+            15.0
+        );
+        assert_eq!(calculator.quick_evaluate("prod(2, 3, 4)").unwrap(), 24.0);
+        assert_eq!(calculator.quick_evaluate("prod(-7)").unwrap(), -7.0);
+        calculator.evaluate("10").unwrap();
+        let (_, result) = calculator.evaluate("sum($0, 2 * 3, sqrt(16))").unwrap();
+        assert_eq!(result, 20.0);
+        let (_, result) = calculator.evaluate("prod($0, $1 / 4, -1)").unwrap();
+        assert_eq!(result, -50.0);
+
+        for input in ["sum()", "prod()"] {
+            let err = calculator.quick_evaluate(input).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_ncr_npr() {
+        let calculator = Calculator::new();
+        assert_eq!(
+            calculator.quick_evaluate("ncr(52, 5)").unwrap(),
+            2_598_960.0
+        );
+        assert_eq!(calculator.quick_evaluate("npr(5, 2)").unwrap(), 20.0);
+        assert_eq!(calculator.quick_evaluate("ncr(10, 0)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("ncr(10, 10)").unwrap(), 1.0);
+        let err = calculator.quick_evaluate("ncr(3, 5)").unwrap_err();
+        assert_eq!(err.message(), "ncr(n, k) requires k <= n");
+        assert!(calculator.quick_evaluate("npr(5)").is_err());
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("gcd(12, 18)").unwrap(), 6.0);
+        assert_eq!(calculator.quick_evaluate("lcm(4, 6)").unwrap(), 12.0);
+        assert_eq!(calculator.quick_evaluate("gcd(0, 5)").unwrap(), 5.0);
+        assert!(calculator.quick_evaluate("gcd(1.5, 3)").is_err());
+        assert!(calculator.quick_evaluate("lcm(4)").is_err());
+    }
+
+    #[test]
+    fn test_round_digits() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("round(1.23456)").unwrap(), 1.0);
+        assert_eq!(
+            calculator.quick_evaluate("round(1.23456, 2)").unwrap(),
+            1.23
+        );
+        assert_eq!(
+            calculator.quick_evaluate("round(1234, -2)").unwrap(),
+            1200.0
+        );
+        assert_eq!(calculator.quick_evaluate("round(2.675, 2)").unwrap(), 2.68);
+        assert!(calculator.quick_evaluate("round(1, 0.5)").is_err());
+        let err = calculator.quick_evaluate("round(1, 2, 3)").unwrap_err();
+        assert_eq!(err.message(), "round expects 1 to 2 argument(s), got 3");
+        assert_eq!(
+            calculator
+                .quick_evaluate("round(,2)")
+                .unwrap_err()
+                .message(),
+            "Missing argument 1 of round"
+        );
+    }
+
+    #[test]
+    fn test_clamp() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("clamp(7, 1, 5)").unwrap(), 5.0);
+        assert_eq!(calculator.quick_evaluate("clamp(-2, 1, 5,)").unwrap(), 1.0);
+        assert_eq!(
+            calculator
+                .quick_evaluate("clamp(3, 5, 1)")
+                .unwrap_err()
+                .message(),
+            "clamp(x, lo, hi) requires lo <= hi"
+        );
+        for input in ["clamp(1, 2)", "clamp(1, 2, 3, 4)"] {
+            let err = calculator.quick_evaluate(input).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", input);
+            assert!(err.message().starts_with("clamp expects 3 argument(s)"));
+            assert_eq!(err.span().unwrap().start, 0, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_nthroot() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("nthroot(27, 3)").unwrap(), 3.0);
+        assert_eq!(calculator.quick_evaluate("nthroot(-8, 3)").unwrap(), -2.0);
+        assert!(calculator
+            .quick_evaluate("pow(-8, 1 / 3)")
+            .unwrap()
+            .is_nan());
+        assert!(calculator.quick_evaluate("nthroot(-16, 4)").is_err());
+        assert!(calculator.quick_evaluate("nthroot(8, 0)").is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("sign(-7)").unwrap(), -1.0);
+        assert_eq!(
+            calculator.quick_evaluate("sign(0) + sign(inf)").unwrap(),
+            1.0
+        );
+        assert!(calculator.quick_evaluate("sign(0 / 0)").unwrap().is_nan());
+        assert_eq!(calculator.quick_evaluate("copysign(2, -5)").unwrap(), -2.0);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut original = Calculator::new();
+        original.evaluate("radius = 2").unwrap();
+        original
+            .register_function("double", 1, |args| Ok(args[0] * 2.0))
+            .unwrap();
+
+        let mut fork = original.clone();
+        assert_eq!(fork.quick_evaluate("double(radius)").unwrap(), 4.0);
+
+        assert_eq!(
+            fork.evaluate("radius = 3").unwrap(),
+            ("radius".to_string(), 3.0)
+        );
+        assert_eq!(original.evaluate("10").unwrap(), ("$1".to_string(), 10.0));
+        assert_eq!(original.get_variable("radius"), Some(2.0));
+        assert_eq!(fork.get_variable("$1"), Some(3.0));
+        assert_eq!(original.history().len(), 2);
+        assert_eq!(fork.history().len(), 2);
+
+        fork.reset();
+        assert!(fork.get_variable("radius").is_none());
+        assert!(fork.history().is_empty());
+        assert_eq!(original.get_variable("radius"), Some(2.0));
+        assert_eq!(original.get_variable("$ans"), Some(10.0));
+        assert_eq!(original.quick_evaluate("double($1)").unwrap(), 20.0);
+
+        assert!(original.unregister_function("double"));
+        assert_eq!(fork.quick_evaluate("double(1)").unwrap(), 2.0);
+
+        let mut fork = original.clone();
+        let sequence: Vec<f64> = (0..3)
+            .map(|_| original.quick_evaluate("rand()").unwrap())
+            .collect();
+        let replayed: Vec<f64> = (0..3)
+            .map(|_| fork.quick_evaluate("rand()").unwrap())
+            .collect();
+        assert_eq!(sequence, replayed);
+        fork.seed_rng(1);
+        original.seed_rng(2);
+        assert_ne!(
+            fork.quick_evaluate("rand()").unwrap(),
+            original.quick_evaluate("rand()").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_script() {
+        let mut calculator = Calculator::new();
+        let results = calculator
+            .evaluate_script("a = 2\nb = 3\na * b\n\n  ; 1; $ans + 1;\n")
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                ("a".to_string(), 2.0),
+                ("b".to_string(), 3.0),
+                ("$2".to_string(), 6.0),
+                ("$3".to_string(), 1.0),
+                ("$4".to_string(), 2.0),
+            ]
+        );
+        assert!(calculator.evaluate_script(" \n ;; ").unwrap().is_empty());
+        assert_eq!(calculator.evaluate("1\t+ 2").unwrap().1, 3.0);
+        assert_eq!(
+            calculator
+                .evaluate_script("x = 1\r\n\ty =\t2\r\n\tx +\ty\r\n")
+                .unwrap(),
+            vec![
+                ("x".to_string(), 1.0),
+                ("y".to_string(), 2.0),
+                ("$8".to_string(), 3.0),
+            ]
+        );
+
+        let mut single = Calculator::new();
+        assert_eq!(
+            single.evaluate_script("1 + 2").unwrap(),
+            vec![single
+                .quick_evaluate("1 + 2")
+                .map(|r| ("$0".to_string(), r))
+                .unwrap()]
+        );
+        assert_eq!(
+            single.evaluate_script("1 +").unwrap_err(),
+            Calculator::new().evaluate("1 +").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$r", 2.0).unwrap();
+        assert_eq!(
+            calculator
+                .quick_evaluate("2 * pi * $r  # circumference")
+                .unwrap(),
+            4.0 * std::f64::consts::PI
+        );
+        assert_eq!(calculator.quick_evaluate("10 / 4 // quarter").unwrap(), 2.5);
+        assert_eq!(calculator.quick_evaluate("1 # c\n").unwrap(), 1.0);
+        assert_eq!(
+            calculator.quick_evaluate("1 # c\n+ 2 // d\r\n").unwrap(),
+            3.0
+        );
+        assert_eq!(
+            Calculator::new().evaluate("1 # c\n").unwrap(),
+            ("$0".to_string(), 1.0)
+        );
+        assert_eq!(
+            calculator.quick_evaluate("# nothing").unwrap_err(),
+            calculator.quick_evaluate("").unwrap_err()
+        );
+
+        let results = calculator
+            .evaluate_script("# worksheet\na = 2 # first; not a statement\n// b\nb = 3\na * b")
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                ("a".to_string(), 2.0),
+                ("b".to_string(), 3.0),
+                ("$2".to_string(), 6.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_script_failure() {
+        let mut calculator = Calculator::new();
+        let err = calculator
+            .evaluate_script("x = 1\n\ny = 2; 1 / $z\n3")
+            .unwrap_err();
+        assert_eq!(err.message(), "Variable not found: $z");
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.statement(), Some(2));
+        assert_eq!(
+            err.to_string(),
+            "CalcError: Variable not found: $z in statement 2 on line 3"
+        );
+        assert_eq!(calculator.get_variable("y"), Some(2.0));
+        assert!(calculator.get_variable("$2").is_none());
+    }
+
+    #[test]
+    fn test_evaluate_all_atomic_failure() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("10").unwrap();
+
+        let statements = ["1", "2", "$missing", "4", "5"];
+        let err = calculator.evaluate_all_atomic(&statements).unwrap_err();
+        assert_eq!(err.statement(), Some(2));
+
+        assert_eq!(calculator.quick_evaluate("$0").unwrap(), 10.0);
+        assert!(calculator.quick_evaluate("$1").is_err());
+        assert!(calculator.quick_evaluate("$2").is_err());
+        assert_eq!(calculator.quick_evaluate("$ans").unwrap(), 10.0);
+
+        let result = calculator.evaluate("20").unwrap();
+        assert_eq!(result, ("$1".to_string(), 20.0));
+    }
+
+    #[test]
+    fn test_evaluate_all_atomic_success() {
+        let mut calculator = Calculator::new();
+        let results = calculator.evaluate_all_atomic(&["1", "$ans + 1"]).unwrap();
+        assert_eq!(
+            results,
+            vec![("$0".to_string(), 1.0), ("$1".to_string(), 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_logaddexp() {
+        let calculator = Calculator::new();
+        let expected = 1000.0 + std::f64::consts::LN_2;
+        assert_eq!(
+            calculator.quick_evaluate("logaddexp(1000, 1000)").unwrap(),
+            expected
+        );
+        assert_eq!(
+            calculator
+                .quick_evaluate("ln(exp(1000) + exp(1000))")
+                .unwrap(),
+            f64::INFINITY
+        );
+    }
+
+    #[test]
+    fn test_logsumexp() {
+        let calculator = Calculator::new();
+        let expected = 1000.0 + 3.0f64.ln();
+        let result = calculator
+            .quick_evaluate("logsumexp(1000, 1000, 1000,)")
+            .unwrap();
+        assert!((result - expected).abs() < 1e-12);
+        assert_eq!(calculator.quick_evaluate("logsumexp(-5)").unwrap(), -5.0);
+        assert!(calculator.quick_evaluate("logsumexp()").is_err());
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let mut calculator = Calculator::new();
+        let err = calculator.quick_evaluate("1 + &").unwrap_err();
+        assert!(err.is_scan_error());
+        assert_eq!(err.message(), "Invalid character");
+
+        let err = calculator.quick_evaluate("(1 + 2").unwrap_err();
+        assert!(err.is_parse_error());
+        assert_eq!(err.message(), "Expected closing parenthesis");
+
+        let err = calculator.quick_evaluate("$missing").unwrap_err();
+        assert!(err.is_evaluation_error());
+        assert_eq!(
+            err,
+            CalcError::new("Variable not found: $missing", None).with_kind(ErrorKind::Evaluation)
+        );
+
+        let err = calculator
+            .register_table("cal", &[(0.0, 0.0)], false)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Configuration);
+    }
+
+    #[test]
+    fn test_error_eq_ignores_position_and_source() {
+        let calculator = Calculator::new();
+        let a = calculator.quick_evaluate("1 + &").unwrap_err();
+        let b = calculator.quick_evaluate("&").unwrap_err();
+        assert_eq!(a, b);
+        assert_ne!(a.position(), b.position());
+
+        let a = calculator.quick_evaluate("1.2.3").unwrap_err();
+        let b = CalcError::new("Number has more than one decimal point", None)
+            .with_kind(ErrorKind::Scan);
+        assert_eq!(a, b);
+        assert_ne!(
+            a,
+            CalcError::new("Number has more than one decimal point", None)
+        );
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("1 and 2").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("1 and 0").unwrap(), 0.0);
+        assert_eq!(calculator.quick_evaluate("0 or 0").unwrap(), 0.0);
+        assert_eq!(calculator.quick_evaluate("0 or 3 - 3").unwrap(), 0.0);
+        assert_eq!(calculator.quick_evaluate("0 or -2").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("0 and 1 or 1").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_logical_short_circuit() {
+        let mut calculator = Calculator::new();
+        calculator.set_strict(true);
+        calculator.set_strict_division(true);
+        assert_eq!(calculator.quick_evaluate("0 and (1 / 0)").unwrap(), 0.0);
+        assert_eq!(calculator.quick_evaluate("1 or sqrt(-1)").unwrap(), 1.0);
+        assert_eq!(calculator.quick_evaluate("0 and $missing").unwrap(), 0.0);
+
+        assert!(calculator.quick_evaluate("1 and (1 / 0)").is_err());
+        assert!(calculator.quick_evaluate("0 or sqrt(-1)").is_err());
+    }
+
+    #[test]
+    fn test_assignment() {
+        let mut calculator = Calculator::new();
+        let result = calculator.evaluate("radius = 5").unwrap();
+        assert_eq!(result, ("radius".to_string(), 5.0));
+
+        let result = calculator.evaluate("area = pi * radius * radius").unwrap();
+        assert_eq!(result, ("area".to_string(), std::f64::consts::PI * 25.0));
+
+        assert_eq!(calculator.quick_evaluate("$0").unwrap(), 5.0);
+        assert_eq!(
+            calculator.quick_evaluate("$ans").unwrap(),
+            std::f64::consts::PI * 25.0
+        );
+
+        let result = calculator.evaluate("radius = radius + 1").unwrap();
+        assert_eq!(result, ("radius".to_string(), 6.0));
+        let result = calculator.evaluate("radius").unwrap();
+        assert_eq!(result, ("$3".to_string(), 6.0));
+    }
+
+    #[test]
+    fn test_assignment_errors() {
+        let mut calculator = Calculator::new();
+        let err = calculator.evaluate("pi = 3").unwrap_err();
+        assert!(err.is_parse_error());
+        assert!(err.message().contains("pi"));
+        assert!(calculator.evaluate("x = y").is_err());
+        assert!(calculator.quick_evaluate("x").is_err());
+    }
+
+    #[test]
+    fn test_quick_assignment() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("x = 3").unwrap(), 3.0);
+        assert!(calculator.quick_evaluate("x").is_err());
+    }
+
+    #[test]
+    fn test_set_variable() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$health", 80.0).unwrap();
+        calculator.set_variable("armor", 20.0).unwrap();
+        assert_eq!(calculator.quick_evaluate("$health + armor").unwrap(), 100.0);
+
+        let result = calculator.evaluate("$health / 2").unwrap();
+        assert_eq!(result, ("$0".to_string(), 40.0));
+
+        calculator.set_variable("$health", 10.0).unwrap();
+        let result = calculator.evaluate("$health").unwrap();
+        assert_eq!(result, ("$1".to_string(), 10.0));
+
+        assert_eq!(calculator.get_variable("$health"), Some(10.0));
+        assert_eq!(calculator.get_variable("$0"), Some(40.0));
+        assert_eq!(calculator.get_variable("$missing"), None);
+    }
+
+    #[test]
+    fn test_set_variable_invalid_name() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.set_variable("$", 1.0).is_err());
+        assert!(calculator.set_variable("$a-b", 1.0).is_err());
+        assert!(calculator.set_variable("pi", 1.0).is_err());
+        assert!(calculator.set_variable("1x", 1.0).is_err());
+        assert!(calculator.set_variable("", 1.0).is_err());
+        assert_eq!(calculator.get_variable("pi"), None);
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.variables().count(), 0);
+
+        for i in 0..12 {
+            calculator.evaluate(&format!("{}", i)).unwrap();
+        }
+        calculator.evaluate("speed = 5").unwrap();
+        calculator.set_variable("$health", 80.0).unwrap();
+
+        let names: Vec<String> = calculator.variables().map(|(name, _)| name).collect();
+        let mut expected: Vec<String> = (0..13).map(|i| format!("${}", i)).collect();
+        expected.extend(["$health", "speed", "$ans"].map(String::from));
+        assert_eq!(names, expected);
+
+        let values: Vec<f64> = calculator.variables().map(|(_, value)| value).collect();
+        assert_eq!(values[11], 11.0);
+        assert_eq!(values[12], 5.0);
+        assert_eq!(values[13], 80.0);
+        assert_eq!(values[14], 5.0);
+    }
+
+    #[test]
+    fn test_variables_order_after_remove_and_reset() {
+        let mut calculator = Calculator::new();
+        for i in 0..10 {
+            calculator.evaluate(&format!("{} * 10", i)).unwrap();
+        }
+        calculator.evaluate("alpha = 1").unwrap();
+        calculator.remove_variable("$3");
+
+        let names: Vec<String> = calculator.variables().map(|(name, _)| name).collect();
+        let mut expected: Vec<String> = (0..11)
+            .filter(|i| *i != 3)
+            .map(|i| format!("${}", i))
+            .collect();
+        expected.extend(["alpha", "$ans"].map(String::from));
+        assert_eq!(names, expected);
+        let values: Vec<f64> = calculator
+            .variables()
+            .map(|(_, value)| value)
+            .take(9)
+            .collect();
+        assert_eq!(
+            values,
+            [0.0, 10.0, 20.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0]
+        );
+
+        calculator.reset();
+        assert_eq!(calculator.variables().count(), 0);
+        calculator.evaluate("7").unwrap();
+        calculator.evaluate("beta = 8").unwrap();
+        let variables: Vec<(String, f64)> = calculator.variables().collect();
+        assert_eq!(
+            variables,
+            [
+                ("$0".to_string(), 7.0),
+                ("$1".to_string(), 8.0),
+                ("beta".to_string(), 8.0),
+                ("$ans".to_string(), 8.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_results() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("zeta = 100").unwrap();
+        for i in 0..10 {
+            calculator.evaluate(&format!("{} + 1", i)).unwrap();
+        }
+        calculator.evaluate("alpha = 0.5").unwrap();
+        calculator.remove_variable("$5");
+
+        // `$0` is the first assignment, `$1` to `$10` are the sums, and `$11` is the last assignment
+        let mut expected = vec![("$0".to_string(), 100.0)];
+        expected.extend(
+            (1..=10)
+                .filter(|i| *i != 5)
+                .map(|i| (format!("${}", i), i as f64)),
+        );
+        expected.push(("$11".to_string(), 0.5));
+        assert_eq!(calculator.results().collect::<Vec<_>>(), expected);
+
+        calculator.reset();
+        assert_eq!(calculator.results().count(), 0);
+        calculator.evaluate("3").unwrap();
+        calculator.evaluate("b = 4").unwrap();
+        assert_eq!(
+            calculator.results().collect::<Vec<_>>(),
+            [("$0".to_string(), 3.0), ("$1".to_string(), 4.0)]
+        );
+    }
+
+    #[test]
+    fn test_clear_history() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$health", 80.0).unwrap();
+        calculator
+            .register_table("armor", &[(0.0, 0.0), (10.0, 5.0)], false)
+            .unwrap();
+        calculator.set_strict_division(true);
+        calculator.evaluate("speed = 5").unwrap();
+        calculator.evaluate("1 + 2").unwrap();
+
+        calculator.clear_history(false);
+        assert_eq!(calculator.get_variable("$0"), None);
+        assert_eq!(calculator.get_variable("$1"), None);
+        assert_eq!(calculator.get_variable("$ans"), Some(3.0));
+        assert_eq!(calculator.get_variable("$health"), Some(80.0));
+        assert_eq!(calculator.get_variable("speed"), Some(5.0));
+        assert_eq!(calculator.quick_evaluate("armor(4)").unwrap(), 2.0);
+        assert!(calculator.quick_evaluate("1 / 0").is_err());
+
+        let result = calculator.evaluate("speed * 2").unwrap();
+        assert_eq!(result, ("$0".to_string(), 10.0));
+        assert_eq!(calculator.get_variable("speed"), Some(5.0));
+
+        calculator.clear_history(true);
+        assert_eq!(calculator.get_variable("$ans"), None);
+        assert_eq!(calculator.get_variable("$health"), Some(80.0));
+        let names: Vec<String> = calculator.variables().map(|(name, _)| name).collect();
+        assert_eq!(names, ["$health", "speed"]);
+    }
+
+    #[test]
+    fn test_evaluate_full() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$x", 1.5).unwrap();
+        let input = "radius = 2*$x + sqrt(9) * ($x - 0.5) + 12345678901234567890 * 0";
+        let evaluation = calculator.evaluate_full(input).unwrap();
+        assert_eq!(evaluation.name(), "radius");
+        assert_eq!(evaluation.value(), 6.0);
+        assert!(evaluation.is_assignment());
+        assert_eq!(evaluation.warnings().len(), 1);
+        assert_eq!(evaluation.warnings()[0].span().start, 39);
+        assert_eq!(
+            evaluation.normalized(),
+            Some("radius = 2 * $x + sqrt(9) * ($x - 0.5) + 1.2345678901234567e19 * 0")
+        );
+        assert_eq!(evaluation.dependencies().unwrap(), ["$x"]);
+        assert!(evaluation.timings().is_some());
+        assert_eq!(calculator.get_variable("radius"), Some(6.0));
+
+        let evaluation = calculator.evaluate_full("radius + radius").unwrap();
+        assert_eq!(evaluation.name(), "$1");
+        assert!(!evaluation.is_assignment());
+        assert!(evaluation.warnings().is_empty());
+        assert_eq!(evaluation.dependencies().unwrap(), ["radius"]);
+
+        // The wrapper returns the same name and value, and both are recorded in the history
+        let result = calculator.evaluate("radius = 2*$x + sqrt(9)").unwrap();
+        assert_eq!(result, ("radius".to_string(), 6.0));
+        assert_eq!(
+            calculator.evaluate("1 + 2").unwrap(),
+            ("$3".to_string(), 3.0)
+        );
+        assert_eq!(calculator.history().len(), 4);
+        assert!(calculator.evaluate_full("1 +").is_err());
+    }
+
+    /// Prints an expression with every operation in parentheses, to show how it was parsed.
+    struct Parenthesizer;
+
+    impl ast::Visitor<String> for Parenthesizer {
+        fn visit(&self, expr: &ast::Expr) -> Result<String, CalcError> {
+            let list = |args: &[ast::Expr]| -> Result<String, CalcError> {
+                let args: Result<Vec<_>, _> = args.iter().map(|arg| self.visit(arg)).collect();
+                Ok(args?.join(", "))
+            };
+            Ok(match expr {
+                ast::Expr::Number(n) => n.to_string(),
+                ast::Expr::Variable(name) => name.clone(),
+                ast::Expr::UnaryOp { op, operand } => format!("{:?}({})", op, self.visit(operand)?),
+                ast::Expr::BinaryOp { op, left, right } => {
+                    format!("({} {:?} {})", self.visit(left)?, op, self.visit(right)?)
+                }
+                ast::Expr::NaryOp { op, args } => format!("{:?}({})", op, list(args)?),
+                ast::Expr::Call { name, args } => format!("{}({})", name, list(args)?),
+                ast::Expr::Assign { name, value } => format!("{} = {}", name, self.visit(value)?),
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        use ast::Visitor;
+
+        let expr = parse("total = 1 + 2 * -$x ^ 2 + cal(3!)").unwrap();
+        assert_eq!(
+            Parenthesizer.visit(&expr).unwrap(),
+            "total = ((1 Plus (2 Star Minus(($x Caret 2)))) Plus cal(Bang(3)))"
+        );
+        let expr = parse("sqrt(max(2, 4)) % logsumexp(0, pi)").unwrap();
+        assert_eq!(
+            Parenthesizer.visit(&expr).unwrap(),
+            "(Keyword(Sqrt)((2 Keyword(Max) 4)) Percent Keyword(Logsumexp)(0, 3.141592653589793))"
+        );
+
+        let err = parse("1 +").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+
+        // A parsed expression can be transformed and evaluated later
+        let ast::Expr::Assign { value, .. } = parse("y = $x + 1").unwrap() else {
+            panic!("expected an assignment");
+        };
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$x", 2.0).unwrap();
+        assert_eq!(calculator.quick_evaluate_expr(*value).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_quick_evaluate_expr() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$x", 4.0).unwrap();
+        let expr = ast::Expr::BinaryOp {
+            op: ast::Token::Plus,
+            left: Box::new(ast::Expr::Variable("$x".to_string())),
+            right: Box::new(ast::Expr::UnaryOp {
+                op: ast::Token::Keyword(Word::Sqrt),
+                operand: Box::new(ast::Expr::Number(9.0)),
+            }),
+        };
+        assert_eq!(calculator.quick_evaluate_expr(expr).unwrap(), 7.0);
+        assert!(calculator
+            .quick_evaluate_expr(ast::Expr::Variable("$y".to_string()))
+            .is_err());
+        assert!(calculator.get_variable("$0").is_none());
+    }
+
+    #[test]
+    fn test_compiled_matches_quick_evaluate() {
+        let mut calculator = Calculator::new();
+        let input = "sqrt($x^2 + 1) * sin($x) - $x % 3 + max($x, 2)";
+        let compiled = calculator.compile(input).unwrap();
+        assert_eq!(compiled.input(), input);
+        for i in -200..=200 {
+            let x = i as f64 / 8.0;
+            calculator.set_variable("$x", x).unwrap();
+            let expected = calculator.quick_evaluate(input).unwrap();
+            let actual = calculator.evaluate_compiled(&compiled).unwrap();
+            assert_eq!(actual.to_bits(), expected.to_bits(), "x = {}", x);
+        }
+        assert!(calculator.get_variable("$0").is_none());
+    }
+
+    #[test]
+    fn test_evaluate_compiled_with() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$x", 100.0).unwrap();
+        calculator.set_variable("$k", 3.0).unwrap();
+        let compiled = calculator.compile("$k * $x + 1").unwrap();
+
+        let mut vars = HashMap::new();
+        for x in 0..5 {
+            vars.insert("$x".to_string(), x as f64);
+            assert_eq!(
+                calculator.evaluate_compiled_with(&compiled, &vars).unwrap(),
+                3.0 * x as f64 + 1.0
+            );
+        }
+        assert_eq!(calculator.get_variable("$x"), Some(100.0));
+        assert_eq!(calculator.evaluate_compiled(&compiled).unwrap(), 301.0);
+
+        let compiled = calculator.compile("$y + 1").unwrap();
+        assert!(calculator.evaluate_compiled(&compiled).is_err());
+        assert!(calculator.compile("1 +").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_rpn() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.evaluate_rpn("3 4 + 2 *").unwrap(),
+            ("$0".to_string(), 14.0)
+        );
+        assert_eq!(calculator.evaluate_rpn("9 sqrt").unwrap().1, 3.0);
+        assert_eq!(calculator.evaluate_rpn("2 3 pow").unwrap().1, 8.0);
+        assert_eq!(calculator.evaluate_rpn("$0 1 neg -").unwrap().1, 15.0);
+        assert_eq!(
+            calculator.evaluate_rpn("0 0 logsumexp(2) ans +").unwrap().1,
+            2f64.ln() + 15.0
+        );
+        assert_eq!(
+            calculator.evaluate_rpn("2 pi * radius =").unwrap(),
+            ("radius".to_string(), std::f64::consts::TAU)
+        );
+        assert_eq!(calculator.get_variable("$5"), Some(std::f64::consts::TAU));
+        assert_eq!(calculator.get_variable("$ans"), Some(std::f64::consts::TAU));
+        assert_eq!(calculator.history()[0].input(), "3 4 + 2 *");
+
+        calculator
+            .register_function(
+                "lerp",
+                3,
+                |args| Ok(args[0] + (args[1] - args[0]) * args[2]),
+            )
+            .unwrap();
+        assert_eq!(
+            calculator.evaluate_rpn("10 20 0.25 lerp(3)").unwrap().1,
+            12.5
+        );
+
+        // Infix input converted to RPN evaluates to the same result
+        for input in [
+            "1 + 2 * 3",
+            "-(2 - 5)^2 % 4",
+            "3! / 15% - max(1, 2)",
+            "max(1, 7, 3) - min(4, 2, 8, 6)",
+            "logsumexp(1, 2, 3)",
+            "clamp(7, 1, 2 + 3)",
+            "round(2.5) + round(1.23456, 2) + round(1234, -2)",
+        ] {
+            let mut infix = Calculator::new();
+            infix.set_percent_suffix(true);
+            let expected = infix.quick_evaluate(input).unwrap();
+            let rpn = infix.parse(input).unwrap().to_rpn();
+            assert_eq!(infix.evaluate_rpn(&rpn).unwrap().1, expected, "{}", rpn);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rpn_errors() {
+        let mut calculator = Calculator::new();
+        let cases = [
+            (
+                "3 +",
+                "Stack underflow: operator needs 2 operand(s), found 1",
+                2,
+            ),
+            (
+                "sqrt",
+                "Stack underflow: operator needs 1 operand(s), found 0",
+                0,
+            ),
+            (
+                "1 2 3 +",
+                "2 values left on the stack; expected one result",
+                2,
+            ),
+            ("1 2", "2 values left on the stack; expected one result", 2),
+            ("", "Stack is empty; expected one result", 0),
+            ("1 ( 2 +", "Unexpected token in RPN input", 2),
+            (
+                "1 2 logsumexp",
+                "Expected argument count, such as logsumexp(2)",
+                4,
+            ),
+            ("1 cal(x)", "Expected argument count, such as cal(2)", 2),
+        ];
+        for (input, message, start) in cases {
+            let err = calculator.evaluate_rpn(input).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", input);
+            assert_eq!(err.message(), message, "{}", input);
+            assert_eq!(err.span().unwrap().start, start, "{}", input);
+        }
+        let err = calculator.evaluate_rpn("2 foo *").unwrap_err();
+        assert_eq!(err.message(), "Variable not found: foo");
+        assert!(calculator.get_variable("$0").is_none());
+        assert!(calculator.history().is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("2.5 * 5").unwrap();
+        calculator.evaluate("radius = 2").unwrap();
+        assert!(calculator.evaluate("1 +").is_err());
+        calculator.quick_evaluate("radius * 2").unwrap();
+        calculator.evaluate_display("$0 / 5").unwrap();
+
+        let expected = [
+            HistoryEntry::new("2.5 * 5", "$0", 12.5),
+            HistoryEntry::new("radius = 2", "radius", 2.0),
+            HistoryEntry::new("$0 / 5", "$2", 2.5),
+        ];
+        assert_eq!(calculator.history(), expected);
+
+        assert!(calculator.evaluate_all_atomic(&["1", "1 / x"]).is_err());
+        assert_eq!(calculator.history().len(), 3);
+        calculator.evaluate_all(&["1", "2"]).unwrap();
+        assert_eq!(calculator.history()[4].input(), "2");
+
+        calculator.clear_history(false);
+        assert!(calculator.history().is_empty());
+        calculator.evaluate("3").unwrap();
+        calculator.reset();
+        assert!(calculator.history().is_empty());
+    }
+
+    #[test]
+    fn test_set_numbered_variable() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.set_variable("$0", 1.0).is_err());
+        calculator.set_variable("$01", 1.0).unwrap();
+        let result = calculator.evaluate("$01 + 1").unwrap();
+        assert_eq!(result, ("$0".to_string(), 2.0));
+        assert_eq!(calculator.get_variable("$1"), None);
+    }
+
+    #[test]
+    fn test_error_caret_multi_byte() {
+        let calculator = Calculator::new();
+        let input = "√\u{00A0}4 § 2";
+        let err = calculator.quick_evaluate(input).unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!(span.start, 4);
+        assert_eq!(span.byte_start, 7);
+        assert_eq!(err.caret().unwrap(), "    ^");
+
+        let input = "(π + 1.2.3";
+        let err = calculator.quick_evaluate(input).unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start, span.end), (5, 9));
+        assert_eq!(&input[span.byte_start..span.byte_end], "1.2.");
+        assert_eq!(err.caret().unwrap(), "     ^^^^");
+
+        let input = "(π + 1";
+        let err = calculator.quick_evaluate(input).unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start, span.byte_start), (6, 7));
+        assert_eq!(err.caret().unwrap(), "      ^");
+    }
+
+    #[test]
+    fn test_remove_variable() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("1").unwrap();
+        calculator.evaluate("radius = 2").unwrap();
+        calculator.evaluate("3").unwrap();
+
+        assert_eq!(calculator.remove_variable("$1"), Some(2.0));
+        assert_eq!(
+            calculator.quick_evaluate("$1").unwrap_err().message(),
+            "Variable not found: $1"
+        );
+        assert_eq!(calculator.quick_evaluate("$0 + $2").unwrap(), 4.0);
+        assert_eq!(calculator.quick_evaluate("radius").unwrap(), 2.0);
+
+        assert_eq!(calculator.remove_variable("radius"), Some(2.0));
+        assert!(calculator.quick_evaluate("radius").is_err());
+        assert_eq!(calculator.remove_variable("$2"), Some(3.0));
+        assert_eq!(calculator.get_variable("$ans"), Some(3.0));
+
+        let result = calculator.evaluate("4").unwrap();
+        assert_eq!(result, ("$3".to_string(), 4.0));
+        let names: Vec<String> = calculator.variables().map(|(name, _)| name).collect();
+        assert_eq!(names, ["$0", "$3", "$ans"]);
+    }
+
+    #[test]
+    fn test_remove_missing_variable() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.remove_variable("$0"), None);
+        assert_eq!(calculator.remove_variable("missing"), None);
+        calculator.evaluate("1").unwrap();
+        assert_eq!(calculator.remove_variable("$0"), Some(1.0));
+        assert_eq!(calculator.remove_variable("$0"), None);
+        assert_eq!(calculator.remove_variable("$ans"), Some(1.0));
+        assert_eq!(calculator.remove_variable("$ans"), None);
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let mut calculator = Calculator::new();
+        let input = vec!["1"; 100].join(" + ");
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), 100.0);
+
+        calculator.set_max_nodes(100);
+        let err = calculator.evaluate(&input).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Budget);
+        assert_eq!(err.message(), "Evaluation budget exceeded: too many nodes");
+        assert!(calculator.get_variable("$0").is_none());
+
+        // The budget applies to each expression separately
+        let input = vec!["1"; 50].join(" + ");
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), 50.0);
+        assert_eq!(calculator.quick_evaluate(&input).unwrap(), 50.0);
+    }
+
+    #[test]
+    fn test_ans_keyword() {
+        let mut calculator = Calculator::new();
+        let err = calculator.quick_evaluate("ans + 1").unwrap_err();
+        assert_eq!(err.message(), "Variable not found: $ans");
+
+        calculator.evaluate("2 + 2").unwrap();
+        assert_eq!(calculator.quick_evaluate("ans").unwrap(), 4.0);
+        let result = calculator.evaluate("ans * ans").unwrap();
+        assert_eq!(result, ("$1".to_string(), 16.0));
+        assert_eq!(calculator.evaluate("ans + $ans").unwrap().1, 32.0);
+        assert!(calculator.evaluate("ans = 1").is_err());
+        assert!(calculator.set_variable("ans", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_max_results_error() {
+        let mut calculator = Calculator::new();
+        calculator.set_max_results(2, ResultOverflow::Error);
+        assert_eq!(calculator.evaluate("1").unwrap(), ("$0".to_string(), 1.0));
+        assert_eq!(calculator.evaluate("2").unwrap(), ("$1".to_string(), 2.0));
+
+        let err = calculator.evaluate("3").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Budget);
+        assert_eq!(
+            err.message(),
+            "Result history is full; clear the history or reset to continue"
+        );
+        assert_eq!(calculator.get_variable("$ans"), Some(2.0));
+        assert_eq!(calculator.quick_evaluate("$0 + $1").unwrap(), 3.0);
+
+        // Removing a result does not free its number
+        calculator.remove_variable("$0");
+        assert!(calculator.evaluate("3").is_err());
+
+        calculator.clear_history(false);
+        assert_eq!(calculator.evaluate("3").unwrap(), ("$0".to_string(), 3.0));
+    }
+
+    #[test]
+    fn test_max_results_wrap() {
+        let mut calculator = Calculator::new();
+        calculator.set_max_results(2, ResultOverflow::Wrap);
+        calculator.evaluate("1").unwrap();
+        calculator.evaluate("2").unwrap();
+        assert_eq!(calculator.evaluate("3").unwrap(), ("$0".to_string(), 3.0));
+        assert_eq!(calculator.get_variable("$0"), Some(3.0));
+        assert_eq!(calculator.get_variable("$1"), Some(2.0));
+        assert_eq!(calculator.get_variable("$2"), None);
+
+        // A removed result stays removed until its number is reused
+        calculator.remove_variable("$1");
+        assert_eq!(calculator.get_variable("$1"), None);
+        let names: Vec<String> = calculator.variables().map(|(name, _)| name).collect();
+        assert_eq!(names, ["$0", "$ans"]);
+        assert_eq!(
+            calculator.evaluate("$0 + 1").unwrap(),
+            ("$1".to_string(), 4.0)
+        );
+        assert_eq!(calculator.evaluate("5").unwrap(), ("$0".to_string(), 5.0));
+
+        assert!(calculator.evaluate_all_atomic(&["6", "1 +"]).is_err());
+        assert_eq!(calculator.evaluate("7").unwrap(), ("$1".to_string(), 7.0));
+    }
+
+    #[test]
+    fn test_missing_argument() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_function(
+                "lerp",
+                3,
+                |args| Ok(args[0] + (args[1] - args[0]) * args[2]),
+            )
+            .unwrap();
+        let cases = [
+            ("pow(2, )", "Missing argument 2 of pow", 7),
+            ("pow( , 3)", "Missing argument 1 of pow", 5),
+            ("sqrt(,)", "Missing argument 1 of sqrt", 5),
+            ("logsumexp(,1)", "Missing argument 1 of logsumexp", 10),
+            ("logsumexp(1, ,2)", "Missing argument 2 of logsumexp", 13),
+            ("lerp(0,   , 1)", "Missing argument 2 of lerp", 10),
+        ];
+        for (input, message, start) in cases {
+            let err = calculator.quick_evaluate(input).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", input);
+            assert_eq!(err.message(), message, "{}", input);
+            assert_eq!(err.span().unwrap().start, start, "{}", input);
+        }
+
+        // Trailing commas are still allowed
+        assert_eq!(calculator.quick_evaluate("pow(2, 3,)").unwrap(), 8.0);
+        assert_eq!(calculator.quick_evaluate("sqrt(4 ,)").unwrap(), 2.0);
+        assert!(calculator.quick_evaluate("logsumexp(0, 0,)").is_ok());
+        assert_eq!(calculator.quick_evaluate("lerp(0, 10, 0.5,)").unwrap(), 5.0);
+    }
+
+    #[test]
+    fn test_keyword_arity() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("sqrt(1, 2)", "sqrt expects 1 argument(s), got 2", 0),
+            ("sqrt()", "sqrt expects 1 argument(s), got 0", 0),
+            ("1 + pow(1)", "pow expects 2 argument(s), got 1", 4),
+            ("pow(1, 2, 3,)", "pow expects 2 argument(s), got 3", 0),
+        ];
+        for (input, message, start) in cases {
+            let err = calculator.quick_evaluate(input).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Parse, "{}", input);
+            assert_eq!(err.message(), message, "{}", input);
+            assert_eq!(err.span().unwrap().start, start, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_max_arguments() {
+        let mut calculator = Calculator::new();
+        let input = format!("logsumexp({})", vec!["0"; 1000].join(", "));
+        assert!(calculator.quick_evaluate(&input).is_ok());
+
+        calculator.set_max_arguments(10);
+        let err = calculator.quick_evaluate(&input).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Budget);
+        assert_eq!(
+            err.message(),
+            "Evaluation budget exceeded: too many arguments"
+        );
+        assert!(calculator.quick_evaluate("logsumexp(0, 0)").is_ok());
+    }
+
+    #[test]
+    fn test_quick_evaluate_into() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("width = 3").unwrap();
+
+        let mut scratch = HashMap::new();
+        let area = calculator
+            .quick_evaluate_into("width * 4", &mut scratch, "area")
+            .unwrap();
+        assert_eq!(area, 12.0);
+        let volume = calculator
+            .quick_evaluate_into("area * 2", &mut scratch, "$volume")
+            .unwrap();
+        assert_eq!(volume, 24.0);
+        calculator
+            .quick_evaluate_into("width + 1", &mut scratch, "width")
+            .unwrap();
+        assert_eq!(
+            calculator
+                .quick_evaluate_into("width", &mut scratch, "w")
+                .unwrap(),
+            4.0
+        );
+
+        assert_eq!(scratch.len(), 4);
+        assert_eq!(scratch["$volume"], 24.0);
+        assert_eq!(calculator.get_variable("width"), Some(3.0));
+        assert_eq!(calculator.get_variable("area"), None);
+        assert_eq!(calculator.get_variable("$1"), None);
+        assert_eq!(calculator.variables().count(), 3);
+    }
+
+    #[test]
+    fn test_quick_evaluate_into_errors() {
+        let calculator = Calculator::new();
+        let mut scratch = HashMap::new();
+        assert!(calculator
+            .quick_evaluate_into("1", &mut scratch, "$0")
+            .is_err());
+        assert!(calculator
+            .quick_evaluate_into("1", &mut scratch, "pi")
+            .is_err());
+        assert!(calculator
+            .quick_evaluate_into("$missing", &mut scratch, "x")
+            .is_err());
+        assert!(scratch.is_empty());
+    }
+
+    #[test]
+    fn test_register_function() {
+        let mut calculator = Calculator::new();
+        let prices = HashMap::from([(1_u64, 2.5), (2, 4.0)]);
+        calculator
+            .register_function("price", 1, move |args| {
+                prices
+                    .get(&(args[0] as u64))
+                    .copied()
+                    .ok_or_else(|| CalcError::new("Unknown SKU", None))
+            })
+            .unwrap();
+        calculator
+            .register_function(
+                "lerp",
+                3,
+                |args| Ok(args[0] + (args[1] - args[0]) * args[2]),
+            )
+            .unwrap();
+
+        assert_eq!(calculator.quick_evaluate("price(1) * 2").unwrap(), 5.0);
+        let result = calculator.evaluate("lerp(0, price(2), 0.5)").unwrap();
+        assert_eq!(result, ("$0".to_string(), 2.0));
+        assert_eq!(
+            calculator.quick_evaluate("price(3)").unwrap_err().message(),
+            "Unknown SKU"
+        );
+
+        assert!(calculator.unregister_function("price"));
+        assert!(!calculator.unregister_function("price"));
+        assert_eq!(
+            calculator.quick_evaluate("price(1)").unwrap_err().message(),
+            "Unknown function: price"
+        );
+    }
+
+    #[test]
+    fn test_register_function_send() {
+        let mut calculator = Calculator::new();
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        calculator
+            .register_function("count", 1, move |args| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok(args[0])
+            })
+            .unwrap();
+        let handle =
+            std::thread::spawn(move || calculator.evaluate("count(2) + count(3)").unwrap());
+        assert_eq!(handle.join().unwrap(), ("$0".to_string(), 5.0));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Calculator>();
+
+        let mut calculator = Calculator::new();
+        calculator.evaluate("x = 2").unwrap();
+        calculator.set_usage_stats(true);
+        let calculator = &calculator;
+        let results: Vec<f64> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let input = format!("sqrt(8 * x * {}) + 0 * rand()", i * i);
+                    scope.spawn(move || calculator.quick_evaluate(&input).unwrap())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(results, [0.0, 4.0, 8.0, 12.0]);
+        assert_eq!(calculator.usage_stats()[&Word::Sqrt], 4);
+        assert_eq!(calculator.usage_stats()[&Word::Rand], 4);
+    }
+
+    #[test]
+    fn test_register_function_errors() {
+        let mut calculator = Calculator::new();
+        calculator.register_function("two", 0, |_| Ok(2.0)).unwrap();
+        assert_eq!(calculator.quick_evaluate("two()").unwrap(), 2.0);
+        assert_eq!(
+            calculator.quick_evaluate("two(1)").unwrap_err().message(),
+            "two expects 0 argument(s), got 1"
+        );
+        assert!(calculator
+            .register_function("sqrt", 1, |_| Ok(0.0))
+            .is_err());
+        assert!(calculator.register_function("$f", 1, |_| Ok(0.0)).is_err());
+
+        // A function replaces a table with the same name, and vice versa
+        calculator
+            .register_table("two", &[(0.0, 0.0), (1.0, 1.0)], false)
+            .unwrap();
+        assert_eq!(calculator.quick_evaluate("two(0.5)").unwrap(), 0.5);
+        assert!(calculator.quick_evaluate("two()").is_err());
+    }
+
+    #[test]
+    fn test_error_quote_truncated() {
+        let mut calculator = Calculator::new();
+        let name = format!("Huge{}", "x".repeat(1_000_000));
+        let err = calculator
+            .quick_evaluate(&format!("{}(1)", name))
+            .unwrap_err();
+        assert_eq!(err.message(), format!("Unknown function: {}…", &name[..64]));
+        assert!(err.message().len() < 100);
+
+        calculator.set_max_quote_length(8);
+        let err = calculator
+            .quick_evaluate(&format!("{}(1)", name))
+            .unwrap_err();
+        assert_eq!(err.message(), "Unknown function: Hugexxxx…");
+
+        calculator
+            .register_function("LongName", 1, |args| Ok(args[0]))
+            .unwrap();
+        let err = calculator.quick_evaluate("LongName(1, 2)").unwrap_err();
+        assert_eq!(err.message(), "LongName expects 1 argument(s), got 2");
+        let err = calculator.quick_evaluate("LongName(,)").unwrap_err();
+        assert!(err.is_parse_error());
+        assert_eq!(err.message(), "Missing argument 1 of LongName");
+        let err = calculator
+            .quick_evaluate(&format!("{}(1,,)", name))
+            .unwrap_err();
+        assert_eq!(err.message(), "Missing argument 2 of Hugexxxx…");
+    }
+
+    #[test]
+    fn test_evaluate_bool() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.evaluate_bool("1 and 2").unwrap());
+        assert!(!calculator.evaluate_bool("0 or 0").unwrap());
+        assert!(calculator.evaluate_bool("1").unwrap());
+        assert!(!calculator.evaluate_bool("-0").unwrap());
+        assert_eq!(
+            calculator.evaluate_bool("2.5").unwrap_err().message(),
+            "Result is not a boolean: 2.5"
+        );
+        assert!(calculator.evaluate_bool("-1").is_err());
+
+        calculator.set_truthy(true);
+        assert!(calculator.evaluate_bool("2.5").unwrap());
+        assert!(calculator.evaluate_bool("-1").unwrap());
+        assert!(calculator.evaluate_bool("inf").unwrap());
+        assert!(!calculator.evaluate_bool("0").unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_bool_nan() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$nan", f64::NAN).unwrap();
+        assert_eq!(
+            calculator.evaluate_bool("$nan").unwrap_err().message(),
+            "Result is not a boolean: NaN"
+        );
+        calculator.set_truthy(true);
+        assert!(calculator.evaluate_bool("$nan").is_err());
+        assert!(calculator.evaluate_bool("$missing").is_err());
+    }
+
+    #[test]
+    fn test_factorial() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("0!", 1.0),
+            ("5!", 120.0),
+            ("(2 + 3)!", 120.0),
+            ("3!!", 720.0),
+            ("-3!", -6.0),
+            ("2 ^ 3!", 64.0),
+            ("2 * 3!", 12.0),
+            ("171!", f64::INFINITY),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        let largest = calculator.quick_evaluate("170!").unwrap();
+        assert!(largest.is_finite());
+        assert!((largest / 7.257415615307994e306 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_factorial_errors() {
+        let calculator = Calculator::new();
+        for input in ["2.5!", "(-3)!", "!3"] {
+            assert!(calculator.quick_evaluate(input).is_err(), "{}", input);
+        }
+        assert_eq!(
+            calculator.quick_evaluate("2.5!").unwrap_err().message(),
+            "Factorial requires a non-negative integer"
+        );
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        let mut calculator = Calculator::new();
+        calculator.set_implicit_multiplication(true);
+        calculator.set_variable("x", 2.0).unwrap();
+        let cases = [
+            ("2pi", 2.0 * std::f64::consts::PI),
+            ("3(4 + 5)", 27.0),
+            ("(1 + 2)(3 + 4)", 21.0),
+            ("2sqrt(9)", 6.0),
+            ("(1 + 2)x", 6.0),
+            ("3x", 6.0),
+            ("2^3x", 16.0),
+            ("6 / 2x", 6.0),
+            ("-2x", -4.0),
+            ("1 + 2x", 5.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        calculator.set_variable("$y", 5.0).unwrap();
+        assert_eq!(calculator.quick_evaluate("2$y").unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_implicit_multiplication_errors() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("2pi").is_err());
+        assert!(calculator.quick_evaluate("3(4 + 5)").is_err());
+
+        calculator.set_implicit_multiplication(true);
+        assert!(calculator.quick_evaluate("1 2").is_err());
+        assert!(calculator.quick_evaluate("(1)2").is_err());
+    }
+
+    #[test]
+    fn test_dump_tokens() {
+        let calculator = Calculator::new();
+        let dump = calculator
+            .dump_tokens("r = sqrt(1.5e-3) + $0 * π! or cal(2)")
+            .unwrap();
+        let expected = "\
+IDENTIFIER r @0..1
+EQUAL @2..3
+KEYWORD sqrt @4..8
+LPAREN @8..9
+NUMBER 0.0015 @9..15
+RPAREN @15..16
+PLUS @17..18
+VARIABLE $0 @19..21
+STAR @22..23
+KEYWORD pi @24..25
+BANG @25..26
+OR @27..29
+IDENTIFIER cal @30..33
+LPAREN @33..34
+NUMBER 2 @34..35
+RPAREN @35..36";
+        assert_eq!(dump, expected);
+        assert_eq!(calculator.dump_tokens("").unwrap(), "");
+        assert!(calculator.dump_tokens("1 @ 2").is_err());
+    }
+
+    #[test]
+    fn test_unary_plus() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("+5", 5.0),
+            ("3 * +2", 6.0),
+            ("pow(+2, +3)", 8.0),
+            ("(+1 + 2)", 3.0),
+            ("++5", 5.0),
+            ("+-5", -5.0),
+            ("1 - +2", -1.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        assert!(calculator.quick_evaluate("+").is_err());
+        assert!(calculator.quick_evaluate("5 +").is_err());
+    }
+
+    #[test]
+    fn test_quick_evaluate_shadowed_template() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("10").unwrap();
+        let vars = HashMap::from([("$0".to_string(), 2.0)]);
+        assert_eq!(
+            calculator.quick_evaluate_shadowed("$0 * 3", &vars).unwrap(),
+            6.0
+        );
+        assert_eq!(calculator.get_variable("$0"), Some(10.0));
+        assert_eq!(calculator.quick_evaluate("$0 * 3").unwrap(), 30.0);
+
+        let calculator = Calculator::new();
+        let records = [(10.0, 0.25), (4.0, 0.5)];
+        for (price, tax_rate) in records {
+            let vars = HashMap::from([
+                ("$price".to_string(), price),
+                ("$tax_rate".to_string(), tax_rate),
+            ]);
+            assert_eq!(
+                calculator
+                    .quick_evaluate_shadowed("$price * (1 + $tax_rate)", &vars)
+                    .unwrap(),
+                price * (1.0 + tax_rate)
+            );
+        }
+        assert_eq!(calculator.variables().count(), 0);
+
+        let vars = HashMap::from([("$price".to_string(), 10.0)]);
+        let err = calculator
+            .quick_evaluate_shadowed("$price * (1 + $tax_rate)", &vars)
+            .unwrap_err();
+        assert_eq!(err.message(), "Variable not found: $tax_rate");
+    }
+
+    #[test]
+    fn test_quick_evaluate_shadowed() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("10").unwrap();
+        calculator.evaluate("rate = 0.5").unwrap();
+        let before: Vec<(String, f64)> = calculator.variables().collect();
+
+        let shadow = HashMap::from([("$0".to_string(), 20.0), ("bonus".to_string(), 3.0)]);
+        assert_eq!(
+            calculator
+                .quick_evaluate_shadowed("$0 * rate + bonus", &shadow)
+                .unwrap(),
+            13.0
+        );
+        assert_eq!(
+            calculator
+                .quick_evaluate_shadowed("x = $0", &shadow)
+                .unwrap(),
+            20.0
+        );
+        assert_eq!(
+            calculator
+                .quick_evaluate_shadowed("$missing", &shadow)
+                .unwrap_err()
+                .message(),
+            "Variable not found: $missing"
+        );
+
+        let after: Vec<(String, f64)> = calculator.variables().collect();
+        assert_eq!(before.len(), after.len());
+        for ((name_a, a), (name_b, b)) in before.iter().zip(&after) {
+            assert_eq!(name_a, name_b);
+            assert_eq!(a.to_bits(), b.to_bits());
+        }
+        assert_eq!(calculator.quick_evaluate("$0 * rate").unwrap(), 5.0);
+        assert!(calculator.quick_evaluate("bonus").is_err());
+    }
+
+    #[test]
+    fn test_power_precedence() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("2 ^ 3 ^ 2", 512.0),
+            ("(2 ^ 3) ^ 2", 64.0),
+            ("-2 ^ 2", -4.0),
+            ("(-2) ^ 2", 4.0),
+            ("2 ^ -3", 0.125),
+            ("2 ^ -1 ^ 2", 0.5),
+            ("3 * 2 ^ 2", 12.0),
+            ("2 ^ 2 * 3", 12.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_literal() {
+        let mut calculator = Calculator::new();
+        let input = "9".repeat(10_000);
+        let err = calculator.quick_evaluate(&input).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Scan);
+        assert_eq!(err.message(), "Number literal is too long");
+
+        calculator.set_max_literal_length(20_000);
+        assert_eq!(calculator.evaluate(&input).unwrap().1, f64::INFINITY);
+        assert!(calculator.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_literal_precision_warning() {
+        let mut calculator = Calculator::new();
+        let input = "1 + 1234567890123456789012345678901234567890";
+        let result = calculator.evaluate(input).unwrap();
+        assert_eq!(result.1, 1.2345678901234568e39);
+        let warnings = calculator.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message(),
+            "Number literal has more digits than can be represented"
+        );
+        assert_eq!((warnings[0].span().start, warnings[0].span().end), (4, 44));
+
+        // Evaluating without storing a result leaves the warnings unchanged
+        calculator.quick_evaluate("0.1 + 2").unwrap();
+        assert_eq!(calculator.warnings(), warnings);
+        calculator.evaluate("0.1 + 2").unwrap();
+        assert!(calculator.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_stacked_unary() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("--5", 5.0),
+            ("---5", -5.0),
+            ("-(-(3))", 3.0),
+            ("-sqrt(9)", -3.0),
+            ("-+-5", 5.0),
+            ("2 - -3", 5.0),
+            ("--2 ^ 2", 4.0),
+            ("-(-2 + 1)", 1.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_booleans() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$premium", 1.0).unwrap();
+        let cases = [
+            ("true", 1.0),
+            ("false", 0.0),
+            ("true and false", 0.0),
+            ("false or true", 1.0),
+            ("$premium and true", 1.0),
+            ("false and (1 / 0)", 0.0),
+            ("true + true", 2.0),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        assert!(calculator.evaluate_bool("true or false").unwrap());
+        assert!(calculator.evaluate("true = 0").is_err());
+        assert!(calculator.set_variable("false", 1.0).is_err());
+        assert!(calculator
+            .register_function("true", 0, |_| Ok(1.0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_variable_ordering() {
+        let build = |statements: &[&str], named: &[(&str, f64)]| {
+            let mut calculator = Calculator::new();
+            for (name, value) in named {
+                calculator.set_variable(name, *value).unwrap();
+            }
+            calculator.evaluate_all(statements).unwrap();
+            calculator
+        };
+        let a = build(&["1", "zeta = 2", "3"], &[("alpha", 4.0), ("$b", 5.0)]);
+        let b = build(&["1", "zeta = 2", "3"], &[("$b", 5.0), ("alpha", 4.0)]);
+
+        let names: Vec<String> = a.variables().map(|(name, _)| name).collect();
+        assert_eq!(names, ["$0", "$1", "$2", "$b", "alpha", "zeta", "$ans"]);
+        assert_eq!(a.export_variables(), b.export_variables());
+        assert_eq!(
+            format!("{:?}", a),
+            "Calculator { variables: [(\"$0\", 1.0), (\"$1\", 2.0), (\"$2\", 3.0), \
+             (\"$b\", 5.0), (\"alpha\", 4.0), (\"zeta\", 2.0), (\"$ans\", 3.0)], .. }"
+        );
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+
+    #[test]
+    fn test_import_state() {
+        let mut a = Calculator::new();
+        a.evaluate("inf").unwrap();
+        a.evaluate("5e-324").unwrap();
+        a.evaluate("radius = 2").unwrap();
+        a.evaluate("-inf").unwrap();
+        a.set_variable("$health", 1e-300).unwrap();
+        a.remove_variable("$0");
+
+        let mut b = Calculator::new();
+        b.evaluate("99").unwrap();
+        b.import_state(a.export_state()).unwrap();
+        assert!(b.history().is_empty());
+        assert_eq!(b.export_variables(), a.export_variables());
+        assert_eq!(b.state_fingerprint(), a.state_fingerprint());
+        assert_eq!(b.evaluate("radius").unwrap(), ("$4".to_string(), 2.0));
+        assert_eq!(b.history().len(), 1);
+
+        // A counter past the end of the results would number a result that is never stored
+        let mut state = Calculator::new().export_state();
+        state.next_result = 5;
+        let before = b.export_variables();
+        let err = b.import_state(state).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Invalid state: the next result is $5, but there are only 0 result(s)"
+        );
+        assert_eq!(b.export_variables(), before);
+        assert_eq!(b.history().len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_state_serde_round_trip() {
+        let mut a = Calculator::new();
+        a.evaluate("5e-324").unwrap();
+        a.evaluate("inf").unwrap();
+        a.evaluate("radius = 2.5").unwrap();
+        a.evaluate("-inf * 1").unwrap();
+        a.set_variable("$tiny", f64::MIN_POSITIVE).unwrap();
+        a.remove_variable("$2");
+
+        let json = serde_json::to_string(&a.export_state()).unwrap();
+        let state: CalcState = serde_json::from_str(&json).unwrap();
+        assert_eq!(state, a.export_state());
+
+        let mut b = Calculator::new();
+        b.import_state(state).unwrap();
+        assert_eq!(b.export_variables(), a.export_variables());
+        assert_eq!(b.get_variable("$0"), Some(5e-324));
+        assert_eq!(b.get_variable("$1"), Some(f64::INFINITY));
+        assert_eq!(b.get_variable("$2"), None);
+        assert_eq!(b.get_variable("$ans"), Some(f64::NEG_INFINITY));
+        assert_eq!(b.evaluate("radius * 2").unwrap(), ("$4".to_string(), 5.0));
+
+        let state: CalcState =
+            serde_json::from_str(r#"{"variables":{"pi":1.0},"results":[],"next_result":0}"#)
+                .unwrap();
+        assert!(b.import_state(state).is_err());
+        assert_eq!(b.get_variable("$4"), Some(5.0));
+    }
+
+    #[test]
+    fn test_d_exponent() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("1.5d-3").is_err());
+
+        calculator.set_d_exponent(true);
+        assert_eq!(calculator.quick_evaluate("1.5d-3 * 2").unwrap(), 3e-3);
+        assert_eq!(calculator.quick_evaluate("2.5D-7").unwrap(), 2.5e-7);
+        assert_eq!(calculator.quick_evaluate("deg(pi)").unwrap(), 180.0);
+        assert_eq!(
+            calculator.quick_evaluate("1d").unwrap_err().message(),
+            "Exponent has no digits"
+        );
+    }
+
+    #[test]
+    fn test_hex_literals() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("0xFF + 1").unwrap(), 256.0);
+        assert_eq!(calculator.evaluate("0x10 * 1.5 - 0").unwrap().1, 24.0);
+        assert!(calculator.warnings().is_empty());
+
+        assert_eq!(
+            calculator.evaluate("0x20000000000001").unwrap().1,
+            9007199254740992.0
+        );
+        assert_eq!(calculator.warnings().len(), 1);
+        assert!(calculator.quick_evaluate("0x").is_err());
+    }
+
+    #[test]
+    fn test_binary_literals() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("0b1010 * 2").unwrap(), 20.0);
+        assert_eq!(calculator.quick_evaluate("0b11 + 0x11 + 11").unwrap(), 31.0);
+        assert!(calculator.quick_evaluate("0b2").is_err());
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let calculator = Calculator::new();
+        assert_eq!(
+            calculator.quick_evaluate("3 × π ÷ 2").unwrap(),
+            3.0 * std::f64::consts::PI / 2.0
+        );
+        assert_eq!(calculator.quick_evaluate("√(16) − 1").unwrap(), 3.0);
+        assert_eq!(calculator.quick_evaluate("−2 × −3").unwrap(), 6.0);
+        assert_eq!(calculator.quick_evaluate("2 × 3 − 4 ÷ 2").unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_degree_literals() {
+        let calculator = Calculator::new();
+        let cases = [
+            ("sin(90°)", 1.0),
+            ("sin(90 deg)", 1.0),
+            ("cos((30 + 30)°)", 0.5),
+            ("tan(45°) + deg(pi)", 181.0),
+            ("-180°", -std::f64::consts::PI),
+        ];
+        for (input, expected) in cases {
+            let actual = calculator.quick_evaluate(input).unwrap();
+            assert!(approx::is_close(actual, expected, 1e-12), "{}", input);
+        }
+        let err = calculator.quick_evaluate("° + 1").unwrap_err();
+        assert!(err.is_parse_error());
+        assert_eq!(err.position(), Some(0));
+        assert!(calculator.quick_evaluate("1 + °").is_err());
+    }
+
+    #[test]
+    fn test_vulgar_fractions() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate("1½ + ¼").unwrap().1, 1.75);
+        assert_eq!(calculator.quick_evaluate("2 * ¾").unwrap(), 1.5);
+        assert_eq!(calculator.quick_evaluate("-1½").unwrap(), -1.5);
+        assert_eq!(calculator.quick_evaluate("sqrt(2¼)").unwrap(), 1.5);
+        assert_eq!(calculator.quick_evaluate("max(⅛, ⅒)").unwrap(), 0.125);
+        assert!(calculator.warnings().is_empty());
+        let err = calculator.quick_evaluate("⅟2").unwrap_err();
+        assert!(err.is_scan_error());
+        assert_eq!(err.message(), "Unsupported fraction character");
+        for input in ["2.5½", "1e3½"] {
+            let err = calculator.quick_evaluate(input).unwrap_err();
+            assert!(err.is_scan_error(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_machine_info() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("float_digits").unwrap(), 15.0);
+        assert_eq!(calculator.quick_evaluate("float_maxexp").unwrap(), 1024.0);
+        assert_eq!(calculator.quick_evaluate("ulp(1)").unwrap(), f64::EPSILON);
+        assert_eq!(calculator.quick_evaluate("ulp(0)").unwrap(), 5e-324);
+        assert_eq!(
+            calculator.quick_evaluate("ulp(-2)").unwrap(),
+            2.0 * f64::EPSILON
+        );
+        assert_eq!(
+            calculator.quick_evaluate("ulp(inf)").unwrap(),
+            f64::INFINITY
+        );
+        assert!(calculator.quick_evaluate("ulp").is_err());
+        assert!(calculator.quick_evaluate("float_digits(1)").is_err());
+    }
+
+    #[test]
+    fn test_percent_suffix() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("15%").is_err());
+        assert_eq!(calculator.quick_evaluate("10 % -3").unwrap(), 1.0);
+
+        calculator.set_percent_suffix(true);
+        let cases = [
+            ("200 * 15%", 30.0),
+            ("50% + 1", 1.5),
+            ("(10 + 5)%", 0.15),
+            ("10 % 3", 1.0),
+            ("10% * 3", 0.3),
+            ("10 % (-3)", 1.0),
+            ("10 % -3", -2.9),
+            ("max(50%, 20%)", 0.5),
+            ("-50%", -0.5),
+            ("10 % pi", 10.0 % std::f64::consts::PI),
+        ];
+        for (input, expected) in cases {
+            let actual = calculator.quick_evaluate(input).unwrap();
+            assert!(approx::is_close(actual, expected, 1e-12), "{}", input);
+        }
+        assert!(calculator.quick_evaluate("% 5").is_err());
+    }
+
+    #[test]
+    fn test_leading_decimal_point() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate(".5 + .25").unwrap(), 0.75);
+        assert_eq!(calculator.quick_evaluate("1+.5").unwrap(), 1.5);
+        assert_eq!(calculator.quick_evaluate("-.5").unwrap(), -0.5);
+        assert!(calculator.quick_evaluate("..5").is_err());
+        assert!(calculator.quick_evaluate(".").is_err());
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("1_000_000 * 3").unwrap(), 3e6);
+        assert_eq!(calculator.evaluate("1.234_567").unwrap().1, 1.234567);
+        assert!(calculator.quick_evaluate("1_ + 2").is_err());
+        assert!(calculator.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_octal_literals() {
+        let calculator = Calculator::new();
+        assert_eq!(calculator.quick_evaluate("0o755 - 400").unwrap(), 93.0);
+        assert_eq!(
+            calculator.quick_evaluate("0o10 * 0x10 + 0b10").unwrap(),
+            130.0
+        );
+        assert!(calculator
+            .quick_evaluate("0o8")
+            .unwrap_err()
+            .is_scan_error());
+        assert!(calculator
+            .quick_evaluate("0o + 1")
+            .unwrap_err()
+            .is_scan_error());
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let mut calculator = Calculator::new();
+        calculator.quick_evaluate("sqrt(4)").unwrap();
+        assert!(calculator.usage_stats().is_empty());
+
+        calculator.set_usage_stats(true);
+        calculator.evaluate("sqrt(4) + sqrt(9) * pi").unwrap();
+        calculator.quick_evaluate("max(1, |-2|) ^ 2").unwrap();
+        calculator
+            .evaluate_all(&["logsumexp(1, 2, 3)", "mod(5, 3) + 5 % 3"])
+            .unwrap();
+        calculator.quick_evaluate("0 and sqrt(4)").unwrap();
+        calculator.quick_evaluate("1 or ln(2)").unwrap();
+
+        let expected = HashMap::from([
+            (Word::Sqrt, 2),
+            (Word::Max, 1),
+            (Word::Abs, 1),
+            (Word::Logsumexp, 1),
+            (Word::Mod, 1),
+        ]);
+        assert_eq!(calculator.usage_stats(), expected);
+
+        calculator.reset_usage_stats();
+        assert!(calculator.usage_stats().is_empty());
+        calculator.set_usage_stats(false);
+        calculator.quick_evaluate("sqrt(4)").unwrap();
+        assert!(calculator.usage_stats().is_empty());
+    }
+
+    #[test]
+    fn test_keyword_without_call() {
+        let mut calculator = Calculator::new();
+        for implicit_multiplication in [false, true] {
+            calculator.set_implicit_multiplication(implicit_multiplication);
+            let err = calculator.quick_evaluate("sqrt + 1").unwrap_err();
+            assert!(err.is_parse_error());
+            assert_eq!(
+                err.message(),
+                "'sqrt' is a function and must be called like sqrt(...)"
+            );
+            assert_eq!(err.caret().unwrap(), "^^^^");
+
+            let err = calculator.quick_evaluate("2 * max").unwrap_err();
+            assert_eq!(
+                err.message(),
+                "'max' is a function and must be called like max(...)"
+            );
+            assert_eq!(err.position(), Some(4));
+        }
+
+        calculator.set_implicit_multiplication(false);
+        let err = calculator.quick_evaluate("1 + pi(3)").unwrap_err();
+        assert!(err.is_parse_error());
+        assert_eq!(err.message(), "'pi' is a constant, not a function");
+        assert_eq!(err.caret().unwrap(), "    ^^");
+
+        calculator.set_implicit_multiplication(true);
+        assert_eq!(
+            calculator.quick_evaluate("1 + pi(3)").unwrap(),
+            1.0 + std::f64::consts::PI * 3.0
+        );
+    }
+
+    #[test]
+    fn test_state_fingerprint() {
+        let build = || {
+            let mut calculator = Calculator::new();
+            calculator.evaluate("radius = 2").unwrap();
+            calculator.evaluate("pi * radius ^ 2").unwrap();
+            calculator
+                .register_table("cal", &[(0.0, 1.0), (10.0, 2.0)], false)
+                .unwrap();
+            calculator
+                .register_function("double", 1, |args| Ok(args[0] * 2.0))
+                .unwrap();
+            calculator
+        };
+        let calculator = build();
+        assert_eq!(calculator.state_fingerprint(), build().state_fingerprint());
+        assert_ne!(
+            calculator.state_fingerprint(),
+            Calculator::new().state_fingerprint()
+        );
+        // The fingerprint must not change between runs or versions
+        assert_eq!(Calculator::new().state_fingerprint(), 0x2920_8398_444f_3e80);
+
+        let changes: [fn(&mut Calculator); 7] = [
+            |c| c.set_variable("radius", 3.0).unwrap(),
+            |c| c.set_variable("radius", -0.0).unwrap(),
+            |c| c.set_variable("other", 0.0).unwrap(),
+            |c| assert!(c.remove_variable("$0").is_some()),
+            |c| c.set_implicit_multiplication(true),
+            |c| c.set_angle_mode(AngleMode::Degrees),
+            |c| {
+                c.register_function("half", 1, |args| Ok(args[0] / 2.0))
+                    .unwrap()
+            },
+        ];
+        for change in changes {
+            let mut changed = build();
+            change(&mut changed);
+            assert_ne!(changed.state_fingerprint(), calculator.state_fingerprint());
+        }
+
+        let mut limited = build();
+        limited.set_max_nodes(10);
+        assert_eq!(limited.state_fingerprint(), calculator.state_fingerprint());
+    }
+
+    #[test]
+    fn test_evaluate_with_cancel() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_function("slow", 1, |args| {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                Ok(args[0])
+            })
+            .unwrap();
+        let terms: Vec<_> = (0..5000).map(|i| format!("slow({})", i)).collect();
+        let input = format!("logsumexp({})", terms.join(", "));
+
+        let token = CancellationToken::new();
+        let remote = token.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            remote.cancel();
+        });
+        let start = std::time::Instant::now();
+        let err = calculator.evaluate_with_cancel(&input, &token).unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        // Uncancelled, the evaluation would take at least 5 seconds
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(calculator.get_variable("$ans"), None);
+
+        let err = calculator
+            .evaluate_with_cancel("1 + 2", &token)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        // Later evaluations do not use the token
+        assert_eq!(calculator.evaluate("1 + 2").unwrap().1, 3.0);
+    }
+
+    #[test]
+    fn test_evaluate_with_cancel_untriggered() {
+        let input = format!("logsumexp({})", vec!["sqrt(2) * 3"; 5000].join(", "));
+        let mut calculator = Calculator::new();
+        let token = CancellationToken::new();
+
+        let start = std::time::Instant::now();
+        let expected = calculator.evaluate(&input).unwrap();
+        let without_token = start.elapsed();
+        let start = std::time::Instant::now();
+        let (name, value) = calculator.evaluate_with_cancel(&input, &token).unwrap();
+        let with_token = start.elapsed();
+
+        assert_eq!(name, "$1");
+        assert_eq!(value, expected.1);
+        // A loose bound, since timing varies between runs
+        assert!(with_token < without_token * 10 + std::time::Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("$rate", 0.07).unwrap();
+        calculator.evaluate("radius = 1 / 3").unwrap();
+        calculator.evaluate("-2").unwrap();
+        let cases = [
+            ("100 * (1 + $rate)", "100 * (1 + 0.07)"),
+            (
+                "pi * radius^2",
+                "3.141592653589793 * 0.3333333333333333 ^ 2",
+            ),
+            ("$ans ^ 2", "(-2) ^ 2"),
+            ("2 ^ $ans", "2 ^ -2"),
+            ("x = $rate * unknown", "x = 0.07 * unknown"),
+            ("max($1, |$rate - 1|)", "max(-2, abs(0.07 - 1))"),
+            ("10 % $ans", "10 % (-2)"),
+            ("10 % -$rate", "10 % (-0.07)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(calculator.substitute(input).unwrap(), expected, "{}", input);
+        }
+
+        for input in [
+            "100 * (1 + $rate)",
+            "pi * radius^2",
+            "$ans ^ 2",
+            "-$rate ^ $ans",
+        ] {
+            let substituted = calculator.substitute(input).unwrap();
+            assert_eq!(
+                calculator.quick_evaluate(&substituted).unwrap(),
+                calculator.quick_evaluate(input).unwrap(),
+                "{}",
+                input
+            );
+        }
+        assert!(calculator.substitute("1 +").is_err());
+
+        // A negative right operand of `%` must not be read as a percent suffix
+        calculator.set_percent_suffix(true);
+        calculator.set_variable("$x", -3.0).unwrap();
+        for input in ["10 % $x", "10 % (-3)", "10 % -$x", "50% * 10 % $x"] {
+            let substituted = calculator.substitute(input).unwrap();
+            assert_eq!(
+                calculator.quick_evaluate(&substituted).unwrap(),
+                calculator.quick_evaluate(input).unwrap(),
+                "{} -> {}",
+                input,
+                substituted
+            );
+        }
+        assert_eq!(calculator.substitute("10 % $x").unwrap(), "10 % (-3)");
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.quick_evaluate("SQRT(9)").is_err());
+
+        calculator.set_case_insensitive_keywords(true);
+        let cases = [
+            ("SQRT(9)", 3.0),
+            ("Sqrt(16) + ABS(-1)", 5.0),
+            ("LOG10(1000)", 3.0),
+            ("Max(1, 2) AND TRUE", 1.0),
+            ("COS(PI)", -1.0),
+            ("Euler", std::f64::consts::E),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                calculator.quick_evaluate(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        let err = calculator.quick_evaluate("SQRTX(9)").unwrap_err();
+        assert_eq!(err.message(), "Unknown function: SQRTX");
+        assert!(calculator.evaluate("PI = 3").is_err());
     }
 }
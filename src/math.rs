@@ -0,0 +1,535 @@
+//! Module for the numeric functions behind the keyword functions, such as `gamma`, `round`, and `ncr`.
+//!
+//! [`Word::apply`](crate::scanner::Word::apply) checks the number of arguments before calling into this module.
+
+use crate::calc_error::CalcError;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Computes the sine and cosine of an angle in degrees.
+///
+/// The angle is reduced to within 45 degrees of a multiple of 90 degrees before converting to radians,
+/// which is exact in floating point. Angles of 30 and 45 degrees are looked up,
+/// so special angles like `sind(30)`, `cosd(60)`, and `sind(180)` give exact results.
+pub(crate) fn sin_cos_degrees(x: f64) -> (f64, f64) {
+    let x = x % 360.0;
+    let quadrant = (x / 90.0).round();
+    let r = x - quadrant * 90.0;
+    let (sin, cos) = match r.abs() {
+        30.0 => (0.5f64.copysign(r), 0.75f64.sqrt()),
+        45.0 => (FRAC_1_SQRT_2.copysign(r), FRAC_1_SQRT_2),
+        _ => r.to_radians().sin_cos(),
+    };
+    // Subtracting from zero avoids negative zero, so `sind(180)` is `0` rather than `-0`
+    match (quadrant as i64).rem_euclid(4) {
+        0 => (sin, cos),
+        1 => (cos, 0.0 - sin),
+        2 => (0.0 - sin, 0.0 - cos),
+        _ => (0.0 - cos, sin),
+    }
+}
+
+/// Computes the tangent of an angle in degrees, which is exact for special angles like `tand(45)`.
+pub(crate) fn tan_degrees(x: f64) -> f64 {
+    let (sin, cos) = sin_cos_degrees(x);
+    // Adding zero turns the negative zero of `tand(180)` into zero
+    sin / cos + 0.0
+}
+
+/// Rounds an angle in degrees to a whole number of degrees if it is within a few units in the last place.
+///
+/// Converting from radians loses a little precision, so `asin(0.5)` in degrees is `30.000000000000004`.
+/// The error is smaller than the error of the inverse function itself, so this only removes noise.
+pub(crate) fn snap_degrees(x: f64) -> f64 {
+    let rounded = x.round();
+    if (x - rounded).abs() <= 4.0 * ulp(rounded) {
+        rounded
+    } else {
+        x
+    }
+}
+
+/// Computes the unit in the last place of `x`, the spacing between `|x|` and the next larger f64.
+///
+/// For the largest finite f64, which has no larger neighbor, the spacing to the next smaller f64 is used.
+/// Infinities give infinity, and NaN gives NaN.
+pub(crate) fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+    if !x.is_finite() {
+        x
+    } else if x == f64::MAX {
+        x - x.next_down()
+    } else {
+        x.next_up() - x
+    }
+}
+
+/// Computes `ln(exp(x1) + exp(x2) + ...)` without overflow.
+///
+/// The largest value is factored out of the sum so that every exponent is at most zero.
+/// If the largest value is infinite or NaN, it is returned directly.
+pub(crate) fn log_sum_exp(args: &[f64]) -> f64 {
+    let max = args.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if args.iter().any(|x| x.is_nan()) {
+        return f64::NAN;
+    }
+    if max.is_infinite() {
+        return max;
+    }
+    let sum: f64 = args.iter().map(|x| (x - max).exp()).sum();
+    max + sum.ln()
+}
+
+/// Computes the arithmetic mean of one or more values.
+///
+/// The values are added with Neumaier's compensated summation,
+/// so the result does not drift as more values are averaged.
+/// If the sum overflows, each value is divided by the count before adding instead.
+pub(crate) fn mean(args: &[f64]) -> f64 {
+    let count = args.len() as f64;
+    let sum = compensated_sum(args.iter().copied());
+    if sum.is_finite() || args.iter().any(|x| !x.is_finite()) {
+        sum / count
+    } else {
+        compensated_sum(args.iter().map(|x| x / count))
+    }
+}
+
+/// Adds values with Neumaier's compensated summation.
+///
+/// The rounding error of each addition is accumulated separately and added back at the end.
+/// Once the sum is not finite, there is no rounding error left to compensate,
+/// and the remaining values are added as usual.
+pub(crate) fn compensated_sum(mut values: impl Iterator<Item = f64>) -> f64 {
+    let mut sum: f64 = 0.0;
+    let mut compensation = 0.0;
+    for x in values.by_ref() {
+        let t = sum + x;
+        if !t.is_finite() {
+            return values.fold(t, |acc, x| acc + x);
+        }
+        if sum.abs() >= x.abs() {
+            compensation += (sum - t) + x;
+        } else {
+            compensation += (x - t) + sum;
+        }
+        sum = t;
+    }
+    sum + compensation
+}
+
+/// The largest integer such that it and every smaller integer are exactly representable as an f64, `2^53`.
+pub(crate) const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Rounds `x` half away from zero to a number of decimal places.
+///
+/// The rounding is done on the shortest decimal representation of `x`, the same digits it is displayed with,
+/// so `round(2.675, 2)` is `2.68` and `round(1.005, 2)` is `1.01`,
+/// even though both are stored as slightly less than the decimal written.
+/// A negative number of digits rounds to a power of ten, so `round(1234, -2)` is `1200`.
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if `digits` is not an integer.
+pub(crate) fn round_digits(x: f64, digits: f64) -> Result<f64, CalcError> {
+    if digits.fract() != 0.0 || !digits.is_finite() {
+        return Err(CalcError::new(
+            "round(x, digits) requires an integer number of digits",
+            None,
+        ));
+    }
+    if !x.is_finite() {
+        return Ok(x);
+    }
+    // Scientific notation gives the shortest digits that round-trip, such as `2.675e0`
+    let text = format!("{:e}", x.abs());
+    let (mantissa, exponent) = text
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let mantissa: Vec<u8> = mantissa.bytes().filter(u8::is_ascii_digit).collect();
+    let exponent: i64 = exponent.parse().expect("the exponent is an integer");
+    // The number of digits to keep, counting from the first significant digit
+    let keep = exponent + 1 + digits.clamp(-1000.0, 1000.0) as i64;
+    if keep >= mantissa.len() as i64 {
+        return Ok(x);
+    }
+    if keep < 0 {
+        return Ok(0.0f64.copysign(x));
+    }
+    let keep = keep as usize;
+    let mut kept = mantissa[..keep].to_vec();
+    if mantissa[keep] >= b'5' {
+        // Carry the increment through any trailing nines
+        match kept.iter().rposition(|&d| d != b'9') {
+            Some(i) => {
+                kept[i] += 1;
+                kept[i + 1..].fill(b'0');
+            }
+            None => {
+                kept.fill(b'0');
+                kept.insert(0, b'1');
+            }
+        }
+    }
+    if kept.is_empty() {
+        return Ok(0.0f64.copysign(x));
+    }
+    let kept = String::from_utf8(kept).expect("digits are ASCII");
+    let rounded: f64 = format!("{}e{}", kept, exponent + 1 - keep as i64)
+        .parse()
+        .expect("digits with an exponent form a valid number");
+    Ok(rounded.copysign(x))
+}
+
+/// Computes the real `n`th root of `x`.
+///
+/// A negative `x` has a real root only for an odd integer `n`, which has the opposite sign of the root of `-x`.
+/// Whole-number roots are exact, so `nth_root(27, 3)` is `3` rather than `3.0000000000000004`.
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if `n` is zero, or if `x` is negative and `n` is not an odd integer.
+pub(crate) fn nth_root(x: f64, n: f64) -> Result<f64, CalcError> {
+    if n == 0.0 {
+        return Err(CalcError::new("nthroot(x, n) requires n != 0", None));
+    }
+    let is_integer = n.fract() == 0.0;
+    if x < 0.0 {
+        // Every f64 above 2^53 is even
+        if !is_integer || n % 2.0 == 0.0 {
+            return Err(CalcError::new(
+                "nthroot(x, n) requires x >= 0 unless n is an odd integer",
+                None,
+            ));
+        }
+        return Ok(-nth_root(-x, n)?);
+    }
+    let root = x.powf(n.recip());
+    let rounded = root.round();
+    if rounded != 0.0 && rounded.powf(n) == x {
+        Ok(rounded)
+    } else {
+        Ok(root)
+    }
+}
+
+/// Maps a sample in `[0, 1)` to the range of a call to `rand`.
+///
+/// With no arguments, the range is `[0, 1)`; with two arguments `a` and `b`, it is `[a, b)`.
+/// If `a` and `b` are equal, the result is `a`.
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if the bounds are not finite or `a > b`.
+pub(crate) fn uniform(sample: f64, args: &[f64]) -> Result<f64, CalcError> {
+    let (a, b) = match args {
+        [a, b] => (*a, *b),
+        _ => return Ok(sample),
+    };
+    if !(a.is_finite() && b.is_finite() && a <= b) {
+        return Err(CalcError::new(
+            "rand(a, b) requires finite bounds with a <= b",
+            None,
+        ));
+    }
+    let mut result = a + (b - a) * sample;
+    if !result.is_finite() {
+        // The width of the range overflows, as in rand(-1e308, 1e308)
+        result = a * (1.0 - sample) + b * sample;
+    }
+    // Rounding can reach the excluded upper bound
+    if result >= b && a < b {
+        result = b.next_down();
+    }
+    Ok(result)
+}
+
+/// Computes the greatest common divisor of two integers with Euclid's algorithm.
+pub(crate) fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Computes the number of combinations of `k` of `n` items, where `k <= n`.
+///
+/// The multiplicative formula divides at every step, so intermediate values stay close to the result.
+/// Results are exact while they fit in a 128-bit integer, and are `inf` if larger than the largest finite f64.
+pub(crate) fn combinations(n: u64, k: u64) -> f64 {
+    let k = k.min(n - k);
+    let mut exact = Some(1u128);
+    let mut approx = 1.0;
+    for i in 1..=k {
+        let factor = n - k + i;
+        // Each step is C(n - k + i, i), so the division is exact
+        exact = exact
+            .and_then(|c| c.checked_mul(u128::from(factor)))
+            .map(|c| c / u128::from(i));
+        approx *= factor as f64 / i as f64;
+        if approx.is_infinite() {
+            return f64::INFINITY;
+        }
+    }
+    exact.map_or(approx, |c| c as f64)
+}
+
+/// Computes the number of permutations of `k` of `n` items, where `k <= n`.
+///
+/// Results are exact while they fit in a 128-bit integer, and are `inf` if larger than the largest finite f64.
+pub(crate) fn permutations(n: u64, k: u64) -> f64 {
+    let mut exact = Some(1u128);
+    let mut approx = 1.0;
+    for factor in n - k + 1..=n {
+        exact = exact.and_then(|p| p.checked_mul(u128::from(factor)));
+        approx *= factor as f64;
+        if approx.is_infinite() {
+            return f64::INFINITY;
+        }
+    }
+    exact.map_or(approx, |p| p as f64)
+}
+
+/// Computes the factorial of a non-negative integer.
+///
+/// Results larger than the largest finite f64, from `171!` onwards, are infinite.
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if `n` is negative, not an integer, or NaN.
+pub(crate) fn factorial(n: f64) -> Result<f64, CalcError> {
+    if !(n >= 0.0 && n.fract() == 0.0) {
+        return Err(CalcError::new(
+            "Factorial requires a non-negative integer",
+            None,
+        ));
+    }
+    if n > 170.0 {
+        return Ok(f64::INFINITY);
+    }
+    Ok((1..=n as u32).map(f64::from).product())
+}
+
+/// The parameter `g` of the Lanczos approximation used by [`gamma`] and [`ln_gamma`].
+const LANCZOS_G: f64 = 7.0;
+
+/// The coefficients of the Lanczos approximation for `g = 7`, accurate to about 15 significant digits.
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Computes the Lanczos sum and the shifted argument `t` for `x >= 0.5`.
+///
+/// Then `gamma(x)` is `sqrt(2 pi) * t^(x - 0.5) * e^(-t) * sum`.
+fn lanczos(x: f64) -> (f64, f64) {
+    let x = x - 1.0;
+    let sum = LANCZOS_COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS_COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+    (sum, x + LANCZOS_G + 0.5)
+}
+
+/// Computes the gamma function with the Lanczos approximation.
+///
+/// Positive integers up to `171` give exact factorials. Arguments below `0.5` use the reflection formula.
+/// At the poles, zero and the negative integers, the result is infinite.
+pub(crate) fn gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::INFINITY;
+    }
+    if x == f64::INFINITY {
+        return f64::INFINITY;
+    }
+    if x > 0.0 && x.fract() == 0.0 {
+        return factorial(x - 1.0).unwrap_or(f64::INFINITY);
+    }
+    if x < 0.5 {
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+    let (sum, t) = lanczos(x);
+    // Split the power so that it does not overflow before the result does
+    let half_power = t.powf((x - 0.5) / 2.0);
+    (2.0 * PI).sqrt() * half_power * (half_power * (-t).exp()) * sum
+}
+
+/// Computes the natural logarithm of the absolute value of the gamma function.
+///
+/// Unlike [`gamma`], this does not overflow for large arguments.
+/// Positive integers up to `171` use the logarithm of the exact factorial.
+/// At the poles, zero and the negative integers, the result is infinite.
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    if x.is_infinite() || (x <= 0.0 && x.fract() == 0.0) {
+        return f64::INFINITY;
+    }
+    if x > 0.0 && x <= 171.0 && x.fract() == 0.0 {
+        return gamma(x).ln();
+    }
+    if x < 0.5 {
+        return (PI / (PI * x).sin().abs()).ln() - ln_gamma(1.0 - x);
+    }
+    let (sum, t) = lanczos(x);
+    (x - 0.5) * t.ln() - t + ((2.0 * PI).sqrt() * sum).ln()
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma() {
+        let sqrt_pi = PI.sqrt();
+        let exact = [(1.0, 1.0), (2.0, 1.0), (6.0, 120.0), (11.0, 3_628_800.0)];
+        for (x, expected) in exact {
+            assert_eq!(gamma(x), expected, "gamma({})", x);
+            assert_eq!(ln_gamma(x), f64::ln(expected), "lgamma({})", x);
+        }
+
+        let close = [
+            (0.5, sqrt_pi),
+            (1.5, sqrt_pi / 2.0),
+            (2.5, 0.75 * sqrt_pi),
+            (-0.5, -2.0 * sqrt_pi),
+            (-1.5, 4.0 / 3.0 * sqrt_pi),
+            (0.1, 9.513_507_698_668_732),
+            (3.7, 4.170_651_783_796_603),
+            (30.5, 4.822_696_933_490_909_5e31),
+            (170.5, 5.562_092_414_56e305),
+        ];
+        for (x, expected) in close {
+            let result = gamma(x);
+            assert!(
+                ((result - expected) / expected).abs() < 1e-10,
+                "gamma({}) = {}",
+                x,
+                result
+            );
+            let result = ln_gamma(x);
+            let expected = expected.abs().ln();
+            assert!(
+                (result - expected).abs() < 1e-10 * expected.abs().max(1.0),
+                "lgamma({}) = {}",
+                x,
+                result
+            );
+        }
+
+        assert_eq!(gamma(172.0), f64::INFINITY);
+        assert_eq!(gamma(200.5), f64::INFINITY);
+        let expected = 857.933_669_825_857_5;
+        assert!(((ln_gamma(200.0) - expected) / expected).abs() < 1e-12);
+        assert_eq!(gamma(f64::INFINITY), f64::INFINITY);
+        assert_eq!(ln_gamma(f64::INFINITY), f64::INFINITY);
+
+        for pole in [0.0, -1.0, -2.0, -100.0] {
+            assert_eq!(gamma(pole), f64::INFINITY, "gamma({})", pole);
+            assert_eq!(ln_gamma(pole), f64::INFINITY, "lgamma({})", pole);
+        }
+        assert!(gamma(f64::NAN).is_nan());
+        assert!(ln_gamma(f64::NAN).is_nan());
+        assert!(gamma(f64::NEG_INFINITY).is_nan());
+    }
+
+    #[test]
+    fn test_nth_root() {
+        let cases = [
+            (27.0, 3.0, 3.0),
+            (-8.0, 3.0, -2.0),
+            (-32.0, 5.0, -2.0),
+            (16.0, 2.0, 4.0),
+            (16.0, 4.0, 2.0),
+            (1024.0, 10.0, 2.0),
+            (8.0, 1.5, 4.0),
+            (8.0, -3.0, 0.5),
+            (-8.0, -3.0, -0.5),
+            (0.0, 3.0, 0.0),
+            (1.0, 1e300, 1.0),
+            (f64::INFINITY, 2.0, f64::INFINITY),
+            (-f64::INFINITY, 3.0, -f64::INFINITY),
+        ];
+        for (x, n, expected) in cases {
+            let result = nth_root(x, n).unwrap();
+            assert_eq!(result, expected, "nthroot({}, {})", x, n);
+        }
+
+        let close = [
+            (2.0, 2.0, std::f64::consts::SQRT_2),
+            (10.0, 3.0, 10f64.cbrt()),
+            (2.0, 1e6, 1.000_000_693_147_420_6),
+            (1e300, 1e300, 1.0),
+            (-5.0, 7.0, -(5f64.powf(1.0 / 7.0))),
+        ];
+        for (x, n, expected) in close {
+            let result = nth_root(x, n).unwrap();
+            assert!(
+                (result - expected).abs() < 1e-15,
+                "nthroot({}, {}) = {}",
+                x,
+                n,
+                result
+            );
+        }
+
+        assert_eq!(
+            nth_root(8.0, 0.0).unwrap_err().message(),
+            "nthroot(x, n) requires n != 0"
+        );
+        for (x, n) in [(-16.0, 2.0), (-8.0, 1.5), (-2.0, 1e300)] {
+            assert_eq!(
+                nth_root(x, n).unwrap_err().message(),
+                "nthroot(x, n) requires x >= 0 unless n is an odd integer",
+                "nthroot({}, {})",
+                x,
+                n
+            );
+        }
+        assert!(nth_root(f64::NAN, 3.0).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_ulp() {
+        let smallest_subnormal = f64::from_bits(1);
+        let cases = [
+            (1.0, f64::EPSILON),
+            (-1.0, f64::EPSILON),
+            (0.0, smallest_subnormal),
+            (f64::MIN_POSITIVE, smallest_subnormal),
+            (smallest_subnormal, smallest_subnormal),
+            (f64::MAX, 2f64.powi(971)),
+            (f64::INFINITY, f64::INFINITY),
+        ];
+        for (x, expected) in cases {
+            assert_eq!(ulp(x), expected, "ulp({:e})", x);
+        }
+        assert!(ulp(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn test_uniform() {
+        assert_eq!(uniform(0.25, &[]).unwrap(), 0.25);
+        assert_eq!(uniform(0.0, &[5.0, 10.0]).unwrap(), 5.0);
+        assert_eq!(uniform(0.5, &[5.0, 10.0]).unwrap(), 7.5);
+        assert_eq!(uniform(0.5, &[3.0, 3.0]).unwrap(), 3.0);
+        let below_one = 1.0f64.next_down();
+        assert!(uniform(below_one, &[5.0, 10.0]).unwrap() < 10.0);
+        assert!(uniform(below_one, &[-1e308, 1e308]).unwrap() < 1e308);
+        assert_eq!(uniform(0.5, &[-1e308, 1e308]).unwrap(), 0.0);
+
+        for args in [[10.0, 5.0], [0.0, f64::INFINITY], [f64::NAN, 1.0]] {
+            assert_eq!(
+                uniform(0.5, &args).unwrap_err().message(),
+                "rand(a, b) requires finite bounds with a <= b"
+            );
+        }
+    }
+}
@@ -1,16 +1,21 @@
 //! Module for parsing a vector of tokens into an abstract syntax tree.
 
-use crate::scanner::Token;
-use crate::{calc_error::CalcError, scanner::Word};
+use crate::calc_error::{self, CalcError, ErrorKind};
+use crate::scanner::{Arity, Word};
+use crate::scanner::{Span, Token};
 
 use std::{iter::Peekable, slice::Iter};
 
-const PHI: f64 = 1.618033988749894848204586834365638118_f64;
-
 /// An expression in the form of an abstract syntax tree.
-#[derive(Debug, PartialEq)]
+///
+/// With the `serde` feature, expressions can be serialized, such as to send them to another process.
+/// Infinities and NaN are written as the strings `"inf"`, `"-inf"`, and `"nan"`.
+///
+/// Equality is derived from the numbers, so as with f64, an expression containing NaN is not equal to itself.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
-    Number(f64),
+    Number(#[cfg_attr(feature = "serde", serde(with = "crate::values::number"))] f64),
     Variable(String),
     UnaryOp {
         op: Token,
@@ -21,6 +26,18 @@ pub enum Expr {
         left: Box<Expr>,
         right: Box<Expr>,
     },
+    NaryOp {
+        op: Token,
+        args: Vec<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    Assign {
+        name: String,
+        value: Box<Expr>,
+    },
 }
 
 /// A visitor trait for traversing an abstract syntax tree.
@@ -37,32 +54,234 @@ pub trait Visitor<T> {
     fn visit(&self, expr: &Expr) -> Result<T, CalcError>;
 }
 
+/// How a binary operator groups with other operators of the same precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    /// Groups from the left, so `1 - 2 - 3` is `(1 - 2) - 3`.
+    Left,
+    /// Groups from the right, so `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`.
+    Right,
+}
+
+/// A binary operator and how it binds, as listed in [`BINARY_OPERATORS`].
+#[derive(Debug, PartialEq)]
+pub struct BinaryOperator {
+    /// The token of the operator.
+    pub token: Token,
+    /// The operator as it is written in an expression.
+    pub symbol: &'static str,
+    /// The precedence of the operator, where higher binds tighter.
+    pub precedence: u8,
+    /// How the operator groups with operators of the same precedence.
+    pub associativity: Associativity,
+}
+
+/// The precedence of prefix operators, such as unary minus.
+///
+/// Binary operators with a higher precedence, such as `^`, bind tighter than a unary minus on their left,
+/// so `-2^2` is `-(2^2)`.
+pub const PREFIX_PRECEDENCE: u8 = 5;
+
+/// Every binary operator, from loosest to tightest binding.
+///
+/// This is the table the parser reads, so it can be used to render a precedence chart.
+/// Assignment binds looser than every operator, and postfix operators, such as `!`, bind tighter.
+pub const BINARY_OPERATORS: &[BinaryOperator] = &[
+    BinaryOperator {
+        token: Token::Or,
+        symbol: "or",
+        precedence: 1,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::And,
+        symbol: "and",
+        precedence: 2,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Plus,
+        symbol: "+",
+        precedence: 3,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Minus,
+        symbol: "-",
+        precedence: 3,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Star,
+        symbol: "*",
+        precedence: 4,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Slash,
+        symbol: "/",
+        precedence: 4,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Percent,
+        symbol: "%",
+        precedence: 4,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: Token::Caret,
+        symbol: "^",
+        precedence: 6,
+        associativity: Associativity::Right,
+    },
+];
+
+/// Find the binary operator for a token in [`BINARY_OPERATORS`], if it is one.
+pub fn binary_operator(token: &Token) -> Option<&'static BinaryOperator> {
+    BINARY_OPERATORS
+        .iter()
+        .find(|operator| operator.token == *token)
+}
+
 /// A parser used for generating an abstract syntax tree from a vector of tokens.
 ///
-/// First, create a new Parser with a slice of tokens using [`Parser::new`]. Then call [`Parser::parse`] to generate the AST.
+/// First, create a new Parser with a slice of tokens and their spans using [`Parser::with_spans`]. Then call [`Parser::parse`] to generate the AST.
 pub struct Parser<'a> {
     iter: Peekable<Iter<'a, Token>>,
+    spans: Vec<Span>,
+    position: usize,
+    implicit_multiplication: bool,
+    percent_suffix: bool,
+    max_quote_length: usize,
 }
 
 impl<'a> Parser<'a> {
     /// Create a new Parser with a slice of tokens.
+    ///
+    /// Without spans, errors report the index of the offending token as their position.
+    #[cfg(test)]
     pub fn new(tokens: &'a [Token]) -> Self {
+        let spans = (0..tokens.len())
+            .map(|i| Span {
+                start: i,
+                end: i + 1,
+                byte_start: i,
+                byte_end: i + 1,
+            })
+            .collect();
+        Self::with_spans(tokens, spans)
+    }
+
+    /// Create a new Parser with a slice of tokens and their spans from [`Scanner::scan_with_spans`](crate::scanner::Scanner::scan_with_spans).
+    ///
+    /// Errors report the span of the offending token in the input.
+    pub fn with_spans(tokens: &'a [Token], spans: Vec<Span>) -> Self {
         Parser {
             iter: tokens.iter().peekable(),
+            spans,
+            position: 0,
+            implicit_multiplication: false,
+            percent_suffix: false,
+            max_quote_length: calc_error::DEFAULT_MAX_QUOTE_LENGTH,
+        }
+    }
+
+    /// Set whether adjacent factors are multiplied without a `*`, such as `2pi` or `(1 + 2)(3 + 4)`.
+    ///
+    /// A factor is implicitly multiplied by a following parenthesis, keyword, identifier, or variable.
+    /// Two adjacent numbers, such as `1 2`, are never multiplied.
+    /// Implicit multiplication is disabled by default.
+    pub fn implicit_multiplication(mut self, enabled: bool) -> Self {
+        self.implicit_multiplication = enabled;
+        self
+    }
+
+    /// Set whether a `%` can be a percent suffix, such as `15%` for `0.15`.
+    ///
+    /// A `%` is a percent suffix if the next token cannot start an operand, and modulo otherwise.
+    /// Tokens that start an operand are numbers, variables, identifiers, keywords, `(`, and `|`.
+    /// The percent suffix is disabled by default, so every `%` is modulo.
+    pub fn percent_suffix(mut self, enabled: bool) -> Self {
+        self.percent_suffix = enabled;
+        self
+    }
+
+    /// Set the maximum number of characters of a function name quoted in an error message.
+    ///
+    /// Longer names are truncated and followed by an ellipsis.
+    /// The default is [`DEFAULT_MAX_QUOTE_LENGTH`](calc_error::DEFAULT_MAX_QUOTE_LENGTH).
+    pub fn max_quote_length(mut self, max_quote_length: usize) -> Self {
+        self.max_quote_length = max_quote_length;
+        self
+    }
+
+    /// Returns the span of the token at an index.
+    ///
+    /// If the index is past the last token, an empty span after the last token is returned.
+    fn span_at(&self, index: usize) -> Span {
+        match self.spans.get(index) {
+            Some(span) => *span,
+            None => {
+                let last = self.spans.last().copied().unwrap_or_default();
+                Span {
+                    start: last.end,
+                    byte_start: last.byte_end,
+                    ..last
+                }
+            }
         }
     }
 
+    /// Consume the next token, keeping track of the position.
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.iter.next();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
     /// Parse the tokens into an abstract syntax tree, consuming the Parser.
     ///
     /// This function will call the first part of the recursive descent parser.
     /// If the iterator is not empty after parsing, an error is returned, even if
     /// the preceding tokens were valid.
     pub fn parse(mut self) -> Result<Box<Expr>, CalcError> {
-        let result = self.expr();
+        let result = self.statement()?;
         // Ensure that the iterator is empty after parsing
         match self.iter.peek() {
-            Some(_) => Err(CalcError::new("Unexpected token", None)),
-            None => result,
+            Some(_) => Err(CalcError::new("Unexpected token", None)
+                .with_kind(ErrorKind::Parse)
+                .with_span(self.span_at(self.position))),
+            None => Ok(result),
+        }
+    }
+
+    /// Parse a statement.
+    ///
+    /// A statement is either an assignment or an expression.
+    /// An assignment is an identifier followed by `=` and an expression, such as `x = 1 + 2`.
+    /// Reserved words cannot be assigned to.
+    fn statement(&mut self) -> Result<Box<Expr>, CalcError> {
+        let mut lookahead = self.iter.clone();
+        match (lookahead.next(), lookahead.next()) {
+            (Some(Token::Identifier(name)), Some(Token::Equal)) => {
+                self.advance();
+                self.advance();
+                let value = self.expr()?;
+                Ok(Box::new(Expr::Assign {
+                    name: name.clone(),
+                    value,
+                }))
+            }
+            (Some(Token::Keyword(w)), Some(Token::Equal)) => Err(CalcError::new(
+                &format!("Cannot assign to reserved word: {}", w.name()),
+                None,
+            )
+            .with_kind(ErrorKind::Parse)
+            .with_span(self.span_at(self.position))),
+            _ => self.expr(),
         }
     }
 
@@ -73,7 +292,7 @@ impl<'a> Parser<'a> {
     fn optional(&mut self, token: Token) -> bool {
         match self.iter.peek() {
             Some(t) if *t == &token => {
-                self.iter.next();
+                self.advance();
                 true
             }
             _ => false,
@@ -85,9 +304,12 @@ impl<'a> Parser<'a> {
     /// Calls `next` on the iterator and compares the result to the expected token.
     /// If the token is not the next token, an error is returned.
     fn require(&mut self, token: Token, msg: &str) -> Result<(), CalcError> {
-        match self.iter.next() {
+        let position = self.position;
+        match self.advance() {
             Some(t) if t == &token => Ok(()),
-            _ => Err(CalcError::new(msg, None)),
+            _ => Err(CalcError::new(msg, None)
+                .with_kind(ErrorKind::Parse)
+                .with_span(self.span_at(position))),
         }
     }
 
@@ -95,127 +317,151 @@ impl<'a> Parser<'a> {
     ///
     /// This function will call the first part of the recursive descent parser.
     fn expr(&mut self) -> Result<Box<Expr>, CalcError> {
-        self.term()
+        self.binary(0)
     }
 
-    /// Parse a term binary expression.
+    /// Parse a binary expression whose operators have at least `min_precedence`.
     ///
-    /// Term operations include addition and subtraction.
-    fn term(&mut self) -> Result<Box<Expr>, CalcError> {
-        let expr = self.factor()?;
+    /// This is a precedence-climbing parser driven by [`BINARY_OPERATORS`].
+    /// Operands are unary expressions, so operators that bind tighter than [`PREFIX_PRECEDENCE`],
+    /// such as `^`, are left to [`Parser::power`].
+    /// If implicit multiplication is enabled, adjacent factors are multiplied with the same precedence as `*`,
+    /// so `2^3x` is `(2^3) * x` and `6 / 2x` is `(6 / 2) * x`.
+    fn binary(&mut self, min_precedence: u8) -> Result<Box<Expr>, CalcError> {
+        let mut expr = self.unary()?;
         loop {
-            match self.iter.peek() {
-                Some(Token::Plus) => {
-                    self.iter.next();
-                    let right = self.factor()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Plus,
-                        left: expr,
-                        right,
-                    }));
-                }
-                Some(Token::Minus) => {
-                    self.iter.next();
-                    let right = self.factor()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Minus,
-                        left: expr,
-                        right,
-                    }));
-                }
-                _ => {
-                    return Ok(expr);
+            let next = self.iter.peek().copied();
+            let (operator, consumed) = match next.and_then(binary_operator) {
+                Some(operator) if operator.precedence < PREFIX_PRECEDENCE => (operator, true),
+                None if self.implicit_multiplication
+                    && matches!(
+                        next,
+                        Some(
+                            Token::LParen
+                                | Token::Keyword(_)
+                                | Token::Identifier(_)
+                                | Token::Variable(_)
+                        )
+                    ) =>
+                {
+                    // There is no operator token to consume
+                    (
+                        binary_operator(&Token::Star).expect("* is a binary operator"),
+                        false,
+                    )
                 }
+                _ => return Ok(expr),
+            };
+            if operator.precedence < min_precedence {
+                return Ok(expr);
             }
-        }
-    }
-
-    /// Parse a factor binary expression.
-    ///
-    /// Factor operations include multiplication and division.
-    fn factor(&mut self) -> Result<Box<Expr>, CalcError> {
-        let expr = self.power()?;
-        loop {
-            match self.iter.peek() {
-                Some(Token::Star) => {
-                    self.iter.next();
-                    let right = self.power()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Star,
-                        left: expr,
-                        right,
-                    }));
-                }
-                Some(Token::Slash) => {
-                    self.iter.next();
-                    let right = self.power()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Slash,
-                        left: expr,
-                        right,
-                    }));
-                }
-                Some(Token::Percent) => {
-                    self.iter.next();
-                    let right = self.power()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Percent,
-                        left: expr,
-                        right,
-                    }));
-                }
-                _ => {
-                    return Ok(expr);
-                }
-            }
-        }
-    }
-
-    fn power(&mut self) -> Result<Box<Expr>, CalcError> {
-        let expr = self.unary()?;
-        loop {
-            match self.iter.peek() {
-                Some(Token::Caret) => {
-                    self.iter.next();
-                    let right = self.unary()?;
-                    return Ok(Box::new(Expr::BinaryOp {
-                        op: Token::Caret,
-                        left: expr,
-                        right,
-                    }));
-                }
-                _ => {
-                    return Ok(expr);
-                }
+            if consumed {
+                self.advance();
             }
+            let right = match operator.associativity {
+                Associativity::Left => self.binary(operator.precedence + 1)?,
+                Associativity::Right => self.binary(operator.precedence)?,
+            };
+            expr = Box::new(Expr::BinaryOp {
+                op: operator.token.clone(),
+                left: expr,
+                right,
+            });
         }
     }
 
     /// Parse a unary expression.
     ///
-    /// A unary expression is either a primary expression or a unary operator followed by a primary expression.
+    /// A unary expression is either a power expression or a unary operator followed by a unary expression,
+    /// so unary operators can be stacked, as in `--5`.
+    /// Exponentiation binds tighter than unary minus, so `-2^2` is `-(2^2)`.
+    /// Unary plus has no effect, so `++5` is `5`.
     fn unary(&mut self) -> Result<Box<Expr>, CalcError> {
         match self.iter.peek() {
+            Some(Token::Plus) => {
+                self.advance();
+                self.unary()
+            }
             Some(Token::Minus) => {
-                self.iter.next();
-                let operand = self.primary()?;
+                self.advance();
+                let operand = self.unary()?;
                 Ok(Box::new(Expr::UnaryOp {
                     op: Token::Minus,
                     operand,
                 }))
             }
-            _ => self.primary(),
+            _ => self.power(),
+        }
+    }
+
+    /// Parse a power binary expression.
+    ///
+    /// Power operations are the operators in [`BINARY_OPERATORS`] that bind tighter than [`PREFIX_PRECEDENCE`],
+    /// which is only exponentiation.
+    /// They must be right-associative, so `2^3^2` is `2^(3^2)`.
+    /// The exponent may itself be negated, as in `2^-3`.
+    fn power(&mut self) -> Result<Box<Expr>, CalcError> {
+        let base = self.postfix()?;
+        let operator = match self.iter.peek().copied().and_then(binary_operator) {
+            Some(operator) if operator.precedence > PREFIX_PRECEDENCE => operator,
+            _ => return Ok(base),
+        };
+        self.advance();
+        let exponent = self.unary()?;
+        Ok(Box::new(Expr::BinaryOp {
+            op: operator.token.clone(),
+            left: base,
+            right: exponent,
+        }))
+    }
+
+    /// Parse a postfix expression.
+    ///
+    /// A postfix expression is a primary expression followed by any number of factorial operators
+    /// or degree suffixes.
+    /// A degree suffix is either `°` or `deg` without parentheses, and marks its operand as an angle in degrees.
+    /// Both become a [`Token::Degree`] operation, which the interpreter converts to its angle unit,
+    /// so `sin(45°)` and `sin(45 deg)` are the sine of 45 degrees in either angle mode.
+    /// If the percent suffix is enabled, a `%` that is not followed by an operand divides by 100,
+    /// so `15%` is `0.15`.
+    /// Postfix operators bind tighter than unary minus, so `-3!` is `-(3!)`.
+    fn postfix(&mut self) -> Result<Box<Expr>, CalcError> {
+        let mut expr = self.primary()?;
+        loop {
+            let mut lookahead = self.iter.clone();
+            let op = match (lookahead.next(), lookahead.next()) {
+                (Some(Token::Bang), _) => Token::Bang,
+                (Some(Token::Percent), next) if self.percent_suffix && !starts_operand(next) => {
+                    Token::Percent
+                }
+                (Some(Token::Degree), _) => Token::Degree,
+                (Some(Token::Keyword(Word::Deg)), next) if next != Some(&Token::LParen) => {
+                    Token::Degree
+                }
+                _ => return Ok(expr),
+            };
+            self.advance();
+            expr = Box::new(Expr::UnaryOp { op, operand: expr });
         }
     }
 
     /// Parse a primary expression.
     ///
-    /// A primary expression is either a number, variable, or an expression enclosed in parentheses.
+    /// A primary expression is either a number, variable, call, or an expression enclosed in parentheses.
+    /// An identifier followed by an opening parenthesis is a call; otherwise, it is a variable.
+    /// An expression enclosed in bars, such as `|x|`, is its absolute value.
+    /// A bar where an operand is expected opens a new absolute value, and a bar after a complete
+    /// expression closes the innermost one, so `||x| - 1|` is `abs(abs(x) - 1)`.
     fn primary(&mut self) -> Result<Box<Expr>, CalcError> {
-        match self.iter.next() {
+        let position = self.position;
+        match self.advance() {
             Some(Token::Number(n)) => Ok(Box::new(Expr::Number(*n))),
             Some(Token::Variable(s)) => Ok(Box::new(Expr::Variable(s.clone()))),
             Some(Token::Keyword(w)) => self.call(w),
+            Some(Token::Identifier(name)) => match self.iter.peek() {
+                Some(Token::LParen) => self.named_call(name),
+                _ => Ok(Box::new(Expr::Variable(name.clone()))),
+            },
             Some(Token::LParen) => {
                 let expr = self.expr()?;
                 self.require(Token::RParen, "Expected closing parenthesis")?;
@@ -229,7 +475,12 @@ impl<'a> Parser<'a> {
                     operand: expr,
                 }))
             }
-            _ => Err(CalcError::new("Not a valid expression", None)),
+            Some(Token::Degree) => Err(CalcError::new("Degree sign must follow a value", None)
+                .with_kind(ErrorKind::Parse)
+                .with_span(self.span_at(position))),
+            _ => Err(CalcError::new("Not a valid expression", None)
+                .with_kind(ErrorKind::Parse)
+                .with_span(self.span_at(position))),
         }
     }
 
@@ -238,14 +489,68 @@ impl<'a> Parser<'a> {
     /// Call expressions are used for functions that take arguments.
     /// The function name is the keyword token, and the arguments are enclosed in parentheses.
     /// Trailing commas are allowed and ignored.
+    /// The full list of arguments is parsed before its length is checked against [`Word::arity`],
+    /// so `sqrt(1, 2)` reports the number of arguments rather than a missing parenthesis.
+    /// Functions of one or two arguments become unary or binary operations,
+    /// and `round`, which takes one or two, becomes whichever matches the arguments given.
+    /// Functions of any other arity, such as `clamp`, become n-ary operations.
     /// Call expressions also include constants, which converted to their respective values.
+    ///
+    /// A function keyword must be followed by an opening parenthesis.
+    /// A constant keyword must not be, unless implicit multiplication is enabled,
+    /// in which case `pi(3)` is `pi * 3`.
     fn call(&mut self, w: &Word) -> Result<Box<Expr>, CalcError> {
+        // The keyword has already been consumed, so it is the previous token
+        let keyword_span = self.span_at(self.position - 1);
+        let is_constant = w.arity() == Arity::Exactly(0);
+        let is_called = self.iter.peek() == Some(&&Token::LParen);
+        if is_constant && is_called && !self.implicit_multiplication {
+            return Err(CalcError::new(
+                &format!("'{}' is a constant, not a function", w.name()),
+                None,
+            )
+            .with_kind(ErrorKind::Parse)
+            .with_span(keyword_span));
+        }
+        if !is_constant && !is_called {
+            return Err(CalcError::new(
+                &format!(
+                    "'{0}' is a function and must be called like {0}(...)",
+                    w.name()
+                ),
+                None,
+            )
+            .with_kind(ErrorKind::Parse)
+            .with_span(keyword_span));
+        }
         match w {
-            Word::Inf => Ok(Box::new(Expr::Number(f64::INFINITY))),
-            Word::Pi => Ok(Box::new(Expr::Number(std::f64::consts::PI))),
-            Word::Tau => Ok(Box::new(Expr::Number(std::f64::consts::TAU))),
-            Word::E => Ok(Box::new(Expr::Number(std::f64::consts::E))),
-            Word::Phi => Ok(Box::new(Expr::Number(PHI))),
+            Word::Inf
+            | Word::Nan
+            | Word::Eps
+            | Word::Maxval
+            | Word::Minval
+            | Word::Pi
+            | Word::Tau
+            | Word::E
+            | Word::Phi
+            | Word::Ln2
+            | Word::Ln10
+            | Word::Sqrt2
+            | Word::Invsqrt2
+            | Word::True
+            | Word::False
+            | Word::FloatDigits
+            | Word::FloatMaxexp
+            | Word::SpeedOfLight
+            | Word::Gravitation
+            | Word::Planck
+            | Word::ReducedPlanck
+            | Word::Avogadro
+            | Word::Boltzmann
+            | Word::ElementaryCharge
+            | Word::StandardGravity => Ok(Box::new(Expr::Number(
+                w.constant().expect("constants are listed in CONSTANTS"),
+            ))),
             Word::Sqrt
             | Word::Cbrt
             | Word::Exp
@@ -258,49 +563,178 @@ impl<'a> Parser<'a> {
             | Word::Asin
             | Word::Acos
             | Word::Atan
+            | Word::Sec
+            | Word::Csc
+            | Word::Cot
+            | Word::Asec
+            | Word::Acsc
+            | Word::Acot
+            | Word::Sind
+            | Word::Cosd
+            | Word::Tand
+            | Word::Asind
+            | Word::Acosd
+            | Word::Atand
             | Word::Sinh
             | Word::Cosh
             | Word::Tanh
             | Word::Asinh
             | Word::Acosh
             | Word::Atanh
+            | Word::Sech
+            | Word::Csch
+            | Word::Coth
+            | Word::Asech
+            | Word::Acsch
+            | Word::Acoth
             | Word::Rad
             | Word::Deg
             | Word::Abs
             | Word::Floor
             | Word::Ceil
             | Word::Trunc
-            | Word::Round => {
-                self.require(Token::LParen, "Expected opening parenthesis")?;
-                let expr = self.expr()?;
-                self.optional(Token::Comma);
-                self.require(Token::RParen, "Expected closing parenthesis")?;
+            | Word::Ulp
+            | Word::Gamma
+            | Word::Lgamma
+            | Word::Fact
+            | Word::Sign
+            | Word::Isnan
+            | Word::Isinf
+            | Word::Isfinite => {
+                let mut args = self.checked_arguments(w)?.into_iter().map(Box::new);
                 Ok(Box::new(Expr::UnaryOp {
                     op: Token::Keyword(w.clone()),
-                    operand: expr,
+                    operand: args.next().expect("the arity is checked"),
                 }))
             }
             Word::Pow
             | Word::Log
             | Word::Hypot
             | Word::Atan2
+            | Word::Atan2d
             | Word::Mod
-            | Word::Max
-            | Word::Min => {
-                self.require(Token::LParen, "Expected opening parenthesis")?;
-                let left = self.expr()?;
-                self.require(Token::Comma, "Expected comma")?;
-                let right = self.expr()?;
-                self.optional(Token::Comma);
-                self.require(Token::RParen, "Expected closing parenthesis")?;
+            | Word::Logaddexp
+            | Word::Gcd
+            | Word::Lcm
+            | Word::Ncr
+            | Word::Npr
+            | Word::Copysign
+            | Word::Nthroot => {
+                let mut args = self.checked_arguments(w)?.into_iter().map(Box::new);
                 Ok(Box::new(Expr::BinaryOp {
                     op: Token::Keyword(w.clone()),
-                    left,
-                    right,
+                    left: args.next().expect("the arity is checked"),
+                    right: args.next().expect("the arity is checked"),
                 }))
             }
+            Word::Max | Word::Min => {
+                let op = Token::Keyword(w.clone());
+                let mut args = self.checked_arguments(w)?;
+                if args.len() > 2 {
+                    return Ok(Box::new(Expr::NaryOp { op, args }));
+                }
+                let right = Box::new(args.pop().expect("max and min take at least 2 arguments"));
+                let left = Box::new(args.pop().expect("max and min take at least 2 arguments"));
+                Ok(Box::new(Expr::BinaryOp { op, left, right }))
+            }
+            Word::Round => {
+                let op = Token::Keyword(w.clone());
+                let mut args = self.checked_arguments(w)?.into_iter().map(Box::new);
+                Ok(Box::new(match (args.next(), args.next()) {
+                    (Some(left), Some(right)) => Expr::BinaryOp { op, left, right },
+                    (Some(operand), None) => Expr::UnaryOp { op, operand },
+                    _ => unreachable!("the arity of round is checked"),
+                }))
+            }
+            Word::Clamp | Word::Logsumexp | Word::Avg | Word::Sum | Word::Prod | Word::Rand => {
+                Ok(Box::new(Expr::NaryOp {
+                    op: Token::Keyword(w.clone()),
+                    args: self.checked_arguments(w)?,
+                }))
+            }
+        }
+    }
+
+    /// Parse the arguments of a call to a keyword and check their number against [`Word::arity`].
+    ///
+    /// The keyword must be the previous token, which the error is reported at.
+    fn checked_arguments(&mut self, w: &Word) -> Result<Vec<Expr>, CalcError> {
+        let position = self.position - 1;
+        let args = self.arguments(w.name(), w.arity().min())?;
+        w.check_arity(args.len()).map_err(|e| {
+            e.with_kind(ErrorKind::Parse)
+                .with_span(self.span_at(position))
+        })?;
+        Ok(args)
+    }
+
+    /// Parse a list of arguments enclosed in parentheses.
+    ///
+    /// The arguments are separated by commas.
+    /// Trailing commas are allowed and ignored, but a leading or repeated comma is an error.
+    /// A comma before the first `required` arguments are parsed must be followed by an argument,
+    /// so `pow(2, )` is an error naming the missing argument, while `pow(2)` is left to the arity check.
+    fn arguments(&mut self, function: &str, required: usize) -> Result<Vec<Expr>, CalcError> {
+        self.require(Token::LParen, "Expected opening parenthesis")?;
+        let mut args = Vec::new();
+        while (1..required).contains(&args.len()) || !self.optional(Token::RParen) {
+            args.push(*self.argument(args.len() + 1, function)?);
+            if !self.optional(Token::Comma) {
+                self.require(Token::RParen, "Expected closing parenthesis")?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    /// Parse one argument of a call, where `index` counts from 1.
+    ///
+    /// If a comma or closing parenthesis is found where the argument should start,
+    /// an error naming the missing argument is returned, such as "Missing argument 2 of pow".
+    fn argument(&mut self, index: usize, function: &str) -> Result<Box<Expr>, CalcError> {
+        if let Some(Token::Comma | Token::RParen) = self.iter.peek() {
+            return Err(CalcError::new(
+                &format!(
+                    "Missing argument {} of {}",
+                    index,
+                    calc_error::quote(function, self.max_quote_length)
+                ),
+                None,
+            )
+            .with_kind(ErrorKind::Parse)
+            .with_span(self.span_at(self.position)));
         }
+        self.expr()
     }
+
+    /// Parse a call to a named function.
+    ///
+    /// Named functions are identifiers that are not reserved words, such as registered tables.
+    /// The arguments are enclosed in parentheses and separated by commas.
+    /// Trailing commas are allowed and ignored.
+    /// The function is resolved by the interpreter, not the parser.
+    fn named_call(&mut self, name: &str) -> Result<Box<Expr>, CalcError> {
+        let args = self.arguments(name, 0)?;
+        Ok(Box::new(Expr::Call {
+            name: name.to_string(),
+            args,
+        }))
+    }
+}
+
+/// Check if a token can be the first token of an operand, such as a number or `(`.
+fn starts_operand(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(
+            Token::Number(_)
+                | Token::Variable(_)
+                | Token::Identifier(_)
+                | Token::Keyword(_)
+                | Token::LParen
+                | Token::Bar
+        )
+    )
 }
 
 // MARK: Tests
@@ -308,6 +742,124 @@ impl<'a> Parser<'a> {
 mod tests {
     use super::*;
 
+    /// Parse an input string, with every binary operation wrapped in parentheses to show its shape.
+    fn shape(input: &str) -> String {
+        fn print(expr: &Expr) -> String {
+            match expr {
+                Expr::Number(n) => n.to_string(),
+                Expr::Variable(name) => name.clone(),
+                Expr::UnaryOp { op, operand } => format!("{:?}[{}]", op, print(operand)),
+                Expr::BinaryOp { op, left, right } => {
+                    format!("({} {:?} {})", print(left), op, print(right))
+                }
+                Expr::NaryOp { op, args } => {
+                    let args: Vec<_> = args.iter().map(print).collect();
+                    format!("{:?}[{}]", op, args.join(", "))
+                }
+                Expr::Call { name, args } => {
+                    let args: Vec<_> = args.iter().map(print).collect();
+                    format!("{}[{}]", name, args.join(", "))
+                }
+                Expr::Assign { name, value } => format!("{} = {}", name, print(value)),
+            }
+        }
+        let (tokens, spans) = crate::scanner::Scanner::new(input)
+            .scan_with_spans()
+            .unwrap();
+        let parser = Parser::with_spans(&tokens, spans).implicit_multiplication(true);
+        print(&parser.parse().unwrap())
+    }
+
+    #[test]
+    fn test_precedence_shapes() {
+        let cases = [
+            ("1 + 2 * 3", "(1 Plus (2 Star 3))"),
+            ("1 * 2 + 3", "((1 Star 2) Plus 3)"),
+            ("1 - 2 - 3", "((1 Minus 2) Minus 3)"),
+            ("8 / 4 / 2 % 3", "(((8 Slash 4) Slash 2) Percent 3)"),
+            ("2 ^ 3 ^ 2", "(2 Caret (3 Caret 2))"),
+            ("-2 ^ 2", "Minus[(2 Caret 2)]"),
+            ("2 ^ -3 * 4", "((2 Caret Minus[3]) Star 4)"),
+            ("-3! ^ 2", "Minus[(Bang[3] Caret 2)]"),
+            ("1 or 0 and 1 + 2", "(1 Or (0 And (1 Plus 2)))"),
+            ("1 and 0 or 1", "((1 And 0) Or 1)"),
+            ("x = 1 + 2 or 3", "x = ((1 Plus 2) Or 3)"),
+            ("2x ^ 2 + 1", "((2 Star (x Caret 2)) Plus 1)"),
+            ("6 / 2(1 + 2)", "((6 Slash 2) Star (1 Plus 2))"),
+            ("-(1 - 2) * 3", "(Minus[(1 Minus 2)] Star 3)"),
+            ("f(1 + 2, 3) - 4", "(f[(1 Plus 2), 3] Minus 4)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(shape(input), expected, "{}", input);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let inputs = [
+            "radius = 2 * pi * max(-$r, 1e-300) ^ 2",
+            "logsumexp(1, |x - 3|, cal(4, 5!)) or 0 and 1",
+            "-sqrt(inf) % 3",
+        ];
+        for input in inputs {
+            let (tokens, spans) = crate::scanner::Scanner::new(input)
+                .scan_with_spans()
+                .unwrap();
+            let expr = Parser::with_spans(&tokens, spans).parse().unwrap();
+            let json = serde_json::to_string(&expr).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Expr>(&json).unwrap(),
+                *expr,
+                "{}",
+                json
+            );
+        }
+
+        let expr = Expr::NaryOp {
+            op: Token::Keyword(Word::Logsumexp),
+            args: vec![
+                Expr::Number(f64::INFINITY),
+                Expr::Number(f64::NEG_INFINITY),
+                Expr::Number(f64::NAN),
+                Expr::Number(-0.0),
+            ],
+        };
+        let json = serde_json::to_string(&expr).unwrap();
+        assert_eq!(
+            json,
+            r#"{"NaryOp":{"op":{"Keyword":"logsumexp"},"args":[{"Number":"inf"},{"Number":"-inf"},{"Number":"nan"},{"Number":-0.0}]}}"#
+        );
+        let Expr::NaryOp { args, .. } = serde_json::from_str(&json).unwrap() else {
+            panic!("expected an n-ary operation");
+        };
+        assert_eq!(args[0], Expr::Number(f64::INFINITY));
+        assert_eq!(args[1], Expr::Number(f64::NEG_INFINITY));
+        assert!(matches!(args[2], Expr::Number(n) if n.is_nan()));
+        assert!(matches!(args[3], Expr::Number(n) if n.is_sign_negative()));
+
+        for word in Word::ALL {
+            let json = serde_json::to_string(word).unwrap();
+            assert_eq!(json, format!("\"{}\"", word.name()));
+            assert_eq!(&serde_json::from_str::<Word>(&json).unwrap(), word);
+        }
+    }
+
+    #[test]
+    fn test_binary_operators_table() {
+        for pair in BINARY_OPERATORS.windows(2) {
+            assert!(pair[0].precedence <= pair[1].precedence);
+        }
+        for operator in BINARY_OPERATORS {
+            assert_eq!(binary_operator(&operator.token), Some(operator));
+            // Operators that bind tighter than prefix operators are parsed as right-associative
+            if operator.precedence > PREFIX_PRECEDENCE {
+                assert_eq!(operator.associativity, Associativity::Right);
+            }
+        }
+        assert_eq!(binary_operator(&Token::Bang), None);
+    }
+
     #[test]
     fn test_parse_empty() {
         let input = vec![];
@@ -380,6 +932,28 @@ mod tests {
         assert_eq!(*parser.parse().unwrap(), *expected);
     }
 
+    #[test]
+    fn test_modulo_chained_with_division() {
+        let input = vec![
+            Token::Number(10.0),
+            Token::Percent,
+            Token::Number(4.0),
+            Token::Slash,
+            Token::Number(2.0),
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::BinaryOp {
+            op: Token::Slash,
+            left: Box::new(Expr::BinaryOp {
+                op: Token::Percent,
+                left: Box::new(Expr::Number(10.0)),
+                right: Box::new(Expr::Number(4.0)),
+            }),
+            right: Box::new(Expr::Number(2.0)),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
     #[test]
     fn test_grouping() {
         let input = vec![
@@ -426,6 +1000,67 @@ mod tests {
         assert!(parser.parse().is_err());
     }
 
+    #[test]
+    fn test_missing_closing_paren_position() {
+        let input = vec![
+            Token::LParen,
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Number(2.0),
+        ];
+        let parser = Parser::new(&input);
+        assert_eq!(parser.parse().unwrap_err().position(), Some(4));
+    }
+
+    #[test]
+    fn test_excess_tokens_position() {
+        let input = vec![
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Number(2.0),
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        assert_eq!(parser.parse().unwrap_err().position(), Some(3));
+    }
+
+    #[test]
+    fn test_missing_closing_paren_span() {
+        let input = vec![Token::LParen, Token::Number(1.0), Token::Number(2.0)];
+        let spans = vec![
+            Span {
+                start: 0,
+                end: 1,
+                byte_start: 0,
+                byte_end: 1,
+            },
+            Span {
+                start: 1,
+                end: 4,
+                byte_start: 1,
+                byte_end: 4,
+            },
+            Span {
+                start: 5,
+                end: 6,
+                byte_start: 5,
+                byte_end: 6,
+            },
+        ];
+        let parser = Parser::with_spans(&input, spans);
+        let err = parser.parse().unwrap_err();
+        assert_eq!(
+            err.span(),
+            Some(Span {
+                start: 5,
+                end: 6,
+                byte_start: 5,
+                byte_end: 6
+            })
+        );
+        assert_eq!(err.position(), Some(5));
+    }
+
     #[test]
     fn test_excess_tokens() {
         let input = vec![Token::Number(1.0), Token::Number(2.0)];
@@ -513,6 +1148,31 @@ mod tests {
         assert_eq!(*parser.parse().unwrap(), *expected);
     }
 
+    #[test]
+    fn test_nan() {
+        let input = vec![Token::Keyword(Word::Nan)];
+        let parser = Parser::new(&input);
+        let expr = parser.parse().unwrap();
+        // NaN is not equal to itself, so the expression cannot be compared with assert_eq
+        assert!(matches!(*expr, Expr::Number(n) if n.is_nan()));
+        assert_ne!(*expr, Expr::Number(f64::NAN));
+
+        let input = vec![
+            Token::Keyword(Word::Isnan),
+            Token::LParen,
+            Token::Keyword(Word::Nan),
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        match *parser.parse().unwrap() {
+            Expr::UnaryOp { op, operand } => {
+                assert_eq!(op, Token::Keyword(Word::Isnan));
+                assert!(matches!(*operand, Expr::Number(n) if n.is_nan()));
+            }
+            expr => panic!("Expected a unary operation, got {:?}", expr),
+        }
+    }
+
     #[test]
     fn test_pi() {
         let input = vec![Token::Keyword(Word::Pi)];
@@ -541,4 +1201,307 @@ mod tests {
         });
         assert_eq!(*parser.parse().unwrap(), *expected);
     }
+
+    #[test]
+    fn test_named_call() {
+        let input = vec![
+            Token::Identifier("cal".to_string()),
+            Token::LParen,
+            Token::Number(1.0),
+            Token::Comma,
+            Token::Number(2.0),
+            Token::Comma,
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::Call {
+            name: "cal".to_string(),
+            args: vec![Expr::Number(1.0), Expr::Number(2.0)],
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_named_variable() {
+        let input = vec![Token::Identifier("cal".to_string())];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::Variable("cal".to_string()));
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_assign() {
+        let input = vec![
+            Token::Identifier("x".to_string()),
+            Token::Equal,
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Number(2.0),
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::Assign {
+            name: "x".to_string(),
+            value: Box::new(Expr::BinaryOp {
+                op: Token::Plus,
+                left: Box::new(Expr::Number(1.0)),
+                right: Box::new(Expr::Number(2.0)),
+            }),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_assign_keyword() {
+        let input = vec![Token::Keyword(Word::Pi), Token::Equal, Token::Number(3.0)];
+        let parser = Parser::new(&input);
+        let err = parser.parse().unwrap_err();
+        assert_eq!(err.message(), "Cannot assign to reserved word: pi");
+    }
+
+    #[test]
+    fn test_assign_nested() {
+        let input = vec![
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Identifier("x".to_string()),
+            Token::Equal,
+            Token::Number(2.0),
+        ];
+        let parser = Parser::new(&input);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_variadic() {
+        let input = vec![
+            Token::Keyword(Word::Logsumexp),
+            Token::LParen,
+            Token::Number(1.0),
+            Token::Comma,
+            Token::Number(2.0),
+            Token::Comma,
+            Token::Number(3.0),
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::NaryOp {
+            op: Token::Keyword(Word::Logsumexp),
+            args: vec![Expr::Number(1.0), Expr::Number(2.0), Expr::Number(3.0)],
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_variadic_no_args() {
+        let input = vec![
+            Token::Keyword(Word::Logsumexp),
+            Token::LParen,
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let input = vec![
+            Token::Number(1.0),
+            Token::Or,
+            Token::Number(0.0),
+            Token::And,
+            Token::Number(2.0),
+            Token::Plus,
+            Token::Number(3.0),
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::BinaryOp {
+            op: Token::Or,
+            left: Box::new(Expr::Number(1.0)),
+            right: Box::new(Expr::BinaryOp {
+                op: Token::And,
+                left: Box::new(Expr::Number(0.0)),
+                right: Box::new(Expr::BinaryOp {
+                    op: Token::Plus,
+                    left: Box::new(Expr::Number(2.0)),
+                    right: Box::new(Expr::Number(3.0)),
+                }),
+            }),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_factorial_precedence() {
+        let input = vec![Token::Minus, Token::Number(3.0), Token::Bang, Token::Bang];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::UnaryOp {
+            op: Token::Minus,
+            operand: Box::new(Expr::UnaryOp {
+                op: Token::Bang,
+                operand: Box::new(Expr::UnaryOp {
+                    op: Token::Bang,
+                    operand: Box::new(Expr::Number(3.0)),
+                }),
+            }),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_degree_suffix() {
+        let degrees = |operand| {
+            Box::new(Expr::UnaryOp {
+                op: Token::Degree,
+                operand,
+            })
+        };
+        let input = vec![Token::Number(45.0), Token::Degree];
+        let expected = degrees(Box::new(Expr::Number(45.0)));
+        assert_eq!(Parser::new(&input).parse().unwrap(), expected);
+
+        let input = vec![Token::Number(45.0), Token::Keyword(Word::Deg)];
+        assert_eq!(Parser::new(&input).parse().unwrap(), expected);
+
+        let input = vec![
+            Token::LParen,
+            Token::Number(30.0),
+            Token::Plus,
+            Token::Number(15.0),
+            Token::RParen,
+            Token::Degree,
+        ];
+        let expected = degrees(Box::new(Expr::BinaryOp {
+            op: Token::Plus,
+            left: Box::new(Expr::Number(30.0)),
+            right: Box::new(Expr::Number(15.0)),
+        }));
+        assert_eq!(Parser::new(&input).parse().unwrap(), expected);
+
+        let input = vec![Token::Degree, Token::Number(45.0)];
+        let err = Parser::new(&input).parse().unwrap_err();
+        assert_eq!(err.message(), "Degree sign must follow a value");
+    }
+
+    #[test]
+    fn test_percent_suffix() {
+        let input = vec![Token::Number(10.0), Token::Percent, Token::Number(3.0)];
+        let expected = Box::new(Expr::BinaryOp {
+            op: Token::Percent,
+            left: Box::new(Expr::Number(10.0)),
+            right: Box::new(Expr::Number(3.0)),
+        });
+        let parser = Parser::new(&input).percent_suffix(true);
+        assert_eq!(parser.parse().unwrap(), expected);
+
+        let input = vec![Token::Number(10.0), Token::Percent];
+        let expected = Box::new(Expr::UnaryOp {
+            op: Token::Percent,
+            operand: Box::new(Expr::Number(10.0)),
+        });
+        let parser = Parser::new(&input).percent_suffix(true);
+        assert_eq!(parser.parse().unwrap(), expected);
+        assert!(Parser::new(&input).parse().is_err());
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        let input = vec![
+            Token::Number(2.0),
+            Token::Caret,
+            Token::Number(3.0),
+            Token::Identifier("x".to_string()),
+        ];
+        let parser = Parser::new(&input).implicit_multiplication(true);
+        let expected = Box::new(Expr::BinaryOp {
+            op: Token::Star,
+            left: Box::new(Expr::BinaryOp {
+                op: Token::Caret,
+                left: Box::new(Expr::Number(2.0)),
+                right: Box::new(Expr::Number(3.0)),
+            }),
+            right: Box::new(Expr::Variable("x".to_string())),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+
+        let parser = Parser::new(&input);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_unary_plus() {
+        let input = vec![Token::Plus, Token::Plus, Token::Number(5.0)];
+        let parser = Parser::new(&input);
+        assert_eq!(*parser.parse().unwrap(), Expr::Number(5.0));
+    }
+
+    #[test]
+    fn test_power_right_associative() {
+        let input = vec![
+            Token::Minus,
+            Token::Number(2.0),
+            Token::Caret,
+            Token::Number(3.0),
+            Token::Caret,
+            Token::Number(2.0),
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::UnaryOp {
+            op: Token::Minus,
+            operand: Box::new(Expr::BinaryOp {
+                op: Token::Caret,
+                left: Box::new(Expr::Number(2.0)),
+                right: Box::new(Expr::BinaryOp {
+                    op: Token::Caret,
+                    left: Box::new(Expr::Number(3.0)),
+                    right: Box::new(Expr::Number(2.0)),
+                }),
+            }),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_stacked_unary() {
+        // -(-2 + 1)
+        let input = vec![
+            Token::Minus,
+            Token::LParen,
+            Token::Minus,
+            Token::Number(2.0),
+            Token::Plus,
+            Token::Number(1.0),
+            Token::RParen,
+        ];
+        let parser = Parser::new(&input);
+        let expected = Box::new(Expr::UnaryOp {
+            op: Token::Minus,
+            operand: Box::new(Expr::BinaryOp {
+                op: Token::Plus,
+                left: Box::new(Expr::UnaryOp {
+                    op: Token::Minus,
+                    operand: Box::new(Expr::Number(2.0)),
+                }),
+                right: Box::new(Expr::Number(1.0)),
+            }),
+        });
+        assert_eq!(*parser.parse().unwrap(), *expected);
+    }
+
+    #[test]
+    fn test_booleans() {
+        let input = vec![Token::Keyword(Word::True)];
+        let parser = Parser::new(&input);
+        assert_eq!(*parser.parse().unwrap(), Expr::Number(1.0));
+
+        let input = vec![
+            Token::Keyword(Word::False),
+            Token::Equal,
+            Token::Number(1.0),
+        ];
+        let parser = Parser::new(&input);
+        assert_eq!(
+            parser.parse().unwrap_err().message(),
+            "Cannot assign to reserved word: false"
+        );
+    }
 }
@@ -0,0 +1,14 @@
+//! Commonly used items, for glob importing.
+//!
+//! ```
+//! use expressive_calc::prelude::*;
+//!
+//! let mut calculator = Calculator::new();
+//! assert_eq!(calculator.quick_evaluate("1 + 2").unwrap(), 3.0);
+//!
+//! let err: CalcError = calculator.evaluate("1 +").unwrap_err();
+//! assert_eq!(err.kind(), ErrorKind::Parse);
+//! ```
+
+pub use crate::ast::{Expr, Span, Visitor, Word};
+pub use crate::{CalcError, Calculator, CancellationToken, ErrorKind, Warning};
@@ -0,0 +1,205 @@
+//! Module for printing an abstract syntax tree back into expression text.
+
+use crate::format;
+use crate::parser::{self, Associativity, Expr, PREFIX_PRECEDENCE};
+use crate::scanner::Token;
+use std::fmt;
+
+// The precedences of binary operators are read from `parser::BINARY_OPERATORS`.
+
+/// The precedence of an assignment, the loosest binding expression.
+pub(crate) const ASSIGN: u8 = 0;
+/// The precedence of `or`, the loosest binary operator.
+pub(crate) const OR: u8 = 1;
+/// The precedence of `*`, `/`, and `%`.
+pub(crate) const FACTOR: u8 = 4;
+/// The precedence of unary minus, including negative numbers.
+pub(crate) const UNARY: u8 = PREFIX_PRECEDENCE;
+/// The precedence of postfix operators, such as `!`.
+pub(crate) const POSTFIX: u8 = 7;
+/// The precedence of numbers, variables, and calls, which never need parentheses.
+pub(crate) const PRIMARY: u8 = 8;
+
+/// Print an expression as text that parses back into the same expression.
+///
+/// Binary operators are surrounded by spaces, and parentheses are only added where precedence requires them,
+/// so `(1 + 2) * 3` keeps its parentheses, but `1 + (2 * 3)` is printed as `1 + 2 * 3`.
+/// Numbers are printed with the shortest representation that round-trips to the same value.
+/// NaN is printed as `nan`.
+/// Functions are printed as calls, so `|x|` is printed as `abs(x)`.
+pub fn to_infix(expr: &Expr) -> String {
+    print(expr, ASSIGN)
+}
+
+impl fmt::Display for Expr {
+    /// Formats the expression as normalized text that parses back into the same expression.
+    ///
+    /// Constants such as `pi` are replaced by their values when parsed,
+    /// so they are printed as numbers with enough digits to parse back to the same value.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_infix(self))
+    }
+}
+
+/// Print an expression, adding parentheses if it binds looser than `min_precedence`.
+fn print(expr: &Expr, min_precedence: u8) -> String {
+    let (text, precedence) = print_unwrapped(expr);
+    if precedence < min_precedence {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Print a list of arguments separated by commas.
+fn print_args<'a>(args: impl IntoIterator<Item = &'a Expr>) -> String {
+    args.into_iter()
+        .map(|arg| print(arg, ASSIGN))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Print an expression without outer parentheses, returning the text and its precedence.
+fn print_unwrapped(expr: &Expr) -> (String, u8) {
+    match expr {
+        Expr::Number(n) if n.is_nan() => ("nan".to_string(), PRIMARY),
+        Expr::Number(n) if n.is_sign_negative() => (format::format_number(*n, None), UNARY),
+        Expr::Number(n) => (format::format_number(*n, None), PRIMARY),
+        Expr::Variable(name) => (name.clone(), PRIMARY),
+        Expr::UnaryOp { op, operand } => match op {
+            Token::Minus => (format!("-{}", print(operand, UNARY)), UNARY),
+            Token::Bang => (format!("{}!", print(operand, PRIMARY)), POSTFIX),
+            Token::Percent => (format!("{}%", print(operand, PRIMARY)), POSTFIX),
+            Token::Degree => (format!("{}°", print(operand, PRIMARY)), POSTFIX),
+            Token::Keyword(w) => (format!("{}({})", w.name(), print(operand, ASSIGN)), PRIMARY),
+            _ => (format!("{:?}({})", op, print(operand, ASSIGN)), PRIMARY),
+        },
+        Expr::BinaryOp { op, left, right } => {
+            let operator = match (parser::binary_operator(op), op) {
+                (Some(operator), _) => operator,
+                (None, Token::Keyword(w)) => {
+                    let text = format!("{}({})", w.name(), print_args([&**left, &**right]));
+                    return (text, PRIMARY);
+                }
+                (None, _) => {
+                    let text = format!("{:?}({})", op, print_args([&**left, &**right]));
+                    return (text, PRIMARY);
+                }
+            };
+            let precedence = operator.precedence;
+            let (left_min, right_min) = match operator.associativity {
+                // A right operand at the same precedence needs parentheses
+                Associativity::Left => (precedence, precedence + 1),
+                // Operators tighter than unary minus, such as `^`, may have a unary minus on the right
+                Associativity::Right if precedence > UNARY => (precedence + 1, UNARY),
+                Associativity::Right => (precedence + 1, precedence),
+            };
+            // With a percent suffix, `10 % -3` is parsed as `10% - 3`, so a leading minus needs parentheses
+            let right_min = match op {
+                Token::Percent => right_min.max(UNARY + 1),
+                _ => right_min,
+            };
+            let text = format!(
+                "{} {} {}",
+                print(left, left_min),
+                operator.symbol,
+                print(right, right_min)
+            );
+            (text, precedence)
+        }
+        Expr::NaryOp { op, args } => {
+            let name = match op {
+                Token::Keyword(w) => w.name().to_string(),
+                _ => format!("{:?}", op),
+            };
+            (format!("{}({})", name, print_args(args)), PRIMARY)
+        }
+        Expr::Call { name, args } => (format!("{}({})", name, print_args(args)), PRIMARY),
+        Expr::Assign { name, value } => (format!("{} = {}", name, print(value, OR)), ASSIGN),
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    /// Parse an input into an expression.
+    fn parse(input: &str) -> Box<Expr> {
+        let tokens = Scanner::new(input).scan().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_to_infix() {
+        let cases = [
+            ("1+2 * 3", "1 + 2 * 3"),
+            ("(1 + 2) * 3", "(1 + 2) * 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("2 ^ 3 ^ 2", "2 ^ 3 ^ 2"),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+            ("-2 ^ 2", "-2 ^ 2"),
+            ("(-2) ^ -2", "(-2) ^ -2"),
+            ("-3!", "-3!"),
+            ("(-3)!", "(-3)!"),
+            ("sin(45 deg) + (1 + 2)°", "sin(45°) + (1 + 2)°"),
+            ("|x - 1|", "abs(x - 1)"),
+            (
+                "max(1, 2,) + logsumexp(1, 2, 3)",
+                "max(1, 2) + logsumexp(1, 2, 3)",
+            ),
+            ("x = 1 or 0 and 1", "x = 1 or 0 and 1"),
+            ("(1 or 0) and 1", "(1 or 0) and 1"),
+            ("1e300 * 0.1", "1e300 * 0.1"),
+            ("cal(2, $ans)", "cal(2, $ans)"),
+            ("10 % -3 * 2", "10 % (-3) * 2"),
+            ("10 % -x ^ 2", "10 % (-x ^ 2)"),
+            ("10 % 2 ^ -x", "10 % 2 ^ -x"),
+        ];
+        for (input, expected) in cases {
+            let expr = parse(input);
+            let printed = to_infix(&expr);
+            assert_eq!(printed, expected, "{}", input);
+            assert_eq!(parse(&printed), expr, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for input in ["1+2 * 3", "-(x - 1)^2", "pi * r ^ 2", "f(-1, 2!) % 3"] {
+            let expr = parse(input);
+            let printed = expr.to_string();
+            assert_eq!(printed, to_infix(&expr), "{}", input);
+            assert_eq!(parse(&printed), expr, "{}", input);
+        }
+        assert_eq!(parse("2 * pi").to_string(), "2 * 3.141592653589793");
+    }
+
+    #[test]
+    fn test_to_infix_numbers() {
+        let cases = [
+            (Expr::Number(-3.0), "2 ^ -3"),
+            (Expr::Number(f64::INFINITY), "2 ^ inf"),
+            (Expr::Number(f64::NEG_INFINITY), "2 ^ -inf"),
+            (Expr::Number(f64::NAN), "2 ^ nan"),
+        ];
+        for (right, expected) in cases {
+            let expr = Expr::BinaryOp {
+                op: Token::Caret,
+                left: Box::new(Expr::Number(2.0)),
+                right: Box::new(right),
+            };
+            assert_eq!(to_infix(&expr), expected);
+        }
+
+        let expr = Expr::BinaryOp {
+            op: Token::Caret,
+            left: Box::new(Expr::Number(-2.0)),
+            right: Box::new(Expr::Number(2.0)),
+        };
+        assert_eq!(to_infix(&expr), "(-2) ^ 2");
+    }
+}
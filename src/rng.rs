@@ -0,0 +1,105 @@
+//! Module for the pseudo-random number generator used by `rand`.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A small pseudo-random number generator using xorshift64*.
+///
+/// It is fast and has no dependencies, but it is not suitable for cryptography.
+/// The state is atomic, so numbers can be drawn through a shared reference, including from several threads.
+/// Cloning copies the state, so the clone produces the same sequence as the original.
+#[derive(Debug)]
+pub(crate) struct Rng {
+    state: AtomicU64,
+}
+impl Rng {
+    /// Create a generator from a seed.
+    ///
+    /// The same seed always produces the same sequence of numbers.
+    pub(crate) fn new(seed: u64) -> Self {
+        // Scramble the seed with splitmix64 so that similar seeds give unrelated sequences
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // The xorshift state must not be zero
+        Self {
+            state: AtomicU64::new(if z == 0 { 1 } else { z }),
+        }
+    }
+
+    /// Create a generator with a seed that differs between runs of the program.
+    pub(crate) fn from_entropy() -> Self {
+        // The standard library seeds the keys of each RandomState randomly
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    /// Returns the next pseudo-random 64-bit number.
+    fn next_u64(&self) -> u64 {
+        let step = |mut state: u64| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            state
+        };
+        // Each caller advances the state exactly once, even when called from several threads at the same time
+        let previous = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |state| {
+                Some(step(state))
+            })
+            .expect("the update always succeeds");
+        step(previous).wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a pseudo-random number uniformly distributed in `[0, 1)`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        // The top 53 bits fill the significand exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Clone for Rng {
+    fn clone(&self) -> Self {
+        Self {
+            state: AtomicU64::new(self.state.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_seeded() {
+        let a = Rng::new(42);
+        let b = Rng::new(42);
+        let c = Rng::new(43);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let third: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn test_rng_range() {
+        let rng = Rng::new(0);
+        let values: Vec<f64> = (0..10_000).map(|_| rng.next_f64()).collect();
+        assert!(values.iter().all(|x| (0.0..1.0).contains(x)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.02, "{}", mean);
+    }
+
+    #[test]
+    fn test_rng_clone() {
+        let rng = Rng::new(7);
+        rng.next_u64();
+        let clone = rng.clone();
+        assert_eq!(rng.next_u64(), clone.next_u64());
+        assert_eq!(rng.next_u64(), clone.next_u64());
+    }
+}
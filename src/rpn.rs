@@ -0,0 +1,334 @@
+//! Module for writing expressions in reverse Polish notation (RPN).
+
+use crate::calc_error::{CalcError, ErrorKind};
+use crate::format;
+use crate::parser::Expr;
+use crate::scanner::{Arity, Span, Token};
+
+/// The word used for unary minus, so that it is not confused with subtraction.
+pub const NEG: &str = "neg";
+
+/// Combines the operands popped by a token into an expression.
+type Build<'a> = Box<dyn FnOnce(Vec<Expr>) -> Expr + 'a>;
+
+impl Expr {
+    /// Render the expression in reverse Polish notation, with operands before their operators.
+    ///
+    /// Words are separated by spaces, so `1 + 2 * 3` is `1 2 3 * +` and `pow(2, 3)` is `2 3 pow`.
+    /// Unary minus is written as `neg`, so `-(1 + 2)` is `1 2 + neg`.
+    /// A percent suffix is written as a division by 100, and NaN is written as `nan`.
+    /// Functions that accept any number of arguments and named functions are followed by their argument count,
+    /// so `logsumexp(1, 2, 3)` is `1 2 3 logsumexp(3)`, while `clamp(5, 0, 1)` is `5 0 1 clamp`
+    /// and `max(1, 2)` is `1 2 max`.
+    /// A function with an optional argument is followed by the count when the argument is given,
+    /// so `round($x, 2)` is `$x 2 round(2)`.
+    /// An assignment is written as its value, the name, and `=`, such as `2 radius =`.
+    /// The output can be evaluated with [`Calculator::evaluate_rpn`](crate::Calculator::evaluate_rpn).
+    ///
+    /// ```
+    /// use expressive_calc::ast::{Expr, Token};
+    ///
+    /// let expr = Expr::UnaryOp {
+    ///     op: Token::Minus,
+    ///     operand: Box::new(Expr::Variable("$x".to_string())),
+    /// };
+    /// assert_eq!(expr.to_rpn(), "$x neg");
+    /// ```
+    pub fn to_rpn(&self) -> String {
+        let mut words = Vec::new();
+        write_rpn(self, &mut words);
+        words.join(" ")
+    }
+}
+
+/// Returns the word for an operator token, such as `+` or `sqrt`.
+fn operator(op: &Token) -> String {
+    match op {
+        Token::Plus => "+".to_string(),
+        Token::Minus => "-".to_string(),
+        Token::Star => "*".to_string(),
+        Token::Slash => "/".to_string(),
+        Token::Percent => "%".to_string(),
+        Token::Caret => "^".to_string(),
+        Token::Bang => "!".to_string(),
+        Token::Degree => "°".to_string(),
+        Token::And => "and".to_string(),
+        Token::Or => "or".to_string(),
+        Token::Keyword(w) => w.name().to_string(),
+        _ => format!("{:?}", op),
+    }
+}
+
+/// Build an expression from tokens in reverse Polish notation, such as `3 4 + 2 *`.
+///
+/// The tokens are read with a stack: operands are pushed, and each operator pops its operands
+/// and pushes the operation, so the result is the same expression the infix parser builds.
+/// The notation is the one written by [`Expr::to_rpn`]: `-` is always subtraction, `neg` is unary minus,
+/// and functions that accept any number of arguments, as well as named functions,
+/// are followed by their argument count, such as `cal(2)`.
+/// The count may be left out for `min` and `max` with two arguments, such as `1 2 max`.
+/// Any other identifier is a variable, and an identifier followed by `=` assigns the value below it.
+///
+/// # Errors
+///
+/// Returns a [`CalcError`] if an operator has too few operands, if more than one value is left at the end,
+/// or if a token cannot be used in reverse Polish notation, such as `(` or `,`.
+pub fn parse(tokens: &[Token], spans: &[Span]) -> Result<Box<Expr>, CalcError> {
+    let span_at = |index: usize| match spans.get(index) {
+        Some(span) => *span,
+        None => {
+            let last = spans.last().copied().unwrap_or_default();
+            Span {
+                start: last.end,
+                byte_start: last.byte_end,
+                ..last
+            }
+        }
+    };
+    let error = |message: &str, index: usize| {
+        CalcError::new(message, None)
+            .with_kind(ErrorKind::Parse)
+            .with_span(span_at(index))
+    };
+
+    // Each value on the stack keeps the index of its first token, for error spans
+    let mut stack: Vec<(Box<Expr>, usize)> = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let start = index;
+        // The number of operands the token pops, and how it combines them
+        let (count, build): (usize, Build) = match &tokens[index] {
+            Token::Number(n) => (0, Box::new(move |_| Expr::Number(*n))),
+            Token::Variable(name) => (0, Box::new(move |_| Expr::Variable(name.clone()))),
+            Token::Plus
+            | Token::Minus
+            | Token::Star
+            | Token::Slash
+            | Token::Percent
+            | Token::Caret
+            | Token::And
+            | Token::Or => {
+                let op = tokens[index].clone();
+                (2, Box::new(move |args| binary(op, args)))
+            }
+            Token::Bang => (1, Box::new(|args| unary(Token::Bang, args))),
+            Token::Degree => (1, Box::new(|args| unary(Token::Degree, args))),
+            Token::Keyword(w) => match w.constant() {
+                Some(value) => (0, Box::new(move |_| Expr::Number(value))),
+                None => {
+                    let count = match (w.arity(), argument_count(tokens, &mut index)) {
+                        (_, Some(count)) => count,
+                        // Without an argument count, a word takes its fewest arguments,
+                        // unless it is variadic and takes as few as one
+                        (Arity::AtLeast(min), None) if min >= 2 => min,
+                        (Arity::AtLeast(_), None) => {
+                            return Err(error(
+                                &format!("Expected argument count, such as {}(2)", w.name()),
+                                start,
+                            ))
+                        }
+                        (arity, None) => arity.min(),
+                    };
+                    w.check_arity(count)
+                        .map_err(|e| e.with_kind(ErrorKind::Parse).with_span(span_at(start)))?;
+                    let op = Token::Keyword(w.clone());
+                    // Calls with one or two arguments are unary or binary, as in infix input
+                    let build: Build = match (w.arity(), count) {
+                        (Arity::AtLeast(min), _) if min < 2 || count > 2 => {
+                            Box::new(move |args| Expr::NaryOp { op, args })
+                        }
+                        (_, 1) => Box::new(move |args| unary(op, args)),
+                        (_, 2) => Box::new(move |args| binary(op, args)),
+                        _ => Box::new(move |args| Expr::NaryOp { op, args }),
+                    };
+                    (count, build)
+                }
+            },
+            Token::Identifier(name) if name == NEG => {
+                (1, Box::new(|args| unary(Token::Minus, args)))
+            }
+            Token::Identifier(name) => match tokens.get(index + 1) {
+                Some(Token::Equal) => {
+                    index += 1;
+                    let name = name.clone();
+                    (
+                        1,
+                        Box::new(move |mut args| Expr::Assign {
+                            name,
+                            value: Box::new(args.remove(0)),
+                        }),
+                    )
+                }
+                Some(Token::LParen) => {
+                    let count = argument_count(tokens, &mut index).ok_or_else(|| {
+                        error(
+                            &format!("Expected argument count, such as {}(2)", name),
+                            start,
+                        )
+                    })?;
+                    let name = name.clone();
+                    (count, Box::new(move |args| Expr::Call { name, args }))
+                }
+                _ => (0, Box::new(move |_| Expr::Variable(name.clone()))),
+            },
+            _ => return Err(error("Unexpected token in RPN input", index)),
+        };
+
+        if stack.len() < count {
+            return Err(error(
+                &format!(
+                    "Stack underflow: operator needs {} operand(s), found {}",
+                    count,
+                    stack.len()
+                ),
+                start,
+            ));
+        }
+        let operands = stack.split_off(stack.len() - count);
+        let first = operands.first().map_or(start, |(_, first)| *first);
+        let args = operands.into_iter().map(|(expr, _)| *expr).collect();
+        stack.push((Box::new(build(args)), first));
+        index += 1;
+    }
+
+    match stack.len() {
+        0 => Err(error("Stack is empty; expected one result", 0)),
+        1 => Ok(stack.pop().expect("stack has one value").0),
+        n => Err(error(
+            &format!("{} values left on the stack; expected one result", n),
+            stack[1].1,
+        )),
+    }
+}
+
+/// Read an argument count written as `(n)` after the token at `index`, moving `index` to the `)`.
+///
+/// Returns `None` without moving `index` if the count is missing or is not a whole number.
+fn argument_count(tokens: &[Token], index: &mut usize) -> Option<usize> {
+    match tokens.get(*index + 1..*index + 4)? {
+        [Token::LParen, Token::Number(n), Token::RParen] if n.fract() == 0.0 && *n >= 0.0 => {
+            *index += 3;
+            Some(*n as usize)
+        }
+        _ => None,
+    }
+}
+
+/// Build a unary operation from one operand.
+fn unary(op: Token, mut args: Vec<Expr>) -> Expr {
+    Expr::UnaryOp {
+        op,
+        operand: Box::new(args.remove(0)),
+    }
+}
+
+/// Build a binary operation from two operands.
+fn binary(op: Token, mut args: Vec<Expr>) -> Expr {
+    let right = args.remove(1);
+    Expr::BinaryOp {
+        op,
+        left: Box::new(args.remove(0)),
+        right: Box::new(right),
+    }
+}
+
+/// Add the words of an expression to a list, operands first.
+fn write_rpn(expr: &Expr, words: &mut Vec<String>) {
+    match expr {
+        Expr::Number(n) if n.is_nan() => words.push("nan".to_string()),
+        Expr::Number(n) if n.is_sign_negative() => {
+            words.push(format::format_number(-n, None));
+            words.push(NEG.to_string());
+        }
+        Expr::Number(n) => words.push(format::format_number(*n, None)),
+        Expr::Variable(name) => words.push(name.clone()),
+        Expr::UnaryOp { op, operand } => {
+            write_rpn(operand, words);
+            match op {
+                Token::Minus => words.push(NEG.to_string()),
+                Token::Percent => words.extend(["100", "/"].map(String::from)),
+                _ => words.push(operator(op)),
+            }
+        }
+        Expr::BinaryOp { op, left, right } => {
+            write_rpn(left, words);
+            write_rpn(right, words);
+            match op {
+                // Words that also take one argument, such as `round`, need the count
+                Token::Keyword(w) if w.arity().min() < 2 => {
+                    words.push(format!("{}(2)", operator(op)))
+                }
+                _ => words.push(operator(op)),
+            }
+        }
+        Expr::NaryOp { op, args } => {
+            for arg in args {
+                write_rpn(arg, words);
+            }
+            match op {
+                Token::Keyword(w) if matches!(w.arity(), Arity::Exactly(_)) => {
+                    words.push(operator(op))
+                }
+                _ => words.push(format!("{}({})", operator(op), args.len())),
+            }
+        }
+        Expr::Call { name, args } => {
+            for arg in args {
+                write_rpn(arg, words);
+            }
+            words.push(format!("{}({})", name, args.len()));
+        }
+        Expr::Assign { name, value } => {
+            write_rpn(value, words);
+            words.push(name.clone());
+            words.push("=".to_string());
+        }
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Expr, Parser};
+    use crate::scanner::Scanner;
+
+    /// Parse an input into an expression.
+    fn parse(input: &str) -> Box<Expr> {
+        let (tokens, spans) = Scanner::new(input).scan_with_spans().unwrap();
+        Parser::with_spans(&tokens, spans)
+            .percent_suffix(true)
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_to_rpn() {
+        let cases = [
+            ("1 + 2 * 3", "1 2 3 * +"),
+            ("(1 + 2) * 3", "1 2 + 3 *"),
+            ("1 - 2 - 3", "1 2 - 3 -"),
+            ("1 - (2 - 3)", "1 2 3 - -"),
+            ("2 ^ 3 ^ 2", "2 3 2 ^ ^"),
+            ("pow(2, 3)", "2 3 pow"),
+            ("-(1 + 2)", "1 2 + neg"),
+            ("-2 ^ 2", "2 2 ^ neg"),
+            ("2 - -3", "2 3 neg -"),
+            ("sqrt(9) + 3!", "9 sqrt 3 ! +"),
+            ("sin(90°) + cos(x deg)", "90 ° sin x ° cos +"),
+            ("15% * $x", "15 100 / $x *"),
+            ("1 or 0 and 1", "1 0 1 and or"),
+            ("logsumexp(1, 2, 3)", "1 2 3 logsumexp(3)"),
+            ("max(1, 2) + min(1, 2, 3)", "1 2 max 1 2 3 min(3) +"),
+            ("clamp($x, 0, 1)", "$x 0 1 clamp"),
+            ("round($x) + round($x, 2)", "$x round $x 2 round(2) +"),
+            ("cal(4) + armor(1, 2)", "4 cal(1) 1 2 armor(2) +"),
+            ("radius = 2 * pi", "2 3.141592653589793 * radius ="),
+            ("1e300 / inf", "1e300 inf /"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input).to_rpn(), expected, "{}", input);
+        }
+        assert_eq!(Expr::Number(f64::NAN).to_rpn(), "nan");
+        assert_eq!(Expr::Number(-1.5).to_rpn(), "1.5 neg");
+    }
+}
@@ -1,13 +1,159 @@
 //! Module for scanning an input string and converting it into a vector of tokens.
 
-use crate::calc_error::CalcError;
+use crate::calc_error::{CalcError, ErrorKind};
+use crate::format;
+use crate::math;
+use std::f64::consts::{FRAC_1_SQRT_2, LN_10, LN_2, SQRT_2};
 use std::{iter::Peekable, str::Chars};
 
+const PHI: f64 = 1.618033988749894848204586834365638118_f64;
+
+/// The default maximum number of characters in a number literal.
+pub const DEFAULT_MAX_LITERAL_LENGTH: usize = 1000;
+
+/// The name, value, and description of every constant, in the order of [`Word::ALL`].
+///
+/// This is the table read by [`Word::apply`] and the parser, so it always matches what expressions evaluate to.
+pub const CONSTANTS: &[(&str, f64, &str)] = &[
+    ("inf", f64::INFINITY, "Positive infinity"),
+    (
+        "pi",
+        std::f64::consts::PI,
+        "Ratio of a circle's circumference to its diameter",
+    ),
+    (
+        "tau",
+        std::f64::consts::TAU,
+        "Ratio of a circle's circumference to its radius, 2π",
+    ),
+    (
+        "e",
+        std::f64::consts::E,
+        "Euler's number, the base of the natural logarithm",
+    ),
+    ("phi", PHI, "The golden ratio"),
+    ("ln2", LN_2, "Natural logarithm of 2"),
+    ("ln10", LN_10, "Natural logarithm of 10"),
+    ("sqrt2", SQRT_2, "Square root of 2"),
+    (
+        "invsqrt2",
+        FRAC_1_SQRT_2,
+        "Reciprocal of the square root of 2, 1/√2",
+    ),
+    ("true", 1.0, "Boolean true"),
+    ("false", 0.0, "Boolean false"),
+    (
+        "float_digits",
+        f64::DIGITS as f64,
+        "Decimal digits that can be represented without loss",
+    ),
+    (
+        "float_maxexp",
+        f64::MAX_EXP as f64,
+        "Maximum binary exponent of a finite number",
+    ),
+    (
+        "nan",
+        f64::NAN,
+        "Not a number, the result of undefined operations like 0 / 0",
+    ),
+    (
+        "eps",
+        f64::EPSILON,
+        "Difference between 1 and the next larger number",
+    ),
+    ("maxval", f64::MAX, "Largest finite number"),
+    (
+        "minval",
+        f64::MIN_POSITIVE,
+        "Smallest positive normal number",
+    ),
+];
+
+/// The name, value, and description of every physical constant, in the order of [`Word::ALL`].
+///
+/// Values are in SI units, from CODATA 2018.
+/// These names are only reserved when physical constants are enabled with [`Scanner::physical_constants`],
+/// since short names like `c` and `h` are common variable names.
+pub const PHYSICAL_CONSTANTS: &[(&str, f64, &str)] = &[
+    ("c", 299_792_458.0, "Speed of light in vacuum, in m/s"),
+    (
+        "G",
+        6.674_30e-11,
+        "Newtonian constant of gravitation, in m^3/(kg s^2)",
+    ),
+    ("h", 6.626_070_15e-34, "Planck constant, in J s"),
+    (
+        "hbar",
+        1.054_571_817e-34,
+        "Reduced Planck constant, h/2π, in J s",
+    ),
+    ("na", 6.022_140_76e23, "Avogadro constant, in 1/mol"),
+    ("kb", 1.380_649e-23, "Boltzmann constant, in J/K"),
+    ("qe", 1.602_176_634e-19, "Elementary charge, in C"),
+    ("g0", 9.806_65, "Standard acceleration of gravity, in m/s^2"),
+];
+
+/// The numbers of arguments accepted by a [`Word`], as returned by [`Word::arity`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    /// Exactly this many arguments.
+    Exactly(usize),
+    /// Any number of arguments from the first to the second, inclusive.
+    Between(usize, usize),
+    /// Any number of arguments from this many up.
+    AtLeast(usize),
+    /// Exactly one of the listed numbers of arguments, in increasing order.
+    OneOf(&'static [usize]),
+}
+impl Arity {
+    /// Returns the fewest arguments accepted.
+    pub fn min(&self) -> usize {
+        match *self {
+            Arity::Exactly(n) | Arity::Between(n, _) | Arity::AtLeast(n) => n,
+            Arity::OneOf(counts) => counts[0],
+        }
+    }
+
+    /// Returns the most arguments accepted, or `None` if there is no limit.
+    pub fn max(&self) -> Option<usize> {
+        match *self {
+            Arity::Exactly(n) | Arity::Between(_, n) => Some(n),
+            Arity::AtLeast(_) => None,
+            Arity::OneOf(counts) => counts.last().copied(),
+        }
+    }
+
+    /// Returns true if exactly `count` arguments are accepted.
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::OneOf(counts) => counts.contains(&count),
+            _ => count >= self.min() && self.max().is_none_or(|max| count <= max),
+        }
+    }
+}
+impl std::fmt::Display for Arity {
+    /// Formats the accepted numbers of arguments as in an error message, such as `1 to 2` or `0 or 2`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{}", n),
+            Arity::Between(min, max) => write!(f, "{} to {}", min, max),
+            Arity::AtLeast(min) => write!(f, "at least {}", min),
+            Arity::OneOf(counts) => {
+                let counts: Vec<String> = counts.iter().map(usize::to_string).collect();
+                write!(f, "{}", counts.join(" or "))
+            }
+        }
+    }
+}
+
 /// Enum for the different reserved words in the calculator.
 ///
 /// Keywords are special tokens that have a specific meaning in the calculator.
 /// These include functions like `sqrt`.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Word {
     // Numbers
     Inf,
@@ -15,6 +161,55 @@ pub enum Word {
     Tau,
     E,
     Phi,
+    /// The natural logarithm of 2, `ln(2)`.
+    Ln2,
+    /// The natural logarithm of 10, `ln(10)`.
+    Ln10,
+    /// The square root of 2, `sqrt(2)`.
+    Sqrt2,
+    /// The reciprocal of the square root of 2, `1 / sqrt(2)`.
+    Invsqrt2,
+    /// Boolean true, equal to `1`.
+    True,
+    /// Boolean false, equal to `0`.
+    False,
+    /// The number of decimal digits an f64 can represent without loss, `15`.
+    FloatDigits,
+    /// The maximum binary exponent of an f64, `1024`, so finite values are below `2^1024`.
+    FloatMaxexp,
+    /// Not a number. It is not equal to anything, including itself.
+    Nan,
+    /// The machine epsilon, `2^-52`, the difference between `1` and the next larger f64.
+    Eps,
+    /// The largest finite f64, about `1.8e308`.
+    Maxval,
+    /// The smallest positive normal f64, `2^-1022`. Subnormal numbers are smaller.
+    Minval,
+    /// The speed of light in vacuum, `c`. This and the following physical constants are
+    /// listed by [`physical_constants`](crate::physical_constants) and only scanned when enabled.
+    #[cfg_attr(feature = "serde", serde(rename = "c"))]
+    SpeedOfLight,
+    /// The Newtonian constant of gravitation, `G`.
+    #[cfg_attr(feature = "serde", serde(rename = "G"))]
+    Gravitation,
+    /// The Planck constant, `h`.
+    #[cfg_attr(feature = "serde", serde(rename = "h"))]
+    Planck,
+    /// The reduced Planck constant, `hbar`.
+    #[cfg_attr(feature = "serde", serde(rename = "hbar"))]
+    ReducedPlanck,
+    /// The Avogadro constant, `na`.
+    #[cfg_attr(feature = "serde", serde(rename = "na"))]
+    Avogadro,
+    /// The Boltzmann constant, `kb`.
+    #[cfg_attr(feature = "serde", serde(rename = "kb"))]
+    Boltzmann,
+    /// The elementary charge, `qe`.
+    #[cfg_attr(feature = "serde", serde(rename = "qe"))]
+    ElementaryCharge,
+    /// The standard acceleration of gravity, `g0`.
+    #[cfg_attr(feature = "serde", serde(rename = "g0"))]
+    StandardGravity,
 
     // Unary operations
     Sqrt,
@@ -29,37 +224,794 @@ pub enum Word {
     Asin,
     Acos,
     Atan,
+    /// Secant, `1 / cos(x)`.
+    Sec,
+    /// Cosecant, `1 / sin(x)`; `csc(0)` is `inf`.
+    Csc,
+    /// Cotangent, `cos(x) / sin(x)`; `cot(0)` is `inf`.
+    Cot,
+    /// Inverse secant, `acos(1 / x)`; defined for `|x| >= 1`.
+    Asec,
+    /// Inverse cosecant, `asin(1 / x)`; defined for `|x| >= 1`.
+    Acsc,
+    /// Inverse cotangent, `atan(1 / x)`, between `-pi / 2` and `pi / 2`.
+    Acot,
+    /// Sine of an angle in degrees, exact at multiples of 30 and 45 degrees.
+    Sind,
+    /// Cosine of an angle in degrees, exact at multiples of 30 and 45 degrees.
+    Cosd,
+    /// Tangent of an angle in degrees, exact at multiples of 45 degrees.
+    Tand,
+    /// Inverse sine in degrees; defined for `-1 <= x <= 1`.
+    Asind,
+    /// Inverse cosine in degrees; defined for `-1 <= x <= 1`.
+    Acosd,
+    /// Inverse tangent in degrees.
+    Atand,
     Sinh,
     Cosh,
     Tanh,
+    Sech,
+    Csch,
+    Coth,
+    /// Inverse hyperbolic sine; defined for all real numbers.
     Asinh,
+    /// Inverse hyperbolic cosine; defined for `x >= 1`.
     Acosh,
+    /// Inverse hyperbolic tangent; defined for `-1 < x < 1`.
     Atanh,
+    /// Inverse hyperbolic secant; defined for `0 < x <= 1`.
+    Asech,
+    /// Inverse hyperbolic cosecant; defined for `x != 0`.
+    Acsch,
+    /// Inverse hyperbolic cotangent; defined for `|x| > 1`.
+    Acoth,
     Rad,
     Deg,
     Abs,
     Floor,
     Ceil,
     Trunc,
+    /// Rounds half away from zero, written `round(x)` for a whole number
+    /// or `round(x, digits)` for a number of decimal places.
+    /// A negative number of digits rounds to tens, hundreds, and so on.
+    /// Digits are rounded as the number is displayed, so `round(2.675, 2)` is `2.68`.
     Round,
+    /// The spacing between `|x|` and the next larger f64, or the unit in the last place.
+    /// `ulp(inf)` is `inf`.
+    Ulp,
+    /// The gamma function, which extends the factorial so that `gamma(n)` is `(n - 1)!`.
+    /// At the poles, zero and the negative integers, the result is `inf`.
+    Gamma,
+    /// The natural logarithm of the absolute value of the gamma function.
+    /// At the poles, zero and the negative integers, the result is `inf`.
+    Lgamma,
+    /// The factorial of a non-negative integer, the same as the `!` operator.
+    /// Results from `fact(171)` onwards are `inf`.
+    Fact,
+    /// The sign of a number: `-1`, `0`, or `1`.
+    /// Both zeros give `0`, and NaN gives NaN.
+    Sign,
+    /// `1` if the argument is NaN, and `0` otherwise.
+    Isnan,
+    /// `1` if the argument is positive or negative infinity, and `0` otherwise.
+    Isinf,
+    /// `1` if the argument is neither infinite nor NaN, and `0` otherwise.
+    Isfinite,
 
     // Binary operations
     Pow,
     Log,
     Hypot,
     Atan2,
+    /// The angle of the point `(x, y)` in degrees, written `atan2d(y, x)`.
+    Atan2d,
     Mod,
     Max,
     Min,
+    Logaddexp,
+    /// The greatest common divisor of two non-negative integers. `gcd(0, 0)` is `0`.
+    Gcd,
+    /// The least common multiple of two non-negative integers.
+    /// `lcm(0, x)` is `0`, and results too large for a 64-bit integer are `inf`.
+    Lcm,
+    /// The number of ways to choose `k` of `n` items, written `ncr(n, k)`.
+    /// Both must be non-negative integers with `k <= n`.
+    Ncr,
+    /// The number of ordered arrangements of `k` of `n` items, written `npr(n, k)`.
+    /// Both must be non-negative integers with `k <= n`.
+    Npr,
+    /// The magnitude of the first argument with the sign of the second, including for zeros and NaN.
+    Copysign,
+    /// The real `n`th root of `x`, written `nthroot(x, n)`.
+    /// Negative `x` is allowed when `n` is an odd integer, so `nthroot(-8, 3)` is `-2`.
+    Nthroot,
+
+    // Ternary operations
+    /// Restricts `x` to the range from `lo` to `hi`, written `clamp(x, lo, hi)`.
+    /// It is an error if `lo > hi`.
+    Clamp,
+
+    // Variadic operations
+    Logsumexp,
+    /// The arithmetic mean of one or more arguments, summed with compensation for rounding error.
+    Avg,
+    /// The sum of one or more arguments, with compensation for rounding error.
+    Sum,
+    /// The product of one or more arguments.
+    Prod,
+
+    // Random numbers
+    /// A pseudo-random number, written `rand()` for the range `[0, 1)` or `rand(a, b)` for `[a, b)`.
+    /// It is drawn from the calculator's generator, so it cannot be evaluated with [`Word::apply`].
+    Rand,
+}
+
+impl Word {
+    /// All reserved words, in the order they are declared.
+    pub const ALL: &'static [Word] = &[
+        Word::Inf,
+        Word::Pi,
+        Word::Tau,
+        Word::E,
+        Word::Phi,
+        Word::Ln2,
+        Word::Ln10,
+        Word::Sqrt2,
+        Word::Invsqrt2,
+        Word::True,
+        Word::False,
+        Word::FloatDigits,
+        Word::FloatMaxexp,
+        Word::Nan,
+        Word::Eps,
+        Word::Maxval,
+        Word::Minval,
+        Word::SpeedOfLight,
+        Word::Gravitation,
+        Word::Planck,
+        Word::ReducedPlanck,
+        Word::Avogadro,
+        Word::Boltzmann,
+        Word::ElementaryCharge,
+        Word::StandardGravity,
+        Word::Sqrt,
+        Word::Cbrt,
+        Word::Exp,
+        Word::Log2,
+        Word::Log10,
+        Word::Ln,
+        Word::Sin,
+        Word::Cos,
+        Word::Tan,
+        Word::Asin,
+        Word::Acos,
+        Word::Atan,
+        Word::Sec,
+        Word::Csc,
+        Word::Cot,
+        Word::Asec,
+        Word::Acsc,
+        Word::Acot,
+        Word::Sind,
+        Word::Cosd,
+        Word::Tand,
+        Word::Asind,
+        Word::Acosd,
+        Word::Atand,
+        Word::Sinh,
+        Word::Cosh,
+        Word::Tanh,
+        Word::Asinh,
+        Word::Acosh,
+        Word::Atanh,
+        Word::Sech,
+        Word::Csch,
+        Word::Coth,
+        Word::Asech,
+        Word::Acsch,
+        Word::Acoth,
+        Word::Rad,
+        Word::Deg,
+        Word::Abs,
+        Word::Floor,
+        Word::Ceil,
+        Word::Trunc,
+        Word::Round,
+        Word::Ulp,
+        Word::Gamma,
+        Word::Lgamma,
+        Word::Fact,
+        Word::Sign,
+        Word::Isnan,
+        Word::Isinf,
+        Word::Isfinite,
+        Word::Pow,
+        Word::Log,
+        Word::Hypot,
+        Word::Atan2,
+        Word::Atan2d,
+        Word::Mod,
+        Word::Max,
+        Word::Min,
+        Word::Logaddexp,
+        Word::Gcd,
+        Word::Lcm,
+        Word::Ncr,
+        Word::Npr,
+        Word::Copysign,
+        Word::Nthroot,
+        Word::Clamp,
+        Word::Logsumexp,
+        Word::Avg,
+        Word::Sum,
+        Word::Prod,
+        Word::Rand,
+    ];
+
+    /// Returns the keyword used to write this word in an expression.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Word::Inf => "inf",
+            Word::Pi => "pi",
+            Word::Tau => "tau",
+            Word::E => "e",
+            Word::Phi => "phi",
+            Word::Ln2 => "ln2",
+            Word::Ln10 => "ln10",
+            Word::Sqrt2 => "sqrt2",
+            Word::Invsqrt2 => "invsqrt2",
+            Word::True => "true",
+            Word::False => "false",
+            Word::FloatDigits => "float_digits",
+            Word::FloatMaxexp => "float_maxexp",
+            Word::Nan => "nan",
+            Word::Eps => "eps",
+            Word::Maxval => "maxval",
+            Word::Minval => "minval",
+            Word::SpeedOfLight => "c",
+            Word::Gravitation => "G",
+            Word::Planck => "h",
+            Word::ReducedPlanck => "hbar",
+            Word::Avogadro => "na",
+            Word::Boltzmann => "kb",
+            Word::ElementaryCharge => "qe",
+            Word::StandardGravity => "g0",
+            Word::Sqrt => "sqrt",
+            Word::Cbrt => "cbrt",
+            Word::Exp => "exp",
+            Word::Log2 => "log2",
+            Word::Log10 => "log10",
+            Word::Ln => "ln",
+            Word::Sin => "sin",
+            Word::Cos => "cos",
+            Word::Tan => "tan",
+            Word::Asin => "asin",
+            Word::Acos => "acos",
+            Word::Atan => "atan",
+            Word::Sec => "sec",
+            Word::Csc => "csc",
+            Word::Cot => "cot",
+            Word::Asec => "asec",
+            Word::Acsc => "acsc",
+            Word::Acot => "acot",
+            Word::Sind => "sind",
+            Word::Cosd => "cosd",
+            Word::Tand => "tand",
+            Word::Asind => "asind",
+            Word::Acosd => "acosd",
+            Word::Atand => "atand",
+            Word::Sinh => "sinh",
+            Word::Cosh => "cosh",
+            Word::Tanh => "tanh",
+            Word::Asinh => "asinh",
+            Word::Acosh => "acosh",
+            Word::Atanh => "atanh",
+            Word::Sech => "sech",
+            Word::Csch => "csch",
+            Word::Coth => "coth",
+            Word::Asech => "asech",
+            Word::Acsch => "acsch",
+            Word::Acoth => "acoth",
+            Word::Rad => "rad",
+            Word::Deg => "deg",
+            Word::Abs => "abs",
+            Word::Floor => "floor",
+            Word::Ceil => "ceil",
+            Word::Trunc => "trunc",
+            Word::Round => "round",
+            Word::Ulp => "ulp",
+            Word::Gamma => "gamma",
+            Word::Lgamma => "lgamma",
+            Word::Fact => "fact",
+            Word::Sign => "sign",
+            Word::Isnan => "isnan",
+            Word::Isinf => "isinf",
+            Word::Isfinite => "isfinite",
+            Word::Pow => "pow",
+            Word::Log => "log",
+            Word::Hypot => "hypot",
+            Word::Atan2 => "atan2",
+            Word::Atan2d => "atan2d",
+            Word::Mod => "mod",
+            Word::Max => "max",
+            Word::Min => "min",
+            Word::Logaddexp => "logaddexp",
+            Word::Gcd => "gcd",
+            Word::Lcm => "lcm",
+            Word::Ncr => "ncr",
+            Word::Npr => "npr",
+            Word::Copysign => "copysign",
+            Word::Nthroot => "nthroot",
+            Word::Clamp => "clamp",
+            Word::Logsumexp => "logsumexp",
+            Word::Avg => "avg",
+            Word::Sum => "sum",
+            Word::Prod => "prod",
+            Word::Rand => "rand",
+        }
+    }
+
+    /// Returns the numbers of arguments accepted by this word.
+    ///
+    /// Constants accept no arguments.
+    pub fn arity(&self) -> Arity {
+        match self {
+            Word::Inf
+            | Word::Nan
+            | Word::Eps
+            | Word::Maxval
+            | Word::Minval
+            | Word::Pi
+            | Word::Tau
+            | Word::E
+            | Word::Phi
+            | Word::Ln2
+            | Word::Ln10
+            | Word::Sqrt2
+            | Word::Invsqrt2
+            | Word::True
+            | Word::False
+            | Word::FloatDigits
+            | Word::FloatMaxexp
+            | Word::SpeedOfLight
+            | Word::Gravitation
+            | Word::Planck
+            | Word::ReducedPlanck
+            | Word::Avogadro
+            | Word::Boltzmann
+            | Word::ElementaryCharge
+            | Word::StandardGravity => Arity::Exactly(0),
+            Word::Pow
+            | Word::Log
+            | Word::Hypot
+            | Word::Atan2
+            | Word::Atan2d
+            | Word::Mod
+            | Word::Logaddexp
+            | Word::Gcd
+            | Word::Lcm
+            | Word::Ncr
+            | Word::Npr
+            | Word::Copysign
+            | Word::Nthroot => Arity::Exactly(2),
+            Word::Max | Word::Min => Arity::AtLeast(2),
+            Word::Round => Arity::Between(1, 2),
+            Word::Clamp => Arity::Exactly(3),
+            Word::Logsumexp | Word::Avg | Word::Sum | Word::Prod => Arity::AtLeast(1),
+            Word::Rand => Arity::OneOf(&[0, 2]),
+            _ => Arity::Exactly(1),
+        }
+    }
+
+    /// Returns the value of this word if it is a constant, such as `pi`, as listed by [`constants`](crate::constants)
+    /// and [`physical_constants`](crate::physical_constants).
+    pub fn constant(&self) -> Option<f64> {
+        CONSTANTS
+            .iter()
+            .chain(PHYSICAL_CONSTANTS)
+            .find(|(name, _, _)| *name == self.name())
+            .map(|(_, value, _)| *value)
+    }
+
+    /// Returns true if this word is a physical constant, such as `c`, which is only reserved when enabled.
+    pub fn is_physical_constant(&self) -> bool {
+        PHYSICAL_CONSTANTS
+            .iter()
+            .any(|(name, _, _)| *name == self.name())
+    }
+
+    /// Check that this word accepts the given number of arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the number of arguments does not match [`Word::arity`].
+    pub fn check_arity(&self, count: usize) -> Result<(), CalcError> {
+        let arity = self.arity();
+        if !arity.accepts(count) {
+            return Err(CalcError::new(
+                &format!(
+                    "{} expects {} argument(s), got {}",
+                    self.name(),
+                    arity,
+                    count
+                ),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns a description of the domain of the function, if it is restricted.
+    ///
+    /// Functions with a restricted domain may produce non-finite results for finite inputs.
+    /// In strict mode, the interpreter uses this description to report a domain error instead.
+    pub fn domain(&self) -> Option<&'static str> {
+        match self {
+            Word::Sqrt => Some("sqrt(x) requires x >= 0"),
+            Word::Log2 => Some("log2(x) requires x > 0"),
+            Word::Log10 => Some("log10(x) requires x > 0"),
+            Word::Ln => Some("ln(x) requires x > 0"),
+            Word::Asin => Some("asin(x) requires -1 <= x <= 1"),
+            Word::Acos => Some("acos(x) requires -1 <= x <= 1"),
+            Word::Csc => Some("csc(x) requires x != 0"),
+            Word::Cot => Some("cot(x) requires x != 0"),
+            Word::Asec => Some("asec(x) requires |x| >= 1"),
+            Word::Acsc => Some("acsc(x) requires |x| >= 1"),
+            Word::Asind => Some("asind(x) requires -1 <= x <= 1"),
+            Word::Acosd => Some("acosd(x) requires -1 <= x <= 1"),
+            Word::Csch => Some("csch(x) requires x != 0"),
+            Word::Coth => Some("coth(x) requires x != 0"),
+            Word::Acosh => Some("acosh(x) requires x >= 1"),
+            Word::Atanh => Some("atanh(x) requires -1 < x < 1"),
+            Word::Asech => Some("asech(x) requires 0 < x <= 1"),
+            Word::Acsch => Some("acsch(x) requires x != 0"),
+            Word::Acoth => Some("acoth(x) requires |x| > 1"),
+            _ => None,
+        }
+    }
+
+    /// Converts two arguments to integers for a word that requires non-negative integers, such as `gcd`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if either argument is negative, not an integer,
+    /// or larger than `2^53`, above which not every integer is exactly representable.
+    fn integer_arguments(&self, args: &[f64]) -> Result<(u64, u64), CalcError> {
+        let convert = |x: f64| {
+            if x >= 0.0 && x.fract() == 0.0 && x <= math::MAX_EXACT_INTEGER {
+                Ok(x as u64)
+            } else {
+                Err(CalcError::new(
+                    &format!("{} requires non-negative integers up to 2^53", self.name()),
+                    None,
+                ))
+            }
+        };
+        Ok((convert(args[0])?, convert(args[1])?))
+    }
+
+    /// Converts the arguments of `ncr` or `npr` to integers `n` and `k`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if either argument is not a non-negative integer up to `2^53`, or if `k > n`.
+    fn choice_arguments(&self, args: &[f64]) -> Result<(u64, u64), CalcError> {
+        let (n, k) = self.integer_arguments(args)?;
+        if k > n {
+            return Err(CalcError::new(
+                &format!("{}(n, k) requires k <= n", self.name()),
+                None,
+            ));
+        }
+        Ok((n, k))
+    }
+
+    /// Evaluates this word with the given arguments.
+    ///
+    /// Constants are evaluated with no arguments and return their value.
+    /// This is the same evaluation the interpreter performs for keywords.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if the number of arguments does not match [`Word::arity`],
+    /// or for `rand`, which needs the calculator's random number generator.
+    /// If `strict` is true, a [`CalcError`] is also returned if finite arguments
+    /// produce a non-finite result for a function with a restricted [`Word::domain`].
+    pub fn apply(&self, args: &[f64], strict: bool) -> Result<f64, CalcError> {
+        self.check_arity(args.len())?;
+
+        let result = match self {
+            Word::Inf
+            | Word::Nan
+            | Word::Eps
+            | Word::Maxval
+            | Word::Minval
+            | Word::Pi
+            | Word::Tau
+            | Word::E
+            | Word::Phi
+            | Word::Ln2
+            | Word::Ln10
+            | Word::Sqrt2
+            | Word::Invsqrt2
+            | Word::True
+            | Word::False
+            | Word::FloatDigits
+            | Word::FloatMaxexp
+            | Word::SpeedOfLight
+            | Word::Gravitation
+            | Word::Planck
+            | Word::ReducedPlanck
+            | Word::Avogadro
+            | Word::Boltzmann
+            | Word::ElementaryCharge
+            | Word::StandardGravity => self.constant().expect("constants are listed in CONSTANTS"),
+
+            Word::Sqrt => args[0].sqrt(),
+            Word::Cbrt => args[0].cbrt(),
+            Word::Exp => args[0].exp(),
+            Word::Log2 => args[0].log2(),
+            Word::Log10 => args[0].log10(),
+            Word::Ln => args[0].ln(),
+            Word::Sin => args[0].sin(),
+            Word::Cos => args[0].cos(),
+            Word::Tan => args[0].tan(),
+            Word::Asin => args[0].asin(),
+            Word::Acos => args[0].acos(),
+            Word::Atan => args[0].atan(),
+            Word::Sec => args[0].cos().recip(),
+            Word::Csc => args[0].sin().recip(),
+            Word::Cot => {
+                let (sin, cos) = args[0].sin_cos();
+                cos / sin
+            }
+            Word::Asec => args[0].recip().acos(),
+            Word::Acsc => args[0].recip().asin(),
+            Word::Acot => args[0].recip().atan(),
+            Word::Sind => math::sin_cos_degrees(args[0]).0,
+            Word::Cosd => math::sin_cos_degrees(args[0]).1,
+            Word::Tand => math::tan_degrees(args[0]),
+            Word::Asind => math::snap_degrees(args[0].asin().to_degrees()),
+            Word::Acosd => math::snap_degrees(args[0].acos().to_degrees()),
+            Word::Atand => math::snap_degrees(args[0].atan().to_degrees()),
+            Word::Sinh => args[0].sinh(),
+            Word::Cosh => args[0].cosh(),
+            Word::Tanh => args[0].tanh(),
+            Word::Sech => args[0].cosh().recip(),
+            Word::Csch => args[0].sinh().recip(),
+            Word::Coth => args[0].tanh().recip(),
+            Word::Asinh => args[0].asinh(),
+            Word::Acosh => args[0].acosh(),
+            Word::Atanh => args[0].atanh(),
+            Word::Asech => args[0].recip().acosh(),
+            Word::Acsch => args[0].recip().asinh(),
+            Word::Acoth => args[0].recip().atanh(),
+            Word::Rad => args[0].to_radians(),
+            Word::Deg => args[0].to_degrees(),
+            Word::Abs => args[0].abs(),
+            Word::Floor => args[0].floor(),
+            Word::Ceil => args[0].ceil(),
+            Word::Trunc => args[0].trunc(),
+            Word::Round => match args {
+                [x, digits] => math::round_digits(*x, *digits)?,
+                _ => args[0].round(),
+            },
+            Word::Ulp => math::ulp(args[0]),
+            Word::Gamma => math::gamma(args[0]),
+            Word::Lgamma => math::ln_gamma(args[0]),
+            Word::Fact => math::factorial(args[0])?,
+            Word::Sign => match args[0] {
+                x if x > 0.0 => 1.0,
+                x if x < 0.0 => -1.0,
+                // Matches both zeros
+                0.0 => 0.0,
+                x => x,
+            },
+            Word::Isnan => f64::from(u8::from(args[0].is_nan())),
+            Word::Isinf => f64::from(u8::from(args[0].is_infinite())),
+            Word::Isfinite => f64::from(u8::from(args[0].is_finite())),
+
+            Word::Pow => args[0].powf(args[1]),
+            Word::Log => args[0].log(args[1]),
+            Word::Hypot => args[0].hypot(args[1]),
+            Word::Atan2 => args[0].atan2(args[1]),
+            Word::Atan2d => math::snap_degrees(args[0].atan2(args[1]).to_degrees()),
+            Word::Mod => args[0] % args[1],
+            // Folding from NaN ignores NaN arguments unless all of them are NaN, like f64::max
+            Word::Max => args.iter().copied().fold(f64::NAN, f64::max),
+            Word::Min => args.iter().copied().fold(f64::NAN, f64::min),
+            Word::Logaddexp | Word::Logsumexp => math::log_sum_exp(args),
+            Word::Avg => math::mean(args),
+            Word::Sum => math::compensated_sum(args.iter().copied()),
+            Word::Prod => args.iter().product(),
+            Word::Rand => {
+                return Err(CalcError::new(
+                    "rand can only be evaluated by a calculator",
+                    None,
+                ))
+            }
+            Word::Copysign => args[0].copysign(args[1]),
+            Word::Nthroot => math::nth_root(args[0], args[1])?,
+            Word::Clamp => match (args[0], args[1], args[2]) {
+                (_, lo, hi) if lo > hi => {
+                    return Err(CalcError::new("clamp(x, lo, hi) requires lo <= hi", None))
+                }
+                // f64::clamp panics on NaN bounds
+                (_, lo, hi) if lo.is_nan() || hi.is_nan() => f64::NAN,
+                (x, lo, hi) => x.clamp(lo, hi),
+            },
+            Word::Gcd => {
+                let (a, b) = self.integer_arguments(args)?;
+                math::gcd(a, b) as f64
+            }
+            Word::Lcm => {
+                let (a, b) = self.integer_arguments(args)?;
+                match math::gcd(a, b) {
+                    0 => 0.0,
+                    d => (a / d).checked_mul(b).map_or(f64::INFINITY, |m| m as f64),
+                }
+            }
+            Word::Ncr => {
+                let (n, k) = self.choice_arguments(args)?;
+                math::combinations(n, k)
+            }
+            Word::Npr => {
+                let (n, k) = self.choice_arguments(args)?;
+                math::permutations(n, k)
+            }
+        };
+
+        self.check_domain(args, result, strict)
+    }
+
+    /// Evaluates this word with angles in degrees instead of radians.
+    ///
+    /// Forward trigonometric functions such as `sin` and `sec` take their argument in degrees,
+    /// and inverse functions such as `asin`, `asec`, and `atan2` return their result in degrees.
+    /// They are computed like `sind` and `asind`, so special angles give exact results.
+    /// Any other word is evaluated as with [`Word::apply`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] in the same cases as [`Word::apply`].
+    pub(crate) fn apply_degrees(&self, args: &[f64], strict: bool) -> Result<f64, CalcError> {
+        self.check_arity(args.len())?;
+        let result = match self {
+            Word::Sin => math::sin_cos_degrees(args[0]).0,
+            Word::Cos => math::sin_cos_degrees(args[0]).1,
+            Word::Tan => math::tan_degrees(args[0]),
+            Word::Sec => math::sin_cos_degrees(args[0]).1.recip(),
+            Word::Csc => math::sin_cos_degrees(args[0]).0.recip(),
+            Word::Cot => {
+                let (sin, cos) = math::sin_cos_degrees(args[0]);
+                cos / sin
+            }
+            Word::Asin => math::snap_degrees(args[0].asin().to_degrees()),
+            Word::Acos => math::snap_degrees(args[0].acos().to_degrees()),
+            Word::Atan => math::snap_degrees(args[0].atan().to_degrees()),
+            Word::Asec => math::snap_degrees(args[0].recip().acos().to_degrees()),
+            Word::Acsc => math::snap_degrees(args[0].recip().asin().to_degrees()),
+            Word::Acot => math::snap_degrees(args[0].recip().atan().to_degrees()),
+            Word::Atan2 => math::snap_degrees(args[0].atan2(args[1]).to_degrees()),
+            _ => return self.apply(args, strict),
+        };
+        self.check_domain(args, result, strict)
+    }
+
+    /// Returns the result of this word, or a domain error if strict mode rejects it.
+    ///
+    /// In strict mode, finite arguments that produce a non-finite result are an error
+    /// for a function with a restricted [`Word::domain`].
+    fn check_domain(&self, args: &[f64], result: f64, strict: bool) -> Result<f64, CalcError> {
+        if strict && args.iter().all(|a| a.is_finite()) && !result.is_finite() {
+            if let Some(domain) = self.domain() {
+                return Err(CalcError::new(&format!("Domain error: {}", domain), None));
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Returns the value of a vulgar fraction character, such as `½`.
+///
+/// Returns `None` for any other character, including the fraction characters
+/// `↉` (zero thirds) and `⅟` (a numerator without a denominator).
+fn vulgar_fraction(c: char) -> Option<f64> {
+    let (numerator, denominator) = match c {
+        '½' => (1, 2),
+        '⅓' => (1, 3),
+        '⅔' => (2, 3),
+        '¼' => (1, 4),
+        '¾' => (3, 4),
+        '⅕' => (1, 5),
+        '⅖' => (2, 5),
+        '⅗' => (3, 5),
+        '⅘' => (4, 5),
+        '⅙' => (1, 6),
+        '⅚' => (5, 6),
+        '⅐' => (1, 7),
+        '⅛' => (1, 8),
+        '⅜' => (3, 8),
+        '⅝' => (5, 8),
+        '⅞' => (7, 8),
+        '⅑' => (1, 9),
+        '⅒' => (1, 10),
+        _ => return None,
+    };
+    Some(numerator as f64 / denominator as f64)
+}
+
+/// Returns the radix and name of the integer literal that starts with `0` followed by `prefix`.
+fn radix_prefix(prefix: char) -> Option<(u32, &'static str)> {
+    match prefix {
+        'x' | 'X' => Some((16, "hexadecimal")),
+        'o' | 'O' => Some((8, "octal")),
+        'b' | 'B' => Some((2, "binary")),
+        _ => None,
+    }
+}
+
+/// Converts the digits of an integer in a radix to the nearest f64.
+///
+/// The digits must all be valid in the radix.
+fn integer_value(digits: &str, radix: u32) -> f64 {
+    match u128::from_str_radix(digits, radix) {
+        Ok(n) => n as f64,
+        // Too large for u128, so accumulate as f64 instead
+        Err(_) => digits.chars().fold(0.0, |value, c| {
+            value * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64
+        }),
+    }
+}
+
+/// Check if a number literal has significant digits that were lost when it was parsed.
+///
+/// Literals are rounded to the nearest f64, which has about 17 significant digits.
+/// For example, `12345678901234567890` is parsed as `12345678901234567000`.
+/// Trailing zeros and an exponent do not count as lost digits.
+/// Literals containing a fraction character, such as `1⅓`, never lose precision.
+/// Integer literals with a radix prefix, such as `0x20000000000001`, lose precision if they are
+/// larger than 2^53 and not a multiple of the spacing between nearby f64 values.
+pub fn loses_precision(literal: &str, value: f64) -> bool {
+    // Fractions such as `⅓` are never exact, so they are not reported
+    if literal.chars().any(|c| vulgar_fraction(c).is_some()) {
+        return false;
+    }
+    let mut chars = literal.chars().filter(|c| !is_skippable(*c, true));
+    if let (Some('0'), Some(prefix)) = (chars.next(), chars.next()) {
+        if let Some((radix, _)) = radix_prefix(prefix) {
+            let digits: String = chars.filter(|c| c.is_digit(radix)).collect();
+            return match u128::from_str_radix(&digits, radix) {
+                Ok(n) => value as u128 != n,
+                Err(_) => true,
+            };
+        }
+    }
+
+    /// Get the significant digits of a number written in decimal or scientific notation.
+    fn significant_digits(number: &str) -> String {
+        let mantissa = number
+            .split(['e', 'E', 'd', 'D'])
+            .next()
+            .unwrap_or_default();
+        let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+        digits
+            .trim_start_matches('0')
+            .trim_end_matches('0')
+            .to_string()
+    }
+    value.is_finite() && significant_digits(literal) != significant_digits(&format!("{:e}", value))
 }
 
 /// Enum for the different types of tokens that can be scanned.
 ///
 /// Token types include numbers, operators, and parentheses.
 /// All numbers are represented as f64.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
-    Number(f64),
+    Number(#[cfg_attr(feature = "serde", serde(with = "crate::values::number"))] f64),
     Plus,
     Minus,
     Star,
@@ -70,21 +1022,184 @@ pub enum Token {
     RParen,
     Bar,
     Comma,
+    Bang,
+    Degree,
+    Equal,
+    And,
+    Or,
     Variable(String),
+    Identifier(String),
     Keyword(Word),
 }
+impl Token {
+    /// Returns a stable, compact description of the token, such as `NUMBER 1.5` or `KEYWORD sqrt`.
+    ///
+    /// Unlike the derived `Debug` output, this format does not depend on the names or order of variants.
+    /// Each token is described by an uppercase kind, followed by its value if it has one.
+    /// Numbers use the shortest representation that round-trips, such as `0.1`, `1e300`, or `inf`.
+    pub fn dump(&self) -> String {
+        match self {
+            Token::Number(n) => format!("NUMBER {}", format::format_number(*n, None)),
+            Token::Plus => "PLUS".to_string(),
+            Token::Minus => "MINUS".to_string(),
+            Token::Star => "STAR".to_string(),
+            Token::Slash => "SLASH".to_string(),
+            Token::Caret => "CARET".to_string(),
+            Token::Percent => "PERCENT".to_string(),
+            Token::LParen => "LPAREN".to_string(),
+            Token::RParen => "RPAREN".to_string(),
+            Token::Bar => "BAR".to_string(),
+            Token::Comma => "COMMA".to_string(),
+            Token::Bang => "BANG".to_string(),
+            Token::Degree => "DEGREE".to_string(),
+            Token::Equal => "EQUAL".to_string(),
+            Token::And => "AND".to_string(),
+            Token::Or => "OR".to_string(),
+            Token::Variable(name) => format!("VARIABLE {}", name),
+            Token::Identifier(name) => format!("IDENTIFIER {}", name),
+            Token::Keyword(w) => format!("KEYWORD {}", w.name()),
+        }
+    }
+}
+
+/// A range of characters in the input string.
+///
+/// The range starts at the character index `start` and ends before the character index `end`.
+/// Character indices are suitable for aligning a caret under the input in a terminal.
+/// The same range is also given in bytes by `byte_start` and `byte_end`,
+/// which are suitable for slicing the input, such as `&input[span.byte_start..span.byte_end]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
 
 /// A scanner used to help convert an input string into a vector of tokens.
 ///
 /// First, create a new scanner with [`Scanner::new`], then call [`Scanner::scan`] to convert the input string into tokens.
 pub struct Scanner<'a> {
     iter: Peekable<Chars<'a>>,
+    position: usize,
+    byte_position: usize,
+    lenient: bool,
+    single_letter_constants: bool,
+    physical_constants: bool,
+    max_literal_length: usize,
+    d_exponent: bool,
+    case_insensitive_keywords: bool,
 }
 impl<'a> Scanner<'a> {
     /// Create a new scanner with the input string.
     pub fn new(input: &'a str) -> Self {
         Self {
             iter: input.chars().peekable(),
+            position: 0,
+            byte_position: 0,
+            lenient: false,
+            single_letter_constants: true,
+            physical_constants: false,
+            max_literal_length: DEFAULT_MAX_LITERAL_LENGTH,
+            d_exponent: false,
+            case_insensitive_keywords: false,
+        }
+    }
+
+    /// Set whether the scanner is lenient with invisible formatting characters.
+    ///
+    /// The byte order mark and zero-width characters are always skipped.
+    /// When lenient, other invisible formatting characters, such as directional marks
+    /// and soft hyphens, are skipped as well.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set whether single-letter constants, such as `e`, are scanned as keywords.
+    ///
+    /// When disabled, single-letter constants are scanned as identifiers instead.
+    /// Single-letter constants are enabled by default.
+    pub fn single_letter_constants(mut self, enabled: bool) -> Self {
+        self.single_letter_constants = enabled;
+        self
+    }
+
+    /// Set whether physical constants, such as `c` and `hbar`, are scanned as keywords.
+    ///
+    /// When disabled, their names are scanned as identifiers instead, so they can be used as variables.
+    /// The single-letter names `c`, `h`, and `G` also require single-letter constants.
+    /// Physical constants are disabled by default.
+    pub fn physical_constants(mut self, enabled: bool) -> Self {
+        self.physical_constants = enabled;
+        self
+    }
+
+    /// Set the maximum number of characters in a number literal.
+    ///
+    /// Every character of the literal counts, including the decimal point, exponent, radix prefix, and digit separators.
+    ///
+    /// Scanning stops with an error as soon as a literal exceeds this length,
+    /// so very long literals do not take long to scan.
+    pub fn max_literal_length(mut self, max_literal_length: usize) -> Self {
+        self.max_literal_length = max_literal_length;
+        self
+    }
+
+    /// Set whether `d` and `D` are accepted as exponent markers, as in `1.5d-3`.
+    ///
+    /// This notation is written by Fortran and related tools, and means the same as `1.5e-3`.
+    /// When enabled, a `d` directly after a number that is not followed by exponent digits
+    /// or by other letters, such as in `1d`, is an error.
+    /// Words starting with `d`, such as `deg`, are not affected.
+    /// Disabled by default.
+    pub fn d_exponent(mut self, enabled: bool) -> Self {
+        self.d_exponent = enabled;
+        self
+    }
+
+    /// Set whether reserved words are recognized regardless of case, as in `SQRT(9)` or `Pi`.
+    ///
+    /// Identifiers that are not reserved words keep their original case.
+    /// Disabled by default, so reserved words must be written as listed, which is lowercase except for `G`.
+    pub fn case_insensitive_keywords(mut self, enabled: bool) -> Self {
+        self.case_insensitive_keywords = enabled;
+        self
+    }
+
+    /// Consume the next character from the input, keeping track of the position.
+    fn advance(&mut self) -> Option<char> {
+        let c = self.iter.next();
+        if let Some(c) = c {
+            self.position += 1;
+            self.byte_position += c.len_utf8();
+        }
+        c
+    }
+
+    /// Consume a comment, which runs from `#` or `//` to the end of the line or input.
+    ///
+    /// The newline that ends the comment is consumed as whitespace.
+    fn skip_comment(&mut self) {
+        while self.advance().is_some_and(|c| c != '\n') {}
+    }
+
+    /// Returns an empty span at the current position.
+    fn mark(&self) -> Span {
+        Span {
+            start: self.position,
+            end: self.position,
+            byte_start: self.byte_position,
+            byte_end: self.byte_position,
+        }
+    }
+
+    /// Returns the span from the start of a mark to the current position.
+    fn span_from(&self, mark: Span) -> Span {
+        Span {
+            end: self.position,
+            byte_end: self.byte_position,
+            ..mark
         }
     }
 
@@ -95,85 +1210,151 @@ impl<'a> Scanner<'a> {
     /// # Errors
     ///
     /// Returns a [`CalcError`] if an invalid character is encountered, or if a number cannot be parsed.
-    pub fn scan(mut self) -> Result<Vec<Token>, CalcError> {
+    pub fn scan(self) -> Result<Vec<Token>, CalcError> {
+        Ok(self.scan_with_spans()?.0)
+    }
+
+    /// Scans the input string and returns a vector of tokens along with their spans.
+    ///
+    /// Consumes the Scanner to iterate over the input string.
+    /// The span at each index is the range of characters in the input that produced the token at the same index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if an invalid character is encountered, or if a number cannot be parsed.
+    pub fn scan_with_spans(mut self) -> Result<(Vec<Token>, Vec<Span>), CalcError> {
         let mut tokens = Vec::new();
+        let mut spans = Vec::new();
 
         loop {
+            let start = self.mark();
+            let comment = self.iter.clone().take(2).eq(['/', '/']);
             match self.iter.peek() {
-                None => return Ok(tokens),
+                None => return Ok((tokens, spans)),
                 Some(c) => match c {
-                    ' ' => {
-                        self.iter.next();
+                    ' ' | '\t' | '\r' | '\n' | '\u{00A0}' => {
+                        self.advance();
+                    }
+                    c if is_skippable(*c, self.lenient) => {
+                        self.advance();
                     }
                     '+' => {
                         tokens.push(Token::Plus);
-                        self.iter.next();
+                        self.advance();
                     }
-                    '-' => {
+                    '-' | '−' => {
                         tokens.push(Token::Minus);
-                        self.iter.next();
+                        self.advance();
                     }
-                    '*' => {
+                    '*' | '×' => {
                         tokens.push(Token::Star);
-                        self.iter.next();
+                        self.advance();
+                    }
+                    '#' => {
+                        self.skip_comment();
+                    }
+                    '/' if comment => {
+                        self.skip_comment();
                     }
-                    '/' => {
+                    '/' | '÷' => {
                         tokens.push(Token::Slash);
-                        self.iter.next();
+                        self.advance();
                     }
                     '%' => {
                         tokens.push(Token::Percent);
-                        self.iter.next();
+                        self.advance();
                     }
                     '^' => {
                         tokens.push(Token::Caret);
-                        self.iter.next();
+                        self.advance();
                     }
                     '(' => {
                         tokens.push(Token::LParen);
-                        self.iter.next();
+                        self.advance();
                     }
                     ')' => {
                         tokens.push(Token::RParen);
-                        self.iter.next();
+                        self.advance();
                     }
                     '|' => {
                         tokens.push(Token::Bar);
-                        self.iter.next();
+                        self.advance();
                     }
                     ',' => {
                         tokens.push(Token::Comma);
-                        self.iter.next();
+                        self.advance();
+                    }
+                    '!' => {
+                        tokens.push(Token::Bang);
+                        self.advance();
+                    }
+                    '°' => {
+                        tokens.push(Token::Degree);
+                        self.advance();
+                    }
+                    '=' => {
+                        tokens.push(Token::Equal);
+                        self.advance();
+                    }
+                    &c if vulgar_fraction(c).is_some() => {
+                        tokens.push(Token::Number(vulgar_fraction(c).unwrap_or_default()));
+                        self.advance();
+                    }
+                    '↉' | '⅟' => {
+                        self.advance();
+                        return Err(CalcError::new("Unsupported fraction character", None)
+                            .with_kind(ErrorKind::Scan)
+                            .with_span(self.span_from(start)));
                     }
                     '√' => {
                         tokens.push(Token::Keyword(Word::Sqrt));
-                        self.iter.next();
+                        self.advance();
                     }
                     'π' => {
                         tokens.push(Token::Keyword(Word::Pi));
-                        self.iter.next();
+                        self.advance();
                     }
                     'τ' => {
                         tokens.push(Token::Keyword(Word::Tau));
-                        self.iter.next();
+                        self.advance();
                     }
                     'ϕ' | 'φ' => {
                         tokens.push(Token::Keyword(Word::Phi));
-                        self.iter.next();
+                        self.advance();
                     }
                     'a'..='z' | 'A'..='Z' => {
-                        tokens.push(Token::Keyword(self.scan_word()?));
+                        tokens.push(self.scan_word());
                     }
                     '$' => {
-                        self.iter.next();
                         tokens.push(Token::Variable(self.scan_variable()?));
                     }
                     '0'..='9' => {
                         tokens.push(Token::Number(self.scan_number()?));
                     }
-                    _ => return Err(CalcError::new("Invalid character", None)),
+                    '.' => {
+                        let mut lookahead = self.iter.clone().skip(1);
+                        if !lookahead.next().is_some_and(|c| c.is_ascii_digit()) {
+                            self.advance();
+                            return Err(CalcError::new(
+                                "Decimal point must be followed by a digit",
+                                None,
+                            )
+                            .with_kind(ErrorKind::Scan)
+                            .with_span(self.span_from(start)));
+                        }
+                        tokens.push(Token::Number(self.scan_number()?));
+                    }
+                    _ => {
+                        self.advance();
+                        return Err(CalcError::new("Invalid character", None)
+                            .with_kind(ErrorKind::Scan)
+                            .with_span(self.span_from(start)));
+                    }
                 },
             }
+            if spans.len() < tokens.len() {
+                spans.push(self.span_from(start));
+            }
         }
     }
 
@@ -183,43 +1364,222 @@ impl<'a> Scanner<'a> {
     /// then calls [`str::parse`](https://doc.rust-lang.org/std/primitive.str.html#method.parse) to convert the string to an f64.
     /// The behavior of `parse` is based on [`f64::from_str`](https://doc.rust-lang.org/std/primitive.f64.html#method.from_str).
     /// Number characters include digits, a decimal point, and 'E' or 'e' for scientific notation.
+    /// A number may start with a decimal point, as in `.5`, if a digit follows it,
+    /// and may end with one, as in `5.`.
     /// If 'E' or 'e', any '+' or '-' that follows is also consumed as part of the number.
+    /// An 'E' or 'e' that stands alone, as in `1e` or `1e+`, is an exponent with no digits, which is an error.
+    /// An 'E' or 'e' that starts a word is not part of the number, so `2exp(1)` is `2` followed by `exp(1)`.
+    /// Invisible characters that the scanner skips are also skipped inside the number.
+    /// Digits may be separated by underscores, as in `1_000_000` or `3.141_592`, which are ignored.
+    /// Each underscore must be between two digits, so `1_`, `1__0`, `1_.5`, and `1_e5` are errors.
+    /// A fraction character, such as `½`, directly after an integer is added to it, so `1½` is 1.5.
+    /// Numbers starting with a radix prefix, such as `0x`, are scanned with [`Scanner::scan_integer`].
     ///
     /// # Errors
     ///
     /// If the number cannot be parsed, a [`CalcError`] is returned containing the [`std::num::ParseFloatError`].
+    /// An error is also returned if an exponent has no digits or a number has a second exponent, as in `1e+5e2`,
+    /// if an underscore is not between two digits,
+    /// if a number has a second decimal point, as in `1.2.3` or `5..2`, if the exponent has a decimal point,
+    /// or if a fraction character follows a number with a decimal point or exponent, as in `2.5½` or `1e3½`.
+    /// The error spans the number up to and including the offending character.
     fn scan_number(&mut self) -> Result<f64, CalcError> {
+        let mut lookahead = self.iter.clone();
+        if let (Some('0'), Some(prefix)) = (lookahead.next(), lookahead.next()) {
+            if let Some((radix, name)) = radix_prefix(prefix) {
+                return self.scan_integer(radix, name);
+            }
+        }
+
+        let start = self.mark();
         let mut number = String::new();
-        loop {
-            match self.iter.peek() {
-                None => break,
-                Some(c) => match c {
-                    '0'..='9' | '.' => {
-                        number.push(*c);
-                        self.iter.next();
-                    }
-                    'E' | 'e' => {
-                        number.push(*c);
-                        self.iter.next();
-                        match self.iter.peek() {
-                            Some(&'+') | Some(&'-') => {
-                                number.push(self.iter.next().unwrap());
-                            }
-                            _ => {}
-                        }
+        while let Some(&c) = self.iter.peek() {
+            match c {
+                '.' if number.contains('e') => {
+                    self.advance();
+                    return Err(CalcError::new("Exponent must be an integer", None)
+                        .with_kind(ErrorKind::Scan)
+                        .with_span(self.span_from(start)));
+                }
+                '.' if number.contains('.') => {
+                    self.advance();
+                    return Err(
+                        CalcError::new("Number has more than one decimal point", None)
+                            .with_kind(ErrorKind::Scan)
+                            .with_span(self.span_from(start)),
+                    );
+                }
+                '0'..='9' | '.' => {
+                    number.push(c);
+                    self.advance();
+                }
+                c if is_skippable(c, self.lenient) => {
+                    self.advance();
+                }
+                '_' => {
+                    let after_digit = number.ends_with(|c: char| c.is_ascii_digit());
+                    self.advance();
+                    if !(after_digit && self.iter.peek().is_some_and(|c| c.is_ascii_digit())) {
+                        return Err(
+                            CalcError::new("Digit separator must be between digits", None)
+                                .with_kind(ErrorKind::Scan)
+                                .with_span(self.span_from(start)),
+                        );
                     }
-                    _ => break,
-                },
+                }
+                'E' | 'e'
+                    if number.contains('e')
+                        && (self.exponent_follows() || self.marker_ends_word()) =>
+                {
+                    self.advance();
+                    return Err(CalcError::new("Number has more than one exponent", None)
+                        .with_kind(ErrorKind::Scan)
+                        .with_span(self.span_from(start)));
+                }
+                'E' | 'e' if self.exponent_follows() => {
+                    number.push(c);
+                    self.advance();
+                    self.scan_exponent_sign(&mut number);
+                }
+                'E' | 'e' if self.marker_ends_word() => {
+                    self.advance();
+                    return Err(CalcError::new("Exponent has no digits", None)
+                        .with_kind(ErrorKind::Scan)
+                        .with_span(self.span_from(start)));
+                }
+                'D' | 'd' if self.d_exponent && self.exponent_follows() => {
+                    number.push('e');
+                    self.advance();
+                    self.scan_exponent_sign(&mut number);
+                }
+                'D' | 'd' if self.d_exponent && self.marker_ends_word() => {
+                    self.advance();
+                    return Err(CalcError::new("Exponent has no digits", None)
+                        .with_kind(ErrorKind::Scan)
+                        .with_span(self.span_from(start)));
+                }
+                _ => break,
+            }
+            self.check_literal_length(start)?;
+        }
+
+        let n: f64 = match number.parse() {
+            Ok(n) => n,
+            Err(err) => {
+                return Err(CalcError::new("Failed to parse number", Some(err.into()))
+                    .with_kind(ErrorKind::Scan)
+                    .with_span(self.span_from(start)))
+            }
+        };
+        // A fraction character directly after an integer is a mixed number, so `1½` is 1.5
+        match self.iter.peek().and_then(|&c| vulgar_fraction(c)) {
+            Some(_) if number.contains(['.', 'e']) => {
+                self.advance();
+                Err(
+                    CalcError::new("Fraction character must follow an integer", None)
+                        .with_kind(ErrorKind::Scan)
+                        .with_span(self.span_from(start)),
+                )
+            }
+            Some(fraction) => {
+                self.advance();
+                Ok(n + fraction)
+            }
+            None => Ok(n),
+        }
+    }
+
+    /// Scans an integer written with a radix prefix, such as `0xFF`, and converts it to an f64.
+    ///
+    /// The next two characters should be the prefix.
+    /// Digits may be separated by underscores, which are ignored.
+    /// Integers too large to be represented exactly are rounded to the nearest f64.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if no digits follow the prefix,
+    /// or if a decimal digit that is not valid in the radix follows the prefix, such as `0b2`.
+    fn scan_integer(&mut self, radix: u32, name: &str) -> Result<f64, CalcError> {
+        let start = self.mark();
+        self.advance();
+        self.advance();
+        let mut digits = String::new();
+        while let Some(&c) = self.iter.peek() {
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c.is_ascii_digit() {
+                self.advance();
+                return Err(CalcError::new(
+                    &format!("Invalid digit in {} literal: {}", name, c),
+                    None,
+                )
+                .with_kind(ErrorKind::Scan)
+                .with_span(self.span_from(start)));
+            } else if c != '_' && !is_skippable(c, self.lenient) {
+                break;
             }
+            self.advance();
+            self.check_literal_length(start)?;
+        }
+
+        if digits.is_empty() {
+            return Err(
+                CalcError::new(&format!("Missing digits in {} literal", name), None)
+                    .with_kind(ErrorKind::Scan)
+                    .with_span(self.span_from(start)),
+            );
         }
+        Ok(integer_value(&digits, radix))
+    }
+
+    /// Check that the number literal starting at `start` is not longer than the maximum literal length.
+    ///
+    /// Every character scanned counts toward the length, including digit separators and skipped invisible characters.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] spanning the literal so far if it is too long.
+    fn check_literal_length(&self, start: Span) -> Result<(), CalcError> {
+        if self.position - start.start > self.max_literal_length {
+            return Err(CalcError::new("Number literal is too long", None)
+                .with_kind(ErrorKind::Scan)
+                .with_span(self.span_from(start)));
+        }
+        Ok(())
+    }
 
-        match number.parse() {
-            Ok(n) => Ok(n),
-            Err(err) => Err(CalcError::new("Failed to parse number", Some(err.into()))),
+    /// Consume the optional sign of an exponent, after the exponent marker.
+    fn scan_exponent_sign(&mut self, number: &mut String) {
+        if let Some(&c @ ('+' | '-')) = self.iter.peek() {
+            number.push(c);
+            self.advance();
         }
     }
 
-    /// Scans a variable from the input iterator.
+    /// Check if the next character is a letter that stands alone rather than starting a word.
+    ///
+    /// The letter stands alone if it is not followed by a letter, digit, or underscore.
+    fn marker_ends_word(&self) -> bool {
+        let mut lookahead = self.iter.clone().skip(1);
+        !lookahead
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Check if the next characters form the exponent of a number in scientific notation.
+    ///
+    /// The next character should be an exponent marker, such as 'E' or 'e'.
+    /// Returns true if it is followed by a digit, optionally after a '+' or '-'.
+    fn exponent_follows(&self) -> bool {
+        let mut lookahead = self.iter.clone().skip(1);
+        match lookahead.next() {
+            Some('+') | Some('-') => lookahead.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    /// Scans a variable from the input iterator, starting with the '$'.
     ///
     /// All variables must start with a '$' and can contain any alphanumeric character.
     /// The exact characters that are accepted can be expressed as `[0-9a-zA-Z_]` in regex.
@@ -231,6 +1591,8 @@ impl<'a> Scanner<'a> {
     /// Returns a [`CalcError`] if there were no alphanumeric characters following the '$'.
     /// For example, scanning `$v#` will not return an error immediately, but `$#` will.
     fn scan_variable(&mut self) -> Result<String, CalcError> {
+        let start = self.mark();
+        self.advance();
         let mut variable = String::from("$");
         let mut has_char = false;
 
@@ -241,7 +1603,7 @@ impl<'a> Scanner<'a> {
                     '0'..='9' | 'a'..='z' | 'A'..='Z' | '_' => {
                         variable.push(*c);
                         has_char = true;
-                        self.iter.next();
+                        self.advance();
                     }
                     _ => break,
                 },
@@ -249,87 +1611,121 @@ impl<'a> Scanner<'a> {
         }
 
         if !has_char {
-            return Err(CalcError::new("Invalid variable", None));
+            return Err(CalcError::new("Invalid variable", None)
+                .with_kind(ErrorKind::Scan)
+                .with_span(self.span_from(start)));
         }
 
         Ok(variable)
     }
 
-    /// Scans a reserved word from the input iterator.
+    /// Scans a reserved word or identifier from the input iterator.
     ///
-    /// Returns a [`Word`] enum representing the reserved word.
+    /// Returns a [`Token::Keyword`] if the word is a reserved word.
     /// Reserved words include special functions like `sqrt`.
     /// Reserved words also include constants like `pi` and special values like `inf`.
-    /// This function consumes all characters that could be part of the keyword.
-    /// This happens to include uppercase letters despite all reserved words being lowercase.
-    ///
-    /// # Errors
-    ///
-    /// If an unknown keyword is encountered, a [`CalcError`] is returned.
-    fn scan_word(&mut self) -> Result<Word, CalcError> {
-        let mut keyword = String::new();
+    /// The constant `e` can also be written as `euler`, which is scanned even if single-letter constants are disabled.
+    /// The logical operators `and` and `or` are scanned as [`Token::And`] and [`Token::Or`].
+    /// The word `ans` is scanned as the variable `$ans`, which holds the last result.
+    /// Any other word is returned as a [`Token::Identifier`], which is resolved later by the interpreter.
+    /// This function consumes all characters that could be part of the word.
+    /// This includes uppercase letters, although the only reserved word with one is the physical constant `G`.
+    /// Physical constants are only reserved words if enabled.
+    /// If case-insensitive keywords are enabled, the word is compared to reserved words ignoring case,
+    /// but an identifier keeps its original case.
+    /// Digits and underscores are also consumed after the first letter so that words like `log2` can be scanned.
+    fn scan_word(&mut self) -> Token {
+        let mut word = String::new();
         loop {
             match self.iter.peek() {
                 None => break,
                 Some(c) => match c {
-                    'a'..='z' | 'A'..='Z' => {
-                        keyword.push(*c);
-                        self.iter.next();
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
+                        word.push(*c);
+                        self.advance();
                     }
                     _ => break,
                 },
             }
         }
 
-        match keyword.as_str() {
-            "inf" => Ok(Word::Inf),
-            "pi" => Ok(Word::Pi),
-            "tau" => Ok(Word::Tau),
-            "e" => Ok(Word::E),
-            "phi" => Ok(Word::Phi),
-
-            "sqrt" => Ok(Word::Sqrt),
-            "cbrt" => Ok(Word::Cbrt),
-            "exp" => Ok(Word::Exp),
-            "log2" => Ok(Word::Log2),
-            "log10" => Ok(Word::Log10),
-            "ln" => Ok(Word::Ln),
-            "sin" => Ok(Word::Sin),
-            "cos" => Ok(Word::Cos),
-            "tan" => Ok(Word::Tan),
-            "asin" => Ok(Word::Asin),
-            "acos" => Ok(Word::Acos),
-            "atan" => Ok(Word::Atan),
-            "sinh" => Ok(Word::Sinh),
-            "cosh" => Ok(Word::Cosh),
-            "tanh" => Ok(Word::Tanh),
-            "asinh" => Ok(Word::Asinh),
-            "acosh" => Ok(Word::Acosh),
-            "atanh" => Ok(Word::Atanh),
-            "rad" => Ok(Word::Rad),
-            "deg" => Ok(Word::Deg),
-            "abs" => Ok(Word::Abs),
-            "floor" => Ok(Word::Floor),
-            "ceil" => Ok(Word::Ceil),
-            "trunc" => Ok(Word::Trunc),
-            "round" => Ok(Word::Round),
-
-            "pow" => Ok(Word::Pow),
-            "log" => Ok(Word::Log),
-            "hypot" => Ok(Word::Hypot),
-            "atan2" => Ok(Word::Atan2),
-            "mod" => Ok(Word::Mod),
-            "max" => Ok(Word::Max),
-            "min" => Ok(Word::Min),
-            _ => Err(CalcError::new("Unknown keyword", None)),
+        let key = match self.case_insensitive_keywords {
+            true => word.to_ascii_lowercase(),
+            false => word.clone(),
+        };
+        match key.as_str() {
+            "euler" => return Token::Keyword(Word::E),
+            "and" => return Token::And,
+            "or" => return Token::Or,
+            "ans" => return Token::Variable("$ans".to_string()),
+            _ => {}
+        }
+        let reserved = Word::ALL
+            .iter()
+            .find(|w| match self.case_insensitive_keywords {
+                true => w.name().eq_ignore_ascii_case(&word),
+                false => w.name() == word,
+            });
+        match reserved {
+            Some(w) if w.is_physical_constant() && !self.physical_constants => {
+                Token::Identifier(word)
+            }
+            Some(w) if w.name().len() > 1 || self.single_letter_constants => {
+                Token::Keyword(w.clone())
+            }
+            _ => Token::Identifier(word),
         }
     }
 }
 
+/// Check if a name is scanned as a single identifier, such as `radius`.
+///
+/// Reserved words, such as `pi`, are not identifiers.
+pub fn is_identifier(name: &str) -> bool {
+    matches!(Scanner::new(name).scan().as_deref(), Ok([Token::Identifier(n)]) if n == name)
+}
+
+/// Check if a name is scanned as a single variable, such as `$health`.
+pub fn is_variable(name: &str) -> bool {
+    matches!(Scanner::new(name).scan().as_deref(), Ok([Token::Variable(n)]) if n == name)
+}
+
+/// Returns a line with any comment removed.
+///
+/// A comment starts at `#` or `//` and runs to the end of the line, as in the scanner.
+pub fn strip_comment(line: &str) -> &str {
+    let end = [line.find('#'), line.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+/// Check if a character is invisible and should be skipped by the scanner.
+///
+/// The byte order mark and zero-width characters are always skippable.
+/// If `lenient` is true, other invisible formatting characters are skippable as well.
+fn is_skippable(c: char, lenient: bool) -> bool {
+    match c {
+        '\u{FEFF}' | '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' => true,
+        '\u{00AD}'
+        | '\u{061C}'
+        | '\u{180E}'
+        | '\u{200E}'
+        | '\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2061}'..='\u{2064}'
+        | '\u{2066}'..='\u{206F}' => lenient,
+        _ => false,
+    }
+}
+
 // MARK: Tests
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f64::consts::PI;
 
     #[test]
     fn test_scan_empty_str() {
@@ -345,6 +1741,46 @@ mod tests {
         let expected = vec![];
         let scanner = Scanner::new(input);
         assert_eq!(scanner.scan().unwrap(), expected);
+
+        let input = "\t1\t+ 2\r\n";
+        let expected = vec![Token::Number(1.0), Token::Plus, Token::Number(2.0)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scan_leading_bom() {
+        let input = "\u{FEFF}1 + 2";
+        let expected = vec![Token::Number(1.0), Token::Plus, Token::Number(2.0)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scan_nbsp() {
+        let input = "1\u{00A0}+\u{00A0}2";
+        let expected = vec![Token::Number(1.0), Token::Plus, Token::Number(2.0)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scan_zero_width_space_in_number() {
+        let input = "12\u{200B}34";
+        let expected = vec![Token::Number(1234.0)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scan_format_char_lenient() {
+        let input = "1 +\u{200E} 2";
+        let scanner = Scanner::new(input);
+        assert!(scanner.scan().is_err());
+
+        let expected = vec![Token::Number(1.0), Token::Plus, Token::Number(2.0)];
+        let scanner = Scanner::new(input).lenient(true);
+        assert_eq!(scanner.scan().unwrap(), expected);
     }
 
     #[test]
@@ -428,8 +1864,16 @@ mod tests {
     }
 
     #[test]
-    fn test_three_terms() {
-        let input = "1 + 2 * 3";
+    fn test_modulo() {
+        let input = "10 % 3";
+        let expected = vec![Token::Number(10.0), Token::Percent, Token::Number(3.0)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_three_terms() {
+        let input = "1 + 2 * 3";
         let expected = vec![
             Token::Number(1.0),
             Token::Plus,
@@ -467,11 +1911,397 @@ mod tests {
 
     #[test]
     fn test_err_invalid_char() {
-        let input = "1 + a";
+        let input = "1 + @";
         let scanner = Scanner::new(input);
         assert!(matches!(scanner.scan(), Err(CalcError { .. })));
     }
 
+    #[test]
+    fn test_identifier() {
+        let input = "1 + cal_2";
+        let expected = vec![
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Identifier(String::from("cal_2")),
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_err_invalid_char_position() {
+        let input = "1 + & 2";
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap_err().position(), Some(4));
+    }
+
+    #[test]
+    fn test_err_invalid_variable_position() {
+        let input = "1 + $ 2";
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap_err().position(), Some(4));
+    }
+
+    #[test]
+    fn test_err_invalid_number_position() {
+        let input = "2 * 1.2.3";
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap_err().position(), Some(4));
+    }
+
+    #[test]
+    fn test_spans() {
+        let input = "sqrt(1.5E-3) + $var*2";
+        let (tokens, spans) = Scanner::new(input).scan_with_spans().unwrap();
+        let expected = vec![
+            (
+                Token::Keyword(Word::Sqrt),
+                Span {
+                    start: 0,
+                    end: 4,
+                    byte_start: 0,
+                    byte_end: 4,
+                },
+            ),
+            (
+                Token::LParen,
+                Span {
+                    start: 4,
+                    end: 5,
+                    byte_start: 4,
+                    byte_end: 5,
+                },
+            ),
+            (
+                Token::Number(1.5E-3),
+                Span {
+                    start: 5,
+                    end: 11,
+                    byte_start: 5,
+                    byte_end: 11,
+                },
+            ),
+            (
+                Token::RParen,
+                Span {
+                    start: 11,
+                    end: 12,
+                    byte_start: 11,
+                    byte_end: 12,
+                },
+            ),
+            (
+                Token::Plus,
+                Span {
+                    start: 13,
+                    end: 14,
+                    byte_start: 13,
+                    byte_end: 14,
+                },
+            ),
+            (
+                Token::Variable(String::from("$var")),
+                Span {
+                    start: 15,
+                    end: 19,
+                    byte_start: 15,
+                    byte_end: 19,
+                },
+            ),
+            (
+                Token::Star,
+                Span {
+                    start: 19,
+                    end: 20,
+                    byte_start: 19,
+                    byte_end: 20,
+                },
+            ),
+            (
+                Token::Number(2.0),
+                Span {
+                    start: 20,
+                    end: 21,
+                    byte_start: 20,
+                    byte_end: 21,
+                },
+            ),
+        ];
+        let actual: Vec<_> = tokens.into_iter().zip(spans).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_spans_unicode() {
+        let input = "π * √4";
+        let (_, spans) = Scanner::new(input).scan_with_spans().unwrap();
+        let expected = vec![
+            Span {
+                start: 0,
+                end: 1,
+                byte_start: 0,
+                byte_end: 2,
+            },
+            Span {
+                start: 2,
+                end: 3,
+                byte_start: 3,
+                byte_end: 4,
+            },
+            Span {
+                start: 4,
+                end: 5,
+                byte_start: 5,
+                byte_end: 8,
+            },
+            Span {
+                start: 5,
+                end: 6,
+                byte_start: 8,
+                byte_end: 9,
+            },
+        ];
+        for span in &spans {
+            assert!(input.is_char_boundary(span.byte_start));
+            assert!(input.is_char_boundary(span.byte_end));
+        }
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn test_err_span() {
+        let input = "1 + 1.2.3";
+        let err = Scanner::new(input).scan().unwrap_err();
+        assert_eq!(
+            err.span(),
+            Some(Span {
+                start: 4,
+                end: 8,
+                byte_start: 4,
+                byte_end: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_decimal_points() {
+        let input = "5. + 1";
+        let expected = vec![Token::Number(5.0), Token::Plus, Token::Number(1.0)];
+        assert_eq!(Scanner::new(input).scan().unwrap(), expected);
+
+        let cases = [("5..2", (0, 3)), ("5.0.1", (0, 4)), ("1.2.3", (0, 4))];
+        for (input, expected) in cases {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert_eq!(err.message(), "Number has more than one decimal point");
+            let span = err.span().unwrap();
+            assert_eq!((span.start, span.end), expected, "{}", input);
+        }
+
+        let err = Scanner::new("1e5.5").scan().unwrap_err();
+        assert_eq!(err.message(), "Exponent must be an integer");
+        assert_eq!(err.span().map(|span| span.end), Some(4));
+    }
+
+    #[test]
+    fn test_err_span_multi_byte() {
+        let input = "π * 2 + 😀";
+        let err = Scanner::new(input).scan().unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start, span.end), (8, 9));
+        assert_eq!((span.byte_start, span.byte_end), (9, 13));
+        assert_eq!(&input[span.byte_start..span.byte_end], "😀");
+
+        let input = "π § 2";
+        let err = Scanner::new(input).scan().unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start, span.end), (2, 3));
+        assert_eq!((span.byte_start, span.byte_end), (3, 5));
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let input = "3 × π ÷ 2 − √(16)";
+        let (tokens, spans) = Scanner::new(input).scan_with_spans().unwrap();
+        let expected = vec![
+            Token::Number(3.0),
+            Token::Star,
+            Token::Keyword(Word::Pi),
+            Token::Slash,
+            Token::Number(2.0),
+            Token::Minus,
+            Token::Keyword(Word::Sqrt),
+            Token::LParen,
+            Token::Number(16.0),
+            Token::RParen,
+        ];
+        assert_eq!(tokens, expected);
+        let minus = spans[5];
+        assert_eq!((minus.start, minus.end), (10, 11));
+        assert_eq!(&input[minus.byte_start..minus.byte_end], "−");
+    }
+
+    #[test]
+    fn test_degree() {
+        let input = "sin(45°)";
+        let expected = vec![
+            Token::Keyword(Word::Sin),
+            Token::LParen,
+            Token::Number(45.0),
+            Token::Degree,
+            Token::RParen,
+        ];
+        assert_eq!(Scanner::new(input).scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_vulgar_fractions() {
+        let input = "1½ + ¼ * 2¾";
+        let expected = vec![
+            Token::Number(1.5),
+            Token::Plus,
+            Token::Number(0.25),
+            Token::Star,
+            Token::Number(2.75),
+        ];
+        assert_eq!(Scanner::new(input).scan().unwrap(), expected);
+
+        let (_, spans) = Scanner::new("1½").scan_with_spans().unwrap();
+        assert_eq!((spans[0].start, spans[0].end), (0, 2));
+        assert!(!loses_precision("1⅓", 1.0 + 1.0 / 3.0));
+
+        let err = Scanner::new("1 + ↉").scan().unwrap_err();
+        assert_eq!(err.message(), "Unsupported fraction character");
+        assert_eq!(err.position(), Some(4));
+
+        for input in ["2.5½", "1e3½", "1.½", "1_0.0¼"] {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert_eq!(err.message(), "Fraction character must follow an integer");
+            assert!(err.is_scan_error());
+            assert_eq!(err.span().unwrap().end, input.chars().count());
+        }
+    }
+
+    #[test]
+    fn test_err_span_full_width_digit() {
+        let input = "√4 + １";
+        let err = Scanner::new(input).scan().unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start, span.end), (5, 6));
+        assert_eq!((span.byte_start, span.byte_end), (7, 10));
+        assert_eq!(&input[span.byte_start..span.byte_end], "１");
+    }
+
+    #[test]
+    fn test_euler() {
+        let input = "euler e";
+        let expected = vec![Token::Keyword(Word::E), Token::Keyword(Word::E)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+
+        let expected = vec![
+            Token::Keyword(Word::E),
+            Token::Identifier(String::from("e")),
+        ];
+        let scanner = Scanner::new(input).single_letter_constants(false);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_number_followed_by_e() {
+        let input = "2exp(1)";
+        let expected = vec![
+            Token::Number(2.0),
+            Token::Keyword(Word::Exp),
+            Token::LParen,
+            Token::Number(1.0),
+            Token::RParen,
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+
+        let input = "2 e";
+        let expected = vec![Token::Number(2.0), Token::Keyword(Word::E)];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        let cases = [("1e5", 1e5), ("1e+5", 1e5), ("1E-5", 1e-5), ("2.5e0", 2.5)];
+        for (input, expected) in cases {
+            assert_eq!(
+                Scanner::new(input).scan().unwrap(),
+                vec![Token::Number(expected)],
+                "{}",
+                input
+            );
+        }
+
+        let cases = [
+            ("1e", "Exponent has no digits", (0, 2)),
+            ("1e+", "Exponent has no digits", (0, 2)),
+            ("3E- 1", "Exponent has no digits", (0, 2)),
+            ("1 + 2e * 3", "Exponent has no digits", (4, 6)),
+            ("1e+5e2", "Number has more than one exponent", (0, 5)),
+            ("1e5e", "Number has more than one exponent", (0, 4)),
+        ];
+        for (input, message, expected) in cases {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert_eq!(err.message(), message, "{}", input);
+            let span = err.span().unwrap();
+            assert_eq!((span.start, span.end), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let input = "1 and 0 or 1";
+        let expected = vec![
+            Token::Number(1.0),
+            Token::And,
+            Token::Number(0.0),
+            Token::Or,
+            Token::Number(1.0),
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_assignment() {
+        let input = "radius = 5";
+        let expected = vec![
+            Token::Identifier(String::from("radius")),
+            Token::Equal,
+            Token::Number(5.0),
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_is_identifier() {
+        assert!(is_identifier("radius"));
+        assert!(is_identifier("cal_2"));
+        assert!(!is_identifier("pi"));
+        assert!(!is_identifier("and"));
+        assert!(!is_identifier("euler"));
+        assert!(!is_identifier("2x"));
+        assert!(!is_identifier("x y"));
+        assert!(!is_identifier("$x"));
+        assert!(!is_identifier(""));
+    }
+
+    #[test]
+    fn test_is_variable() {
+        assert!(is_variable("$health"));
+        assert!(is_variable("$0"));
+        assert!(!is_variable("$"));
+        assert!(!is_variable("$x-1"));
+        assert!(!is_variable(" $x"));
+        assert!(!is_variable("health"));
+    }
+
     #[test]
     fn test_variable() {
         let input = "$var";
@@ -480,6 +2310,19 @@ mod tests {
         assert_eq!(scanner.scan().unwrap(), expected);
     }
 
+    #[test]
+    fn test_ans() {
+        let input = "ans * answer";
+        let expected = vec![
+            Token::Variable(String::from("$ans")),
+            Token::Star,
+            Token::Identifier(String::from("answer")),
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+        assert!(!is_identifier("ans"));
+    }
+
     #[test]
     fn test_keyword() {
         let input = "sqrt";
@@ -488,6 +2331,507 @@ mod tests {
         assert_eq!(scanner.scan().unwrap(), expected);
     }
 
+    #[test]
+    fn test_reciprocal_hyperbolic_keywords() {
+        let input = "sech csch coth asech acsch acoth";
+        let expected = vec![
+            Token::Keyword(Word::Sech),
+            Token::Keyword(Word::Csch),
+            Token::Keyword(Word::Coth),
+            Token::Keyword(Word::Asech),
+            Token::Keyword(Word::Acsch),
+            Token::Keyword(Word::Acoth),
+        ];
+        let scanner = Scanner::new(input);
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_word_names_round_trip() {
+        for word in Word::ALL {
+            let scanner = Scanner::new(word.name()).physical_constants(true);
+            assert_eq!(scanner.scan().unwrap(), vec![Token::Keyword(word.clone())]);
+        }
+    }
+
+    #[test]
+    fn test_word_apply_arity() {
+        for word in Word::ALL.iter().filter(|w| **w != Word::Rand) {
+            let arity = word.arity();
+            for count in 0..=arity.max().unwrap_or(arity.min() + 2) + 1 {
+                // One is in the domain of every word, including those that require integers
+                let args = vec![1.0; count];
+                assert_eq!(
+                    word.apply(&args, false).is_ok(),
+                    arity.accepts(count),
+                    "{:?} with {} argument(s)",
+                    word,
+                    count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_arity() {
+        let cases = [
+            (Arity::Exactly(1), "1", vec![1]),
+            (Arity::Between(1, 2), "1 to 2", vec![1, 2]),
+            (Arity::AtLeast(2), "at least 2", vec![2, 3, 4]),
+            (Arity::OneOf(&[0, 2]), "0 or 2", vec![0, 2]),
+        ];
+        for (arity, text, accepted) in cases {
+            assert_eq!(arity.to_string(), text);
+            let counts: Vec<usize> = (0..5).filter(|count| arity.accepts(*count)).collect();
+            assert_eq!(counts, accepted, "{}", text);
+            assert_eq!(arity.min(), accepted[0]);
+        }
+        assert_eq!(Arity::AtLeast(2).max(), None);
+        assert_eq!(Arity::OneOf(&[0, 2]).max(), Some(2));
+        assert_eq!(Word::Rand.arity(), Arity::OneOf(&[0, 2]));
+    }
+
+    #[test]
+    fn test_word_apply_strict() {
+        assert_eq!(Word::Acosh.apply(&[1.0], true).unwrap(), 0.0);
+        assert!(Word::Acosh.apply(&[0.5], true).is_err());
+        assert!(Word::Acosh.apply(&[0.5], false).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_log_sum_exp() {
+        let expected = 1000.0 + std::f64::consts::LN_2;
+        assert_eq!(
+            Word::Logaddexp.apply(&[1000.0, 1000.0], false).unwrap(),
+            expected
+        );
+        assert_eq!(
+            Word::Logsumexp.apply(&[1000.0, 1000.0], false).unwrap(),
+            expected
+        );
+
+        let result = Word::Logaddexp.apply(&[1000.0, 0.0], false).unwrap();
+        assert_eq!(result, 1000.0);
+        let result = Word::Logaddexp.apply(&[1.0, 2.0], false).unwrap();
+        assert!((result - (1.0f64.exp() + 2.0f64.exp()).ln()).abs() < 1e-12);
+        let result = Word::Logsumexp
+            .apply(&[-1000.0, -1001.0, -1002.0], false)
+            .unwrap();
+        let expected = -1000.0 + (1.0 + (-1.0f64).exp() + (-2.0f64).exp()).ln();
+        assert!((result - expected).abs() < 1e-12);
+
+        assert_eq!(Word::Logsumexp.apply(&[42.0], false).unwrap(), 42.0);
+        assert_eq!(
+            Word::Logaddexp
+                .apply(&[f64::NEG_INFINITY, f64::NEG_INFINITY], false)
+                .unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            Word::Logsumexp.apply(&[f64::INFINITY, 1.0], false).unwrap(),
+            f64::INFINITY
+        );
+        assert!(Word::Logsumexp.apply(&[], false).is_err());
+    }
+
+    #[test]
+    fn test_degree_trig() {
+        let half_sqrt_3 = 3f64.sqrt() / 2.0;
+        let exact = [
+            (Word::Sind, 0.0, 0.0),
+            (Word::Sind, 30.0, 0.5),
+            (Word::Sind, 90.0, 1.0),
+            (Word::Sind, 150.0, 0.5),
+            (Word::Sind, 180.0, 0.0),
+            (Word::Sind, 210.0, -0.5),
+            (Word::Sind, 270.0, -1.0),
+            (Word::Sind, 360.0, 0.0),
+            (Word::Sind, -30.0, -0.5),
+            (Word::Sind, 45.0, std::f64::consts::FRAC_1_SQRT_2),
+            (Word::Cosd, 0.0, 1.0),
+            (Word::Cosd, 60.0, 0.5),
+            (Word::Cosd, 90.0, 0.0),
+            (Word::Cosd, 120.0, -0.5),
+            (Word::Cosd, 180.0, -1.0),
+            (Word::Cosd, 270.0, 0.0),
+            (Word::Cosd, 720.0 + 60.0, 0.5),
+            (Word::Tand, 0.0, 0.0),
+            (Word::Tand, 45.0, 1.0),
+            (Word::Tand, 135.0, -1.0),
+            (Word::Tand, -45.0, -1.0),
+            (Word::Tand, 180.0, 0.0),
+            (Word::Asind, 0.5, 30.0),
+            (Word::Asind, -0.5, -30.0),
+            (Word::Asind, 1.0, 90.0),
+            (Word::Acosd, 0.5, 60.0),
+            (Word::Acosd, -0.5, 120.0),
+            (Word::Acosd, -1.0, 180.0),
+            (Word::Acosd, half_sqrt_3, 30.0),
+            (Word::Atand, 1.0, 45.0),
+            (Word::Atand, 3f64.sqrt(), 60.0),
+            (Word::Atand, f64::INFINITY, 90.0),
+        ];
+        for (word, x, expected) in exact {
+            let result = word.apply(&[x], false).unwrap();
+            assert_eq!(result, expected, "{}({})", word.name(), x);
+            assert!(!(result == 0.0 && result.is_sign_negative()));
+        }
+
+        let close = [
+            (Word::Sind, 60.0, half_sqrt_3),
+            (Word::Cosd, 30.0, half_sqrt_3),
+            (Word::Tand, 30.0, 1.0 / 3f64.sqrt()),
+            (Word::Tand, 60.0, 3f64.sqrt()),
+            (Word::Sind, 1.0, 1f64.to_radians().sin()),
+            (Word::Cosd, 1e6 + 0.5, 280.5f64.to_radians().cos()),
+            (Word::Asind, 0.3, 0.3f64.asin().to_degrees()),
+        ];
+        for (word, x, expected) in close {
+            let result = word.apply(&[x], false).unwrap();
+            assert!(
+                (result - expected).abs() < 1e-15,
+                "{}({}) = {}",
+                word.name(),
+                x,
+                result
+            );
+        }
+
+        let atan2d = |y: f64, x: f64| Word::Atan2d.apply(&[y, x], false).unwrap();
+        assert_eq!(atan2d(1.0, 1.0), 45.0);
+        assert_eq!(atan2d(1.0, -1.0), 135.0);
+        assert_eq!(atan2d(-1.0, 0.0), -90.0);
+        assert_eq!(atan2d(1.0, 3f64.sqrt()), 30.0);
+        assert!(Word::Sind.apply(&[f64::INFINITY], false).unwrap().is_nan());
+        assert!(Word::Asind.apply(&[2.0], true).is_err());
+    }
+
+    #[test]
+    fn test_fact() {
+        let fact = |n: f64| Word::Fact.apply(&[n], false);
+        assert_eq!(fact(0.0).unwrap(), 1.0);
+        assert_eq!(fact(10.0).unwrap(), 3_628_800.0);
+        assert_eq!(fact(170.0).unwrap(), math::factorial(170.0).unwrap());
+        assert!(fact(170.0).unwrap().is_finite());
+        assert_eq!(fact(171.0).unwrap(), f64::INFINITY);
+        for n in [2.5, -1.0, f64::NAN, f64::INFINITY] {
+            let err = fact(n).unwrap_err();
+            assert_eq!(err.message(), "Factorial requires a non-negative integer");
+        }
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        let apply = |word: Word, a: f64, b: f64| word.apply(&[a, b], false);
+        let cases = [
+            (12.0, 18.0, 6.0, 36.0),
+            (4.0, 6.0, 2.0, 12.0),
+            (0.0, 5.0, 5.0, 0.0),
+            (5.0, 0.0, 5.0, 0.0),
+            (0.0, 0.0, 0.0, 0.0),
+            (1.0, 1.0, 1.0, 1.0),
+            (17.0, 17.0, 17.0, 17.0),
+            (
+                1_000_000_007.0,
+                998_244_353.0,
+                1.0,
+                998_244_359_987_710_471.0,
+            ),
+            (
+                math::MAX_EXACT_INTEGER,
+                2f64.powi(20),
+                2f64.powi(20),
+                math::MAX_EXACT_INTEGER,
+            ),
+        ];
+        for (a, b, gcd, lcm) in cases {
+            assert_eq!(apply(Word::Gcd, a, b).unwrap(), gcd, "gcd({}, {})", a, b);
+            assert_eq!(apply(Word::Lcm, a, b).unwrap(), lcm, "lcm({}, {})", a, b);
+        }
+
+        let large_prime = 9_007_199_254_740_881.0;
+        assert_eq!(
+            apply(Word::Lcm, large_prime, large_prime - 2.0).unwrap(),
+            f64::INFINITY
+        );
+
+        for (a, b) in [
+            (2.5, 5.0),
+            (5.0, -1.0),
+            (f64::NAN, 1.0),
+            (2f64.powi(54), 1.0),
+        ] {
+            for word in [Word::Gcd, Word::Lcm] {
+                let err = apply(word.clone(), a, b).unwrap_err();
+                assert_eq!(
+                    err.message(),
+                    format!("{} requires non-negative integers up to 2^53", word.name())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign() {
+        let sign = |x: f64| Word::Sign.apply(&[x], false).unwrap();
+        assert_eq!(sign(2.5), 1.0);
+        assert_eq!(sign(-0.1), -1.0);
+        assert_eq!(sign(f64::INFINITY), 1.0);
+        assert_eq!(sign(f64::NEG_INFINITY), -1.0);
+        assert_eq!(sign(0.0).to_bits(), 0.0f64.to_bits());
+        assert_eq!(sign(-0.0).to_bits(), 0.0f64.to_bits());
+        assert!(sign(f64::NAN).is_nan());
+
+        let copysign = |a: f64, b: f64| Word::Copysign.apply(&[a, b], false).unwrap();
+        assert_eq!(copysign(3.0, -1.0), -3.0);
+        assert_eq!(copysign(-3.0, 2.0), 3.0);
+        assert_eq!(copysign(3.0, -0.0), -3.0);
+        assert_eq!(copysign(0.0, -1.0).to_bits(), (-0.0f64).to_bits());
+        assert_eq!(copysign(f64::INFINITY, -1.0), f64::NEG_INFINITY);
+        assert!(copysign(f64::NAN, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_clamp() {
+        let clamp = |x: f64, lo: f64, hi: f64| Word::Clamp.apply(&[x, lo, hi], false);
+        assert_eq!(clamp(5.0, 0.0, 10.0).unwrap(), 5.0);
+        assert_eq!(clamp(-5.0, 0.0, 10.0).unwrap(), 0.0);
+        assert_eq!(clamp(15.0, 0.0, 10.0).unwrap(), 10.0);
+        assert_eq!(clamp(f64::INFINITY, 0.0, 10.0).unwrap(), 10.0);
+        assert_eq!(clamp(3.0, 2.0, 2.0).unwrap(), 2.0);
+        assert!(clamp(f64::NAN, 0.0, 1.0).unwrap().is_nan());
+        assert!(clamp(0.5, f64::NAN, 1.0).unwrap().is_nan());
+        assert_eq!(
+            clamp(1.0, 2.0, 0.0).unwrap_err().message(),
+            "clamp(x, lo, hi) requires lo <= hi"
+        );
+        assert_eq!(
+            Word::Clamp.apply(&[1.0, 2.0], false).unwrap_err().message(),
+            "clamp expects 3 argument(s), got 2"
+        );
+    }
+
+    #[test]
+    fn test_round_digits() {
+        let round = |args: &[f64]| Word::Round.apply(args, false);
+        let cases = [
+            (vec![2.5], 3.0),
+            (vec![-2.5], -3.0),
+            (vec![1.23456, 2.0], 1.23),
+            (vec![1.23456, 0.0], 1.0),
+            (vec![-1.23456, 3.0], -1.235),
+            (vec![0.125, 2.0], 0.13),
+            (vec![2.675, 2.0], 2.68),
+            (vec![1.005, 2.0], 1.01),
+            (vec![0.6, 0.0], 1.0),
+            (vec![0.4, 0.0], 0.0),
+            (vec![9.995, 2.0], 10.0),
+            (vec![999.5, 0.0], 1000.0),
+            (vec![1234.0, -2.0], 1200.0),
+            (vec![1250.0, -2.0], 1300.0),
+            (vec![-1234.0, -1.0], -1230.0),
+            (vec![9999.0, -2.0], 10000.0),
+            (vec![1234.0, -4.0], 0.0),
+            (vec![5678.0, -4.0], 10000.0),
+            (vec![5.0, -400.0], 0.0),
+            (vec![1e300, 20.0], 1e300),
+            (vec![1.5e300, -300.0], 2e300),
+            (vec![0.1, 400.0], 0.1),
+            (vec![1e-320, 2.0], 0.0),
+            (vec![0.0, 2.0], 0.0),
+            (vec![f64::INFINITY, 2.0], f64::INFINITY),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(round(&args).unwrap(), expected, "round({:?})", args);
+        }
+        assert!(round(&[-0.4, 0.0]).unwrap().is_sign_negative());
+        assert!(round(&[f64::NAN, 2.0]).unwrap().is_nan());
+        for digits in [0.5, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                round(&[1.0, digits]).unwrap_err().message(),
+                "round(x, digits) requires an integer number of digits"
+            );
+        }
+        assert!(round(&[1.0, 2.0, 3.0]).is_err());
+    }
+
+    #[test]
+    fn test_reciprocal_trig() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, FRAC_PI_6};
+
+        let apply = |word: Word, x: f64| word.apply(&[x], false).unwrap();
+        let cases = [
+            (Word::Sec, 0.0, 1.0),
+            (Word::Sec, FRAC_PI_3, 2.0),
+            (Word::Sec, PI, -1.0),
+            (Word::Csc, FRAC_PI_2, 1.0),
+            (Word::Csc, FRAC_PI_6, 2.0),
+            (Word::Csc, -FRAC_PI_2, -1.0),
+            (Word::Cot, FRAC_PI_4, 1.0),
+            (Word::Cot, FRAC_PI_2, 0.0),
+            (Word::Cot, FRAC_PI_6, 3f64.sqrt()),
+            (Word::Asec, 1.0, 0.0),
+            (Word::Asec, 2.0, FRAC_PI_3),
+            (Word::Asec, -1.0, PI),
+            (Word::Acsc, 1.0, FRAC_PI_2),
+            (Word::Acsc, 2.0, FRAC_PI_6),
+            (Word::Acsc, -2.0, -FRAC_PI_6),
+            (Word::Acot, 1.0, FRAC_PI_4),
+            (Word::Acot, 3f64.sqrt(), FRAC_PI_6),
+            (Word::Acot, f64::INFINITY, 0.0),
+            (Word::Acot, 0.0, FRAC_PI_2),
+        ];
+        for (word, x, expected) in cases {
+            let result = apply(word.clone(), x);
+            assert!(
+                (result - expected).abs() < 1e-15,
+                "{}({}) = {}",
+                word.name(),
+                x,
+                result
+            );
+        }
+
+        // Near the singularities, results grow without bound
+        assert_eq!(apply(Word::Csc, 0.0), f64::INFINITY);
+        assert_eq!(apply(Word::Csc, -0.0), f64::NEG_INFINITY);
+        assert_eq!(apply(Word::Cot, 0.0), f64::INFINITY);
+        assert!(apply(Word::Csc, 1e-10) > 9.9e9);
+        assert!(apply(Word::Cot, -1e-10) < -9.9e9);
+        assert!(apply(Word::Sec, FRAC_PI_2).abs() > 1e15);
+        assert!(apply(Word::Csc, PI).abs() > 1e15);
+
+        assert!(apply(Word::Asec, 0.5).is_nan());
+        assert!(apply(Word::Acsc, -0.5).is_nan());
+        for (word, x) in [
+            (Word::Csc, 0.0),
+            (Word::Cot, 0.0),
+            (Word::Asec, 0.5),
+            (Word::Acsc, 0.5),
+        ] {
+            let err = word.apply(&[x], true).unwrap_err();
+            assert!(err.message().starts_with("Domain error"), "{}", word.name());
+        }
+        assert_eq!(Word::Sec.apply(&[1.0], true).unwrap(), 1f64.cos().recip());
+    }
+
+    #[test]
+    fn test_rand_apply() {
+        assert_eq!(
+            Word::Rand.apply(&[], false).unwrap_err().message(),
+            "rand can only be evaluated by a calculator"
+        );
+        assert_eq!(
+            Word::Rand.apply(&[5.0], false).unwrap_err().message(),
+            "rand expects 0 or 2 argument(s), got 1"
+        );
+    }
+
+    #[test]
+    fn test_ncr_npr() {
+        let ncr = |n: f64, k: f64| Word::Ncr.apply(&[n, k], false);
+        let npr = |n: f64, k: f64| Word::Npr.apply(&[n, k], false);
+        let cases = [
+            (5.0, 2.0, 10.0, 20.0),
+            (52.0, 5.0, 2_598_960.0, 311_875_200.0),
+            (1000.0, 3.0, 166_167_000.0, 997_002_000.0),
+            (7.0, 0.0, 1.0, 1.0),
+            (7.0, 7.0, 1.0, 5040.0),
+            (0.0, 0.0, 1.0, 1.0),
+            (40.0, 20.0, 137_846_528_820.0, 3.353_670_967_863_570_6e29),
+        ];
+        for (n, k, combinations, permutations) in cases {
+            assert_eq!(ncr(n, k).unwrap(), combinations, "ncr({}, {})", n, k);
+            assert_eq!(npr(n, k).unwrap(), permutations, "npr({}, {})", n, k);
+        }
+
+        // The result is symmetric and does not overflow before it must
+        assert_eq!(ncr(60.0, 30.0).unwrap(), 118_264_581_564_861_424.0);
+        assert_eq!(ncr(1000.0, 997.0).unwrap(), 166_167_000.0);
+        assert_eq!(
+            ncr(math::MAX_EXACT_INTEGER, 1.0).unwrap(),
+            math::MAX_EXACT_INTEGER
+        );
+        assert_eq!(
+            ncr(math::MAX_EXACT_INTEGER, math::MAX_EXACT_INTEGER - 1.0).unwrap(),
+            math::MAX_EXACT_INTEGER
+        );
+        let large = ncr(1028.0, 514.0).unwrap();
+        assert!(large.is_finite() && large > 7e307, "{}", large);
+        assert_eq!(ncr(1100.0, 550.0).unwrap(), f64::INFINITY);
+        assert_eq!(npr(200.0, 200.0).unwrap(), f64::INFINITY);
+        assert_eq!(
+            npr(math::MAX_EXACT_INTEGER, math::MAX_EXACT_INTEGER).unwrap(),
+            f64::INFINITY
+        );
+
+        for word in [Word::Ncr, Word::Npr] {
+            let err = word.apply(&[3.0, 5.0], false).unwrap_err();
+            assert_eq!(
+                err.message(),
+                format!("{}(n, k) requires k <= n", word.name())
+            );
+            for (n, k) in [(5.0, -1.0), (5.5, 2.0), (5.0, f64::NAN)] {
+                assert!(
+                    word.apply(&[n, k], false).is_err(),
+                    "{}({}, {})",
+                    word.name(),
+                    n,
+                    k
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_avg() {
+        let avg = |args: &[f64]| Word::Avg.apply(args, false).unwrap();
+        assert_eq!(avg(&[42.0]), 42.0);
+        assert_eq!(avg(&[1.0, 2.0]), 1.5);
+        assert_eq!(avg(&[1.0, 2.0, 3.0, 4.0]), 2.5);
+        assert_eq!(avg(&[-1.0, 1.0]), 0.0);
+
+        // Naive summation of 0.1 drifts away from 0.1
+        let tenths = vec![0.1; 1000];
+        assert_ne!(tenths.iter().sum::<f64>() / 1000.0, 0.1);
+        assert_eq!(avg(&tenths), 0.1);
+        // Large values cancel without losing the small ones
+        assert_eq!(avg(&[1e100, 1.0, -1e100, 3.0]), 1.0);
+
+        assert_eq!(avg(&[f64::MAX, f64::MAX]), f64::MAX);
+        assert_eq!(avg(&[f64::MAX, -f64::MAX, 4.0]), 4.0 / 3.0);
+        assert_eq!(avg(&[f64::INFINITY, 1.0]), f64::INFINITY);
+        assert!(avg(&[f64::INFINITY, f64::NEG_INFINITY]).is_nan());
+        assert!(avg(&[f64::NAN, 1.0]).is_nan());
+        assert_eq!(
+            Word::Avg.apply(&[], false).unwrap_err().message(),
+            "avg expects at least 1 argument(s), got 0"
+        );
+    }
+
+    #[test]
+    fn test_sum_prod() {
+        let sum = |args: &[f64]| Word::Sum.apply(args, false).unwrap();
+        let prod = |args: &[f64]| Word::Prod.apply(args, false).unwrap();
+        assert_eq!(sum(&[1.0, 2.0, 3.0, 4.0, 5.0]), 15.0);
+        assert_eq!(prod(&[2.0, 3.0, 4.0]), 24.0);
+        assert_eq!(sum(&[42.0]), 42.0);
+        assert_eq!(prod(&[42.0]), 42.0);
+        assert_eq!(prod(&[5.0, 0.0, f64::MAX]), 0.0);
+
+        assert_eq!(sum(&[0.1; 10]), 1.0);
+        assert_eq!(sum(&[1e100, 1.0, -1e100]), 1.0);
+        assert_eq!(sum(&[f64::MAX, f64::MAX, -f64::MAX]), f64::INFINITY);
+        assert_eq!(sum(&[f64::INFINITY, 1.0]), f64::INFINITY);
+        assert!(sum(&[f64::INFINITY, f64::NEG_INFINITY]).is_nan());
+        assert!(sum(&[1.0, f64::NAN]).is_nan());
+        assert_eq!(prod(&[f64::MAX, 2.0]), f64::INFINITY);
+
+        for word in [Word::Sum, Word::Prod] {
+            assert!(word.apply(&[], false).is_err(), "{}", word.name());
+        }
+    }
+
     #[test]
     fn test_keyword_with_args() {
         let input = "pow(2, 3)";
@@ -502,4 +2846,322 @@ mod tests {
         let scanner = Scanner::new(input);
         assert_eq!(scanner.scan().unwrap(), expected);
     }
+
+    #[test]
+    fn test_dump() {
+        let cases = [
+            (Token::Number(1.5), "NUMBER 1.5"),
+            (Token::Number(1e300), "NUMBER 1e300"),
+            (Token::Number(f64::INFINITY), "NUMBER inf"),
+            (Token::Caret, "CARET"),
+            (Token::Bang, "BANG"),
+            (Token::Degree, "DEGREE"),
+            (Token::Variable("$x".to_string()), "VARIABLE $x"),
+            (Token::Identifier("cal".to_string()), "IDENTIFIER cal"),
+            (Token::Keyword(Word::Log10), "KEYWORD log10"),
+        ];
+        for (token, expected) in cases {
+            assert_eq!(token.dump(), expected);
+        }
+    }
+
+    #[test]
+    fn test_err_long_literal() {
+        let input = "1".repeat(10_000);
+        let err = Scanner::new(&input).scan().unwrap_err();
+        assert_eq!(err.message(), "Number literal is too long");
+        assert_eq!(err.span().unwrap().end, DEFAULT_MAX_LITERAL_LENGTH + 1);
+
+        for input in ["12345", "1_2.5", "1e+10", "0x1_F", "0b101", "1\u{200B}234"] {
+            let scan = |max| Scanner::new(input).max_literal_length(max).scan();
+            assert!(scan(4).is_err(), "{}", input);
+            assert!(scan(5).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_loses_precision() {
+        let check = |literal: &str| loses_precision(literal, literal.parse().unwrap());
+        assert!(!check("0.1"));
+        assert!(!check("1.500"));
+        assert!(!check("1000"));
+        assert!(!check("0.0025e10"));
+        assert!(!check("9007199254740993e-16"));
+        assert!(check("12345678901234567890"));
+        assert!(check("0.12345678901234567890123"));
+        assert!(!check("1e400"));
+        assert!(!loses_precision("1.5d-3", 1.5e-3));
+    }
+
+    #[test]
+    fn test_booleans() {
+        let input = "true or false";
+        let scanner = Scanner::new(input);
+        let expected = vec![
+            Token::Keyword(Word::True),
+            Token::Or,
+            Token::Keyword(Word::False),
+        ];
+        assert_eq!(scanner.scan().unwrap(), expected);
+        assert!(!is_identifier("true"));
+        assert!(is_identifier("truest"));
+    }
+
+    #[test]
+    fn test_d_exponent() {
+        let cases = [("1d3", 1e3), ("2.5D-7", 2.5e-7), ("1.5d+2", 150.0)];
+        for (input, expected) in cases {
+            let scanner = Scanner::new(input).d_exponent(true);
+            assert_eq!(scanner.scan().unwrap(), vec![Token::Number(expected)]);
+        }
+
+        let scanner = Scanner::new("2deg(180)").d_exponent(true);
+        let tokens = scanner.scan().unwrap();
+        assert_eq!(tokens[1], Token::Keyword(Word::Deg));
+
+        let scanner = Scanner::new("1d3");
+        assert_ne!(scanner.scan().unwrap(), vec![Token::Number(1e3)]);
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        let input = "SQRT(PI) And Log10 OR Radius";
+        let expected = vec![
+            Token::Keyword(Word::Sqrt),
+            Token::LParen,
+            Token::Keyword(Word::Pi),
+            Token::RParen,
+            Token::And,
+            Token::Keyword(Word::Log10),
+            Token::Or,
+            Token::Identifier(String::from("Radius")),
+        ];
+        let scanner = Scanner::new(input).case_insensitive_keywords(true);
+        assert_eq!(scanner.scan().unwrap(), expected);
+
+        let scanner = Scanner::new("PI");
+        assert_eq!(
+            scanner.scan().unwrap(),
+            vec![Token::Identifier(String::from("PI"))]
+        );
+    }
+
+    #[test]
+    fn test_err_d_exponent() {
+        for input in ["1d", "1d+", "2.5D - 1"] {
+            let err = Scanner::new(input).d_exponent(true).scan().unwrap_err();
+            assert_eq!(err.message(), "Exponent has no digits", "{}", input);
+            assert_eq!(err.position(), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_hex() {
+        let cases = [
+            ("0xFF", 255.0),
+            ("0Xff", 255.0),
+            ("0x0", 0.0),
+            ("0xdead_beef", 3735928559.0),
+            ("0x1F_FFFF_FFFF_FFFF", 9007199254740991.0),
+            ("0", 0.0),
+            ("0.5", 0.5),
+        ];
+        for (input, expected) in cases {
+            let scanner = Scanner::new(input);
+            assert_eq!(
+                scanner.scan().unwrap(),
+                vec![Token::Number(expected)],
+                "{}",
+                input
+            );
+        }
+
+        let scanner = Scanner::new("0xFF+1");
+        let expected = vec![Token::Number(255.0), Token::Plus, Token::Number(1.0)];
+        assert_eq!(scanner.scan().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_err_hex() {
+        for input in ["0x", "0xG", "0x_", "0x + 1"] {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert_eq!(
+                err.message(),
+                "Missing digits in hexadecimal literal",
+                "{}",
+                input
+            );
+            assert_eq!(err.position(), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_loses_precision_hex() {
+        assert!(!loses_precision("0xFF", 255.0));
+        assert!(!loses_precision("0x20000000000000", 9007199254740992.0));
+        assert!(loses_precision("0x20000000000001", 9007199254740992.0));
+        assert!(loses_precision(&format!("0x{}", "F".repeat(40)), 1.0));
+    }
+
+    #[test]
+    fn test_binary() {
+        let cases = [
+            ("0b1010", 10.0),
+            ("0B1", 1.0),
+            ("0b0000_0001", 1.0),
+            (
+                "0b11111111111111111111111111111111111111111111111111111",
+                9007199254740991.0,
+            ),
+        ];
+        for (input, expected) in cases {
+            let scanner = Scanner::new(input);
+            assert_eq!(
+                scanner.scan().unwrap(),
+                vec![Token::Number(expected)],
+                "{}",
+                input
+            );
+        }
+        assert!(!loses_precision(
+            "0b11111111111111111111111111111111111111111111111111111",
+            9007199254740991.0
+        ));
+    }
+
+    #[test]
+    fn test_err_binary() {
+        let err = Scanner::new("0b").scan().unwrap_err();
+        assert_eq!(err.message(), "Missing digits in binary literal");
+
+        let err = Scanner::new("0b102").scan().unwrap_err();
+        assert_eq!(err.message(), "Invalid digit in binary literal: 2");
+        assert_eq!(err.span().map(|span| (span.start, span.end)), Some((0, 5)));
+    }
+
+    #[test]
+    fn test_leading_decimal_point() {
+        let input = "1+.5 * .25";
+        let expected = vec![
+            Token::Number(1.0),
+            Token::Plus,
+            Token::Number(0.5),
+            Token::Star,
+            Token::Number(0.25),
+        ];
+        assert_eq!(Scanner::new(input).scan().unwrap(), expected);
+        assert_eq!(
+            Scanner::new(".5e1").scan().unwrap(),
+            vec![Token::Number(5.0)]
+        );
+
+        for input in [".", ".e5", "..5", "1 + . 5"] {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert!(err.is_scan_error(), "{}", input);
+            assert_eq!(err.message(), "Decimal point must be followed by a digit");
+        }
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let cases = [
+            ("1_000_000", 1_000_000.0),
+            ("1.234_567", 1.234_567),
+            ("1_0.2_5e1_0", 10.25e10),
+            ("0xFF_FF", 65535.0),
+            ("0b1010_1010", 170.0),
+            ("1000000", 1_000_000.0),
+        ];
+        for (input, expected) in cases {
+            let scanner = Scanner::new(input);
+            assert_eq!(
+                scanner.scan().unwrap(),
+                vec![Token::Number(expected)],
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_err_digit_separators() {
+        for input in ["1_", "1__0", "1_.5", "1._5", "1_e5", "2 * 3_ + 1"] {
+            let err = Scanner::new(input).scan().unwrap_err();
+            assert!(err.is_scan_error(), "{}", input);
+            assert_eq!(err.message(), "Digit separator must be between digits");
+        }
+        assert!(Scanner::new("___").scan().is_err());
+    }
+
+    #[test]
+    fn test_octal() {
+        let cases = [
+            ("0o755", 493.0),
+            ("0O17", 15.0),
+            ("0o7_7", 63.0),
+            ("0o0", 0.0),
+        ];
+        for (input, expected) in cases {
+            let scanner = Scanner::new(input);
+            assert_eq!(
+                scanner.scan().unwrap(),
+                vec![Token::Number(expected)],
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_err_octal() {
+        let err = Scanner::new("0o").scan().unwrap_err();
+        assert_eq!(err.message(), "Missing digits in octal literal");
+
+        let err = Scanner::new("0o8").scan().unwrap_err();
+        assert!(err.is_scan_error());
+        assert_eq!(err.message(), "Invalid digit in octal literal: 8");
+        assert_eq!(err.span().map(|span| (span.start, span.end)), Some((0, 3)));
+    }
+
+    #[test]
+    fn test_comments() {
+        let cases = [
+            (
+                "2 * pi * $r  # circumference",
+                vec![
+                    Token::Number(2.0),
+                    Token::Star,
+                    Token::Keyword(Word::Pi),
+                    Token::Star,
+                    Token::Variable("$r".to_string()),
+                ],
+            ),
+            (
+                "1 / 2 // half",
+                vec![Token::Number(1.0), Token::Slash, Token::Number(2.0)],
+            ),
+            ("1#2", vec![Token::Number(1.0)]),
+            ("1 # one\n", vec![Token::Number(1.0)]),
+            ("1 // one\r\n", vec![Token::Number(1.0)]),
+            ("# only a comment", vec![]),
+            ("//", vec![]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Scanner::new(input).scan().unwrap(), expected, "{}", input);
+        }
+
+        // The comment ends at the newline, and the next line is scanned as usual
+        assert_eq!(
+            Scanner::new("1 # one\n+ 2").scan().unwrap(),
+            vec![Token::Number(1.0), Token::Plus, Token::Number(2.0)]
+        );
+    }
+
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("a = 2 # two; three"), "a = 2 ");
+        assert_eq!(strip_comment("1 / 2 // half # note"), "1 / 2 ");
+        assert_eq!(strip_comment("1 / 2"), "1 / 2");
+        assert_eq!(strip_comment("# all"), "");
+    }
 }
@@ -0,0 +1,25 @@
+//! Module for the stored state of a calculator, which can be saved and restored.
+
+use std::collections::BTreeMap;
+
+/// The variables and results stored in a [`Calculator`](crate::Calculator).
+///
+/// Created with [`Calculator::export_state`](crate::Calculator::export_state)
+/// and restored with [`Calculator::import_state`](crate::Calculator::import_state).
+/// With the `serde` feature, the state can be serialized, such as to persist a session between launches.
+/// Infinities and NaN are written as the strings `"inf"`, `"-inf"`, and `"nan"`,
+/// since formats like JSON cannot represent them as numbers.
+///
+/// Registered tables, registered functions, and settings are not part of the state.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalcState {
+    /// Named variables, including `$ans`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::values::map"))]
+    pub(crate) variables: BTreeMap<String, f64>,
+    /// Numbered results by index, where removed results are `None`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::values::list"))]
+    pub(crate) results: Vec<Option<f64>>,
+    /// The index of the next numbered result.
+    pub(crate) next_result: usize,
+}
@@ -0,0 +1,146 @@
+//! Module for linear interpolation tables that can be called like functions.
+
+use crate::calc_error::{CalcError, ErrorKind};
+use crate::fingerprint::Fingerprint;
+
+/// A table of breakpoints used for linear interpolation.
+///
+/// Breakpoints are stored sorted by their x value so that lookups can use a binary search.
+/// Outside the range of the breakpoints, the table either clamps to the nearest
+/// endpoint or extrapolates from the nearest segment.
+#[derive(Clone, Debug)]
+pub struct Table {
+    points: Vec<(f64, f64)>,
+    extrapolate: bool,
+}
+impl Table {
+    /// Create a new table from a slice of `(x, y)` breakpoints.
+    ///
+    /// If `extrapolate` is true, lookups outside the range of the breakpoints extend the first or last segment.
+    /// Otherwise, they are clamped to the first or last y value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CalcError`] if there are fewer than two breakpoints,
+    /// if any value is not finite, or if the x values are not strictly increasing.
+    pub fn new(points: &[(f64, f64)], extrapolate: bool) -> Result<Self, CalcError> {
+        if points.len() < 2 {
+            return Err(
+                CalcError::new("Table requires at least two breakpoints", None)
+                    .with_kind(ErrorKind::Configuration),
+            );
+        }
+        if points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err(CalcError::new("Table breakpoints must be finite", None)
+                .with_kind(ErrorKind::Configuration));
+        }
+        for pair in points.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                return Err(CalcError::new("Table has duplicate x values", None)
+                    .with_kind(ErrorKind::Configuration));
+            }
+            if pair[0].0 > pair[1].0 {
+                return Err(CalcError::new("Table x values must be sorted", None)
+                    .with_kind(ErrorKind::Configuration));
+            }
+        }
+
+        Ok(Self {
+            points: points.to_vec(),
+            extrapolate,
+        })
+    }
+
+    /// Write the breakpoints and extrapolation mode of the table to a fingerprint.
+    pub fn write_fingerprint(&self, fingerprint: &mut Fingerprint) {
+        fingerprint.write_u64(self.points.len() as u64);
+        for &(x, y) in &self.points {
+            fingerprint.write_f64(x);
+            fingerprint.write_f64(y);
+        }
+        fingerprint.write_bool(self.extrapolate);
+    }
+
+    /// Look up the interpolated y value for an x value.
+    ///
+    /// Uses a binary search to find the segment containing `x`, then linearly interpolates within it.
+    pub fn lookup(&self, x: f64) -> f64 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if !self.extrapolate {
+            if x <= first.0 {
+                return first.1;
+            }
+            if x >= last.0 {
+                return last.1;
+            }
+        }
+
+        // Index of the first breakpoint with an x value greater than `x`,
+        // restricted so that the segment is always within the table.
+        let i = self
+            .points
+            .partition_point(|(px, _)| *px <= x)
+            .clamp(1, self.points.len() - 1);
+        let (x0, y0) = self.points[i - 1];
+        let (x1, y1) = self.points[i];
+        y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [(f64, f64); 3] = [(0.0, 1.0), (10.0, 1.5), (20.0, 2.2)];
+
+    #[test]
+    fn test_lookup_interior() {
+        let table = Table::new(&POINTS, false).unwrap();
+        assert_eq!(table.lookup(5.0), 1.25);
+        assert!((table.lookup(15.0) - 1.85).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lookup_breakpoints() {
+        let table = Table::new(&POINTS, false).unwrap();
+        assert_eq!(table.lookup(0.0), 1.0);
+        assert_eq!(table.lookup(10.0), 1.5);
+        assert_eq!(table.lookup(20.0), 2.2);
+    }
+
+    #[test]
+    fn test_lookup_clamp() {
+        let table = Table::new(&POINTS, false).unwrap();
+        assert_eq!(table.lookup(-10.0), 1.0);
+        assert_eq!(table.lookup(30.0), 2.2);
+    }
+
+    #[test]
+    fn test_lookup_extrapolate() {
+        let table = Table::new(&POINTS, true).unwrap();
+        assert_eq!(table.lookup(-10.0), 0.5);
+        assert!((table.lookup(30.0) - 2.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_new_too_few_points() {
+        assert!(Table::new(&[(0.0, 1.0)], false).is_err());
+    }
+
+    #[test]
+    fn test_new_unsorted() {
+        assert!(Table::new(&[(0.0, 1.0), (-1.0, 2.0)], false).is_err());
+    }
+
+    #[test]
+    fn test_new_duplicate() {
+        assert!(Table::new(&[(0.0, 1.0), (0.0, 2.0)], false).is_err());
+    }
+
+    #[test]
+    fn test_new_not_finite() {
+        assert!(Table::new(&[(0.0, 1.0), (f64::NAN, 2.0)], false).is_err());
+    }
+}